@@ -0,0 +1,3 @@
+ALTER TABLE tracks
+    DROP COLUMN IF EXISTS loudness_lufs,
+    DROP COLUMN IF EXISTS true_peak_dbtp;
@@ -0,0 +1,3 @@
+ALTER TABLE tracks
+    ADD COLUMN loudness_lufs REAL,
+    ADD COLUMN true_peak_dbtp REAL;
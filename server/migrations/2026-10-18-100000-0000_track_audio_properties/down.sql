@@ -0,0 +1,7 @@
+ALTER TABLE tracks
+    DROP COLUMN IF EXISTS duration_ms,
+    DROP COLUMN IF EXISTS sample_rate,
+    DROP COLUMN IF EXISTS channels,
+    DROP COLUMN IF EXISTS bitrate_kbps,
+    DROP COLUMN IF EXISTS is_vbr,
+    DROP COLUMN IF EXISTS file_size;
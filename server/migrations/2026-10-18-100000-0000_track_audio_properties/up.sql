@@ -0,0 +1,7 @@
+ALTER TABLE tracks
+    ADD COLUMN duration_ms INTEGER,
+    ADD COLUMN sample_rate INTEGER,
+    ADD COLUMN channels INTEGER,
+    ADD COLUMN bitrate_kbps INTEGER,
+    ADD COLUMN is_vbr BOOLEAN,
+    ADD COLUMN file_size BIGINT;
@@ -0,0 +1,4 @@
+DROP SEQUENCE IF EXISTS local_song_id_seq;
+
+ALTER TABLE tracks
+    DROP COLUMN IF EXISTS source;
@@ -0,0 +1,5 @@
+ALTER TABLE tracks
+    ADD COLUMN source VARCHAR NOT NULL DEFAULT 'vk';
+
+-- Uploaded files have no provider id, so they get song ids from here
+CREATE SEQUENCE local_song_id_seq;
@@ -0,0 +1,3 @@
+ALTER TABLE tracks
+    DROP CONSTRAINT tracks_source_song_id_owner_id_key,
+    ADD CONSTRAINT tracks_song_id_owner_id_key UNIQUE (song_id, owner_id);
@@ -0,0 +1,4 @@
+-- The same ids may exist in different providers
+ALTER TABLE tracks
+    DROP CONSTRAINT tracks_song_id_owner_id_key,
+    ADD CONSTRAINT tracks_source_song_id_owner_id_key UNIQUE (source, song_id, owner_id);
@@ -0,0 +1,14 @@
+CREATE TABLE api_keys (
+  id SERIAL PRIMARY KEY,
+  user_id INT REFERENCES users (id) ON DELETE CASCADE NOT NULL,
+  name VARCHAR NOT NULL,
+  -- Public part of the key, used to find it
+  prefix VARCHAR NOT NULL UNIQUE,
+  -- SHA-256 of the secret part, the key itself is only shown once
+  secret_hash VARCHAR NOT NULL,
+  scopes TEXT[] NOT NULL,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  last_used_at TIMESTAMP
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
@@ -0,0 +1,2 @@
+-- Language of the emails the user gets
+ALTER TABLE users ADD COLUMN locale VARCHAR NOT NULL DEFAULT 'ru';
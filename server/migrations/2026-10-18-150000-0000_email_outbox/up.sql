@@ -0,0 +1,23 @@
+CREATE TYPE email_status AS ENUM ('pending', 'sent', 'failed');
+
+-- Emails waiting to be sent by the outbox worker, kept as a delivery log
+CREATE TABLE email_outbox (
+  id SERIAL PRIMARY KEY,
+  recipient VARCHAR NOT NULL,
+  -- Name of the template it was rendered from
+  template VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  -- Bodies are cleared once the email is sent or given up on, they may hold codes
+  text_body TEXT NOT NULL,
+  html_body TEXT NOT NULL,
+  status email_status DEFAULT 'pending' NOT NULL,
+  attempts INT DEFAULT 0 NOT NULL,
+  last_error TEXT,
+  next_attempt_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  sent_at TIMESTAMP,
+  -- Emails with a code are useless once it expired, they are not sent after this
+  expires_at TIMESTAMP
+);
+
+CREATE INDEX email_outbox_pending_idx ON email_outbox (next_attempt_at) WHERE status = 'pending';
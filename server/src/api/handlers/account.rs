@@ -0,0 +1,273 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, Extension, State},
+    http::HeaderMap,
+    middleware,
+};
+use tower_cookies::Cookies;
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
+
+use crate::{
+    AppState,
+    api::handlers::{
+        AuthData, RateLimitKey, RouteRateLimit, auth_required, client_ip, rate_limited,
+    },
+    dto::{
+        request::account::{
+            ChangeLocaleRequest, ChangePasswordRequest, ChangeUsernameRequest,
+            DeleteAccountRequest, ResendEmailChangeOTPRequest, StartEmailChangeRequest,
+            VerifyEmailChangeRequest,
+        },
+        response::{
+            ApiResponse, ApiResult, ValidatedJSON,
+            account::{EmailChangeResponse, MeResponse},
+        },
+    },
+};
+
+pub fn account_router(app_state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_me, delete_account))
+        .routes(routes!(change_username))
+        .routes(routes!(change_password))
+        .routes(routes!(change_locale))
+        .routes(
+            routes!(start_email_change).layer(middleware::from_fn_with_state(
+                RouteRateLimit {
+                    state: app_state.clone(),
+                    route: "email_change_start",
+                    key: RateLimitKey::User,
+                    limit: app_state.config.rate_limit_config.email_change_start,
+                },
+                rate_limited,
+            )),
+        )
+        .routes(routes!(resend_email_change_otp))
+        .routes(routes!(verify_email_change))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth_required,
+        ))
+        .with_state(app_state)
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "Account",
+    responses(
+        (status = 200, description = "The signed in user", body = MeResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn get_me(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+) -> ApiResult<MeResponse> {
+    let res = state
+        .services
+        .account_service
+        .get_me(session.user_id)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/username",
+    tag = "Account",
+    request_body = ChangeUsernameRequest,
+    responses(
+        (status = 200, description = "Username changed", body = MeResponse),
+        (status = 400, description = "Invalid or taken username"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn change_username(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ValidatedJSON(payload): ValidatedJSON<ChangeUsernameRequest>,
+) -> ApiResult<MeResponse> {
+    session.require_user()?;
+    let res = state
+        .services
+        .account_service
+        .change_username(session.user_id, payload)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+/// Language of the emails the user gets.
+#[utoipa::path(
+    put,
+    path = "/locale",
+    tag = "Account",
+    request_body = ChangeLocaleRequest,
+    responses(
+        (status = 200, description = "Locale changed", body = MeResponse),
+        (status = 400, description = "Unsupported locale"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn change_locale(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ValidatedJSON(payload): ValidatedJSON<ChangeLocaleRequest>,
+) -> ApiResult<MeResponse> {
+    session.require_user()?;
+    let res = state
+        .services
+        .account_service
+        .change_locale(session.user_id, payload)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+/// Other sessions are signed out.
+#[utoipa::path(
+    put,
+    path = "/password",
+    tag = "Account",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 400, description = "Bad Request"),
+        (status = 401, description = "Wrong current password")
+    )
+)]
+async fn change_password(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ValidatedJSON(payload): ValidatedJSON<ChangePasswordRequest>,
+) -> ApiResult<()> {
+    session.require_user()?;
+    state
+        .services
+        .account_service
+        .change_password(session.user_id, payload, session.session_id.as_deref())
+        .await?;
+    Ok(ApiResponse::OK(None))
+}
+
+/// Sends a code to the new address and a notice to the current one.
+#[utoipa::path(
+    post,
+    path = "/email",
+    tag = "Account",
+    request_body = StartEmailChangeRequest,
+    responses(
+        (status = 200, description = "OTP sent to the new address", body = EmailChangeResponse),
+        (status = 400, description = "Invalid or taken email"),
+        (status = 401, description = "Wrong password"),
+        (status = 429, description = "Too Many Requests")
+    )
+)]
+async fn start_email_change(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ValidatedJSON(payload): ValidatedJSON<StartEmailChangeRequest>,
+) -> ApiResult<EmailChangeResponse> {
+    session.require_user()?;
+    let res = state
+        .services
+        .email_change_service
+        .start_email_change(session.user_id, payload)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/email/resend",
+    tag = "Account",
+    request_body = ResendEmailChangeOTPRequest,
+    responses(
+        (status = 200, description = "OTP resent", body = EmailChangeResponse),
+        (status = 401, description = "OTP expired or wrong token"),
+        (status = 429, description = "OTP was sent recently")
+    )
+)]
+async fn resend_email_change_otp(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ValidatedJSON(payload): ValidatedJSON<ResendEmailChangeOTPRequest>,
+) -> ApiResult<EmailChangeResponse> {
+    session.require_user()?;
+    let res = state
+        .services
+        .email_change_service
+        .resend_otp(session.user_id, payload)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+/// Changes the email if the code is right. Other sessions are signed out.
+#[utoipa::path(
+    post,
+    path = "/email/verify",
+    tag = "Account",
+    request_body = VerifyEmailChangeRequest,
+    responses(
+        (status = 200, description = "Email changed"),
+        (status = 400, description = "Email was taken in the meantime"),
+        (status = 401, description = "Wrong or expired OTP"),
+        (status = 429, description = "Too many failed attempts")
+    )
+)]
+async fn verify_email_change(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    ValidatedJSON(payload): ValidatedJSON<VerifyEmailChangeRequest>,
+) -> ApiResult<()> {
+    session.require_user()?;
+    state
+        .services
+        .email_change_service
+        .verify_otp(
+            session.user_id,
+            payload,
+            client_ip(&headers, peer, &state.config.trusted_proxies),
+            session.session_id.as_deref(),
+        )
+        .await?;
+    Ok(ApiResponse::OK(None))
+}
+
+/// Deletes the user with their tracks, likes and API keys.
+#[utoipa::path(
+    delete,
+    path = "",
+    tag = "Account",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 200, description = "Account deleted"),
+        (status = 401, description = "Wrong password")
+    )
+)]
+async fn delete_account(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    cookies: Cookies,
+    ValidatedJSON(payload): ValidatedJSON<DeleteAccountRequest>,
+) -> ApiResult<()> {
+    session.require_user()?;
+    state
+        .services
+        .account_service
+        .delete_account(session.user_id, payload)
+        .await?;
+    if let Some(cookie) = cookies.get("x-authenticated") {
+        state
+            .services
+            .auth_service
+            .delete_session(cookies.clone(), cookie.value().to_string())
+            .await?;
+    }
+    Ok(ApiResponse::OK(None))
+}
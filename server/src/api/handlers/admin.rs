@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::{extract::State, middleware};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    api::handlers::{admin_required, auth_required},
+    dto::{
+        request::station::UpdateStationPolicyRequest,
+        response::{
+            raido::PlaybackStateResponse, station::StationPolicyResponse, ApiResponse, ApiResult,
+            ValidatedJSON,
+        },
+    },
+    AppState,
+};
+
+pub fn admin_router(app_state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(pause_radio))
+        .routes(routes!(resume_radio))
+        .routes(routes!(
+            get_station_policy,
+            update_station_policy,
+            reset_station_policy
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            admin_required,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth_required,
+        ))
+        .with_state(app_state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/radio/pause",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Broadcast paused, listeners receive silence", body = PlaybackStateResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin role required")
+    )
+)]
+async fn pause_radio(State(state): State<Arc<AppState>>) -> ApiResult<PlaybackStateResponse> {
+    let playback = state.services.radio_service.set_paused(true).await;
+    Ok(ApiResponse::OK(Some(PlaybackStateResponse {
+        paused: playback.paused,
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/radio/resume",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Broadcast resumed where it was paused", body = PlaybackStateResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin role required")
+    )
+)]
+async fn resume_radio(State(state): State<Arc<AppState>>) -> ApiResult<PlaybackStateResponse> {
+    let playback = state.services.radio_service.set_paused(false).await;
+    Ok(ApiResponse::OK(Some(PlaybackStateResponse {
+        paused: playback.paused,
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/station/policy",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Station policy in effect", body = StationPolicyResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin role required")
+    )
+)]
+async fn get_station_policy(
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<StationPolicyResponse> {
+    let policy = state.services.station_policy_service.get_policy().await?;
+    Ok(ApiResponse::OK(Some(policy.into())))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/station/policy",
+    tag = "Admin",
+    request_body = UpdateStationPolicyRequest,
+    responses(
+        (status = 200, description = "Policy updated, applies to the next requests", body = StationPolicyResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin role required")
+    )
+)]
+async fn update_station_policy(
+    State(state): State<Arc<AppState>>,
+    ValidatedJSON(payload): ValidatedJSON<UpdateStationPolicyRequest>,
+) -> ApiResult<StationPolicyResponse> {
+    let policy = state
+        .services
+        .station_policy_service
+        .update_policy(payload)
+        .await?;
+    Ok(ApiResponse::OK(Some(policy.into())))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/station/policy",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Policy reset to the configured defaults", body = StationPolicyResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin role required")
+    )
+)]
+async fn reset_station_policy(
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<StationPolicyResponse> {
+    let policy = state.services.station_policy_service.reset_policy().await?;
+    Ok(ApiResponse::OK(Some(policy.into())))
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, State},
+    middleware,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState,
+    api::handlers::{AuthData, auth_required},
+    dto::{
+        request::api_key::CreateApiKeyRequest,
+        response::{
+            ApiResponse, ApiResult, ValidatedJSON,
+            api_key::{ApiKeyResponse, CreatedApiKeyResponse},
+        },
+    },
+};
+
+pub fn api_keys_router(app_state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(list_keys, create_key))
+        .routes(routes!(revoke_key))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth_required,
+        ))
+        .with_state(app_state)
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "API keys",
+    responses(
+        (status = 200, description = "Keys of the user", body = Vec<ApiKeyResponse>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn list_keys(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+) -> ApiResult<Vec<ApiKeyResponse>> {
+    session.require_user()?;
+    let res = state
+        .services
+        .api_key_service
+        .list_keys(session.user_id)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+/// The returned key is shown only once.
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "API keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "Key created", body = CreatedApiKeyResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn create_key(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    ValidatedJSON(payload): ValidatedJSON<CreateApiKeyRequest>,
+) -> ApiResult<CreatedApiKeyResponse> {
+    session.require_user()?;
+    let res = state
+        .services
+        .api_key_service
+        .create_key(session.user_id, payload)
+        .await?;
+    Ok(ApiResponse::CREATED(Some(res)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "API keys",
+    params(("id" = i32, Path, description = "Key id")),
+    responses(
+        (status = 200, description = "Key revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Key not found")
+    )
+)]
+async fn revoke_key(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    Path(id): Path<i32>,
+) -> ApiResult<()> {
+    session.require_user()?;
+    state
+        .services
+        .api_key_service
+        .revoke_key(session.user_id, id)
+        .await?;
+    Ok(ApiResponse::OK(None))
+}
@@ -1,20 +1,47 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::{body::Body, extract::State, http::Request};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Request},
+    middleware,
+};
 use tower_cookies::Cookies;
-use utoipa_axum::{router::OpenApiRouter, routes};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
 
 use crate::{
     AppState,
+    api::handlers::{RateLimitKey, RouteRateLimit, rate_limited, session_meta},
     dto::{
-        request::auth::auth::SignInRequest,
-        response::{ApiResponse, ApiResult, ValidatedJSON},
+        request::auth::auth::{RefreshTokenRequest, SignInRequest},
+        response::{ApiResponse, ApiResult, ValidatedJSON, auth::token::TokenResponse},
     },
 };
 
 pub fn auth_router(app_state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(sign_in))
+        .routes(routes!(sign_in).layer(middleware::from_fn_with_state(
+            RouteRateLimit {
+                state: app_state.clone(),
+                route: "sign_in",
+                key: RateLimitKey::Ip,
+                limit: app_state.config.rate_limit_config.sign_in,
+            },
+            rate_limited,
+        )))
+        .routes(routes!(token).layer(middleware::from_fn_with_state(
+            RouteRateLimit {
+                state: app_state.clone(),
+                route: "sign_in",
+                key: RateLimitKey::Ip,
+                limit: app_state.config.rate_limit_config.sign_in,
+            },
+            rate_limited,
+        )))
+        .routes(routes!(refresh))
         .routes(routes!(logout))
         .routes(routes!(check_session))
         .with_state(app_state)
@@ -28,22 +55,86 @@ pub fn auth_router(app_state: Arc<AppState>) -> OpenApiRouter {
         responses(
             (status = 200, description = "Success auth"),
             (status = 400, description = "Bad Request"),
+            (status = 429, description = "Too many failed attempts or the account is locked"),
             (status = 500, description = "Internal Server Error")
         )
     )]
 async fn sign_in(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     cookies: Cookies,
     ValidatedJSON(payload): ValidatedJSON<SignInRequest>,
 ) -> ApiResult<()> {
     state
         .services
         .auth_service
-        .sign_in(payload, cookies)
+        .sign_in(
+            payload,
+            session_meta(&headers, peer, &state.config.trusted_proxies),
+            cookies,
+        )
         .await?;
     Ok(ApiResponse::OK(None))
 }
 
+/// Sign in without cookies, for bots and ComputerCraft computers.
+#[utoipa::path(
+        post,
+        path = "/token",
+        tag = "Auth",
+        request_body = SignInRequest,
+        responses(
+            (status = 200, description = "Access and refresh tokens", body = TokenResponse),
+            (status = 401, description = "Wrong credentials"),
+            (status = 429, description = "Too many failed attempts or the account is locked"),
+            (status = 500, description = "Internal Server Error")
+        )
+    )]
+async fn token(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    ValidatedJSON(payload): ValidatedJSON<SignInRequest>,
+) -> ApiResult<TokenResponse> {
+    let meta = session_meta(&headers, peer, &state.config.trusted_proxies);
+    let user = state
+        .services
+        .auth_service
+        .authenticate(&payload, meta.ip)
+        .await?;
+    let res = state
+        .services
+        .token_auth_service
+        .issue_tokens(user.id, &meta)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+/// Exchange a refresh token for a new pair. Every refresh token works once.
+#[utoipa::path(
+        post,
+        path = "/refresh",
+        tag = "Auth",
+        request_body = RefreshTokenRequest,
+        responses(
+            (status = 200, description = "New access and refresh tokens", body = TokenResponse),
+            (status = 401, description = "Expired, revoked or reused refresh token"),
+            (status = 500, description = "Internal Server Error")
+        )
+    )]
+async fn refresh(
+    State(state): State<Arc<AppState>>,
+    ValidatedJSON(payload): ValidatedJSON<RefreshTokenRequest>,
+) -> ApiResult<TokenResponse> {
+    let res = state
+        .services
+        .token_auth_service
+        .refresh(&payload.refresh_token)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
 #[utoipa::path(
         post,
         path = "/logout",
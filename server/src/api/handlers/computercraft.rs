@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
+};
+use serde::Deserialize;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    error::app_error::AppResult,
+    service::{
+        cc_client::{render_client, render_installer, ClientOptions},
+        playback::DfpwmChannel,
+    },
+    AppState,
+};
+
+pub fn computercraft_router(app_state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_client))
+        .routes(routes!(get_installer))
+        .with_state(app_state)
+}
+
+#[derive(Deserialize)]
+struct ClientParams {
+    channel: Option<DfpwmChannel>,
+    volume: Option<f32>,
+    speaker: Option<String>,
+    startup: Option<bool>,
+}
+
+impl ClientParams {
+    fn options(&self) -> AppResult<ClientOptions> {
+        ClientOptions::new(self.channel, self.volume, self.speaker.clone())
+    }
+}
+
+/// Base URL the station is reachable at, as seen by the CC computer.
+fn station_url(state: &AppState, headers: &HeaderMap) -> String {
+    if let Some(url) = &state.config.public_url {
+        return url.clone();
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let scheme = header("X-Forwarded-Proto").unwrap_or("http");
+    let host = header("Host")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("localhost:{}", state.config.app_port));
+    format!("{}://{}", scheme, host)
+}
+
+fn lua_response(body: String) -> Response {
+    Response::builder()
+        .status(200)
+        .header("Content-Type", "text/x-lua; charset=utf-8")
+        .header("Cache-Control", "no-cache")
+        .body(body.into())
+        .unwrap()
+}
+
+#[utoipa::path(
+    get,
+    path = "/radio.lua",
+    tag = "ComputerCraft",
+    params(
+        ("channel" = Option<DfpwmChannel>, Query, description = "Speaker channel: left, right or mono (default)"),
+        ("volume" = Option<f32>, Query, description = "Speaker volume from 0 to 3, default 1"),
+        ("speaker" = Option<String>, Query, description = "Peripheral name of the speaker, every attached speaker by default")
+    ),
+    responses(
+        (status = 200, description = "Lua client program", content_type = "text/x-lua"),
+        (status = 400, description = "Bad Request")
+    )
+)]
+async fn get_client(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ClientParams>,
+) -> AppResult<Response> {
+    let options = params.options()?;
+    let lua = render_client(&station_url(&state, &headers), &options)?;
+    Ok(lua_response(lua))
+}
+
+#[utoipa::path(
+    get,
+    path = "/install",
+    tag = "ComputerCraft",
+    params(
+        ("channel" = Option<DfpwmChannel>, Query, description = "Speaker channel: left, right or mono (default)"),
+        ("volume" = Option<f32>, Query, description = "Speaker volume from 0 to 3, default 1"),
+        ("speaker" = Option<String>, Query, description = "Peripheral name of the speaker, every attached speaker by default"),
+        ("startup" = Option<bool>, Query, description = "Start the client automatically when the computer boots")
+    ),
+    responses(
+        (status = 200, description = "Installer, run with `wget run <url>`", content_type = "text/x-lua"),
+        (status = 400, description = "Bad Request")
+    )
+)]
+async fn get_installer(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ClientParams>,
+) -> AppResult<Response> {
+    let options = params.options()?;
+    let lua = render_installer(
+        &station_url(&state, &headers),
+        &options,
+        params.startup.unwrap_or(false),
+    )?;
+    Ok(lua_response(lua))
+}
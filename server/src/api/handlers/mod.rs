@@ -1,19 +1,112 @@
 use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::response::IntoResponse;
 use axum::{extract::State, http::Request, middleware::Next, response::Response};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
+use crate::config::{proxy::TrustedProxies, rate_limit::RateLimit};
 use crate::error::app_error::AppResult;
+use crate::service::api_key_service::ApiScope;
+use crate::service::auth::auth_service::SessionMeta;
 use crate::{AppState, error::app_error::AppError};
 
+pub mod account;
+pub mod admin;
+pub mod api_keys;
 pub mod auth;
+pub mod computercraft;
 pub mod radio;
+pub mod restore;
+pub mod sessions;
 pub mod sign_up;
 pub mod track;
 pub mod websocket;
 
+/// Address of the client. Forwarded headers are only believed when `peer` is
+/// a trusted proxy.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trusted: &TrustedProxies) -> IpAddr {
+    let peer = peer.ip().to_canonical();
+    if !trusted.contains(peer) {
+        return peer;
+    }
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    // Every proxy appends the address it was connected from, so entries left
+    // of the first untrusted one from the right may have been made up by the
+    // client
+    if let Some(forwarded) = header("X-Forwarded-For") {
+        let mut client = peer;
+        for entry in forwarded.rsplit(',') {
+            let Ok(ip) = entry.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip.to_canonical();
+            if !trusted.contains(client) {
+                break;
+            }
+        }
+        return client;
+    }
+    header("X-Real-IP")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// Client address and user agent to record on a new session.
+pub fn session_meta(
+    headers: &HeaderMap,
+    peer: SocketAddr,
+    trusted: &TrustedProxies,
+) -> SessionMeta {
+    SessionMeta {
+        ip: client_ip(headers, peer, trusted),
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    }
+}
+
+/// Token of an `Authorization: Bearer <token>` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
 #[derive(Clone, Debug)]
 pub struct AuthData {
     pub user_id: i32,
+    /// Scopes of the API key the request was made with, `None` for users
+    /// signed in with a session or a Bearer token.
+    pub scopes: Option<Vec<ApiScope>>,
+    /// Session id or token family of the request, `None` for API keys.
+    pub session_id: Option<String>,
+}
+
+impl AuthData {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope))
+    }
+
+    /// Rejects API keys, for managing the account itself.
+    pub fn require_user(&self) -> AppResult<()> {
+        match self.scopes {
+            None => Ok(()),
+            Some(_) => Err(AppError::Forbidden(
+                "Not available with an API key".to_string(),
+                None,
+            )),
+        }
+    }
 }
 
 pub async fn auth_required(
@@ -21,6 +114,34 @@ pub async fn auth_required(
     mut req: Request<Body>,
     next: Next,
 ) -> AppResult<Response> {
+    if let Some(key) = req
+        .headers()
+        .get("X-Api-Key")
+        .and_then(|value| value.to_str().ok())
+    {
+        let (user_id, scopes) = state.services.api_key_service.authenticate(key).await?;
+        req.extensions_mut().insert(Arc::new(AuthData {
+            user_id,
+            scopes: Some(scopes),
+            session_id: None,
+        }));
+        return Ok(next.run(req).await);
+    }
+
+    if let Some(token) = bearer_token(req.headers()) {
+        let (user_id, family) = state
+            .services
+            .token_auth_service
+            .verify_access_token(token)
+            .await?;
+        req.extensions_mut().insert(Arc::new(AuthData {
+            user_id,
+            scopes: None,
+            session_id: Some(family),
+        }));
+        return Ok(next.run(req).await);
+    }
+
     let sid = state.services.auth_service.get_session_id_from_req(&req)?;
 
     let session_data = match state
@@ -40,7 +161,181 @@ pub async fn auth_required(
 
     req.extensions_mut().insert(Arc::new(AuthData {
         user_id: session_data.user_id,
+        scopes: None,
+        session_id: Some(session_data.session_id),
     }));
     let response = next.run(req).await;
     Ok(response)
 }
+
+/// Must be layered inside `auth_required`.
+pub async fn admin_required(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+    next: Next,
+) -> AppResult<Response> {
+    let auth_data = req
+        .extensions()
+        .get::<Arc<AuthData>>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string(), None))?;
+    auth_data.require_user()?;
+    state
+        .services
+        .auth_service
+        .require_admin(auth_data.user_id)
+        .await?;
+    Ok(next.run(req).await)
+}
+
+/// Route layer for what API keys need a scope for. Must be layered inside
+/// `auth_required`.
+pub async fn scope_required(
+    State(scope): State<ApiScope>,
+    req: Request<Body>,
+    next: Next,
+) -> AppResult<Response> {
+    let auth_data = req
+        .extensions()
+        .get::<Arc<AuthData>>()
+        .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string(), None))?;
+    if !auth_data.allows(scope) {
+        return Err(AppError::Forbidden(
+            format!("The API key lacks the {} scope", scope.as_str()),
+            None,
+        ));
+    }
+    Ok(next.run(req).await)
+}
+
+/// Whose requests share a rate limit bucket.
+#[derive(Clone, Copy, Debug)]
+pub enum RateLimitKey {
+    Ip,
+    /// Falls back to the IP when the route has no `AuthData`.
+    User,
+    /// Both the IP and the user buckets must have a token.
+    IpAndUser,
+}
+
+/// Rate limit of one route, the state of `rate_limited`.
+#[derive(Clone)]
+pub struct RouteRateLimit {
+    pub state: Arc<AppState>,
+    pub route: &'static str,
+    pub key: RateLimitKey,
+    pub limit: Option<RateLimit>,
+}
+
+/// Must be layered inside `auth_required` for user keys to apply.
+pub async fn rate_limited(
+    State(rule): State<RouteRateLimit>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(limit) = rule.limit else {
+        return next.run(req).await;
+    };
+
+    let ip = format!(
+        "ip:{}",
+        client_ip(req.headers(), peer, &rule.state.config.trusted_proxies)
+    );
+    let user = req
+        .extensions()
+        .get::<Arc<AuthData>>()
+        .map(|auth| format!("user:{}", auth.user_id));
+    let subjects = match (rule.key, user) {
+        (RateLimitKey::Ip, _) | (_, None) => vec![ip],
+        (RateLimitKey::User, Some(user)) => vec![user],
+        (RateLimitKey::IpAndUser, Some(user)) => vec![ip, user],
+    };
+
+    let mut retry_after = None;
+    for subject in &subjects {
+        match rule
+            .state
+            .services
+            .rate_limiter
+            .acquire(rule.route, subject, &limit)
+            .await
+        {
+            Ok(Some(wait)) => retry_after = retry_after.max(Some(wait)),
+            Ok(None) => {}
+            // Don't lock everyone out when Redis is unavailable
+            Err(e) => eprintln!("[rate-limit] {}: {}", rule.route, e),
+        }
+    }
+
+    let Some(retry_after) = retry_after else {
+        return next.run(req).await;
+    };
+    let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response = AppError::TooManyRequests(
+        format!("Too many requests, retry in {} seconds", seconds),
+        None,
+    )
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_map(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn nginx() -> SocketAddr {
+        "172.18.0.3:40000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_ignores_headers_from_untrusted_peers() {
+        let trusted = TrustedProxies::parse("172.16.0.0/12").unwrap();
+        let headers = header_map(&[("X-Forwarded-For", "1.1.1.1"), ("X-Real-IP", "1.1.1.1")]);
+
+        let ip = client_ip(&headers, "203.0.113.7:5000".parse().unwrap(), &trusted);
+        assert_eq!(ip, "203.0.113.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_spoofed_forwarded_entries() {
+        let trusted = TrustedProxies::parse("172.16.0.0/12").unwrap();
+        // The client sent `X-Forwarded-For: 1.1.1.1` and nginx appended the
+        // address it was connected from
+        let headers = header_map(&[("X-Forwarded-For", "1.1.1.1, 203.0.113.7")]);
+
+        let ip = client_ip(&headers, nginx(), &trusted);
+        assert_eq!(ip, "203.0.113.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_client_ip_skips_trusted_hops() {
+        let trusted = TrustedProxies::parse("172.16.0.0/12,10.0.0.0/8").unwrap();
+        let headers = header_map(&[("X-Forwarded-For", "garbage, 203.0.113.7, 10.0.0.2")]);
+        assert_eq!(
+            client_ip(&headers, nginx(), &trusted),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+
+        let headers = header_map(&[("X-Real-IP", "203.0.113.8")]);
+        assert_eq!(
+            client_ip(&headers, nginx(), &trusted),
+            "203.0.113.8".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip(&HeaderMap::new(), nginx(), &trusted),
+            "172.18.0.3".parse::<IpAddr>().unwrap()
+        );
+    }
+}
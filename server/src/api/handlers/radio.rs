@@ -1,21 +1,29 @@
-use std::{convert::Infallible, sync::Arc};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
 
 use axum::{
     body::{Body, Bytes},
-    extract::State,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, HeaderValue},
     response::Response,
 };
-use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use serde::Deserialize;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 use crate::{
+    api::handlers::client_ip,
+    config::STATION_NAME,
     dto::response::{raido::GetCurrentTrackResponse, ApiResponse, ApiResult},
+    service::{
+        live_stream::LiveReceiver,
+        playback::{DfpwmChannel, DfpwmPacket, DFPWM_SAMPLE_RATE},
+    },
     AppState,
 };
 
 pub fn radio_router(app_state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(stream_radio))
+        .routes(routes!(stream_radio_dfpwm))
         .routes(routes!(get_current_track))
         .with_state(app_state)
 }
@@ -29,11 +37,16 @@ pub fn radio_router(app_state: Arc<AppState>) -> OpenApiRouter {
         (status = 503, description = "No tracks available yet")
     )
 )]
-async fn stream_radio(State(state): State<Arc<AppState>>) -> Response {
-    let receiver = state.services.radio_service.subscribe();
+async fn stream_radio(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    let ip = client_ip(&headers, peer, &state.config.trusted_proxies);
+    let label = format!("HTTP MP3 {}", ip);
+    let receiver = state.services.radio_service.subscribe(label);
 
-    let stream = BroadcastStream::new(receiver)
-        .filter_map(|result| result.ok().map(|bytes| Ok::<Bytes, Infallible>(bytes)));
+    let stream = live_body(receiver, Some);
 
     Response::builder()
         .status(200)
@@ -44,6 +57,79 @@ async fn stream_radio(State(state): State<Arc<AppState>>) -> Response {
         .unwrap()
 }
 
+/// Response body fed from a listener, skipping items `to_bytes` returns `None` for.
+fn live_body<T, F>(
+    receiver: LiveReceiver<T>,
+    to_bytes: F,
+) -> impl futures::Stream<Item = Result<Bytes, Infallible>>
+where
+    T: Clone + Send + 'static,
+    F: Fn(T) -> Option<Bytes> + Send + 'static,
+{
+    futures::stream::unfold(
+        (receiver, to_bytes),
+        |(mut receiver, to_bytes)| async move {
+            loop {
+                let item = receiver.recv().await?;
+                if let Some(bytes) = to_bytes(item) {
+                    return Some((Ok(bytes), (receiver, to_bytes)));
+                }
+            }
+        },
+    )
+}
+
+#[derive(Deserialize)]
+struct DfpwmStreamParams {
+    channel: Option<DfpwmChannel>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/stream.dfpwm",
+    tag = "Radio",
+    params(
+        ("channel" = Option<DfpwmChannel>, Query, description = "Speaker to stream for: left, right or mono (default)")
+    ),
+    responses(
+        (status = 200, description = "Live DFPWM stream (audio/dfpwm, 48 kHz, 1 bit per sample). \
+            `icy-name` carries the station name and `icy-title` the track playing at connection time",
+            content_type = "audio/dfpwm"),
+    )
+)]
+async fn stream_radio_dfpwm(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<DfpwmStreamParams>,
+) -> Response {
+    let channel = params.channel.unwrap_or_default();
+    let ip = client_ip(&headers, peer, &state.config.trusted_proxies);
+    let label = format!("HTTP DFPWM {}", ip);
+    let receiver = state.services.radio_service.subscribe_dfpwm(channel, label);
+    let title = state.services.radio_service.get_current_track().await.name;
+
+    let stream = live_body(receiver, |packet| match packet {
+        DfpwmPacket::Audio(chunk) => Some(chunk),
+        DfpwmPacket::TrackStart { .. } => None,
+    });
+
+    let mut response = Response::builder()
+        .status(200)
+        .header("Content-Type", "audio/dfpwm")
+        .header("Cache-Control", "no-cache, no-store")
+        .header("Transfer-Encoding", "chunked")
+        .header("icy-name", STATION_NAME)
+        .header("icy-br", (DFPWM_SAMPLE_RATE / 1000).to_string())
+        .header("icy-sr", DFPWM_SAMPLE_RATE.to_string())
+        .header("icy-pub", "0");
+    // Track names are UTF-8, which is allowed as opaque header bytes
+    if let Some(title) = title.and_then(|t| HeaderValue::from_bytes(t.as_bytes()).ok()) {
+        response = response.header("icy-title", title);
+    }
+    response.body(Body::from_stream(stream)).unwrap()
+}
+
 #[utoipa::path(
         get,
         path = "/current-track",
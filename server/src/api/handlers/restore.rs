@@ -0,0 +1,113 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    middleware,
+};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
+
+use crate::{
+    AppState,
+    api::handlers::{RateLimitKey, RouteRateLimit, client_ip, rate_limited},
+    dto::{
+        request::auth::restore::{
+            CompleteRestoreRequest, StartRestoreRequest, VerifyRestoreOTPRequest,
+        },
+        response::{ApiResponse, ApiResult, ValidatedJSON, auth::restore::StartRestoreResponse},
+    },
+};
+
+pub fn restore_router(app_state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(start).layer(middleware::from_fn_with_state(
+            RouteRateLimit {
+                state: app_state.clone(),
+                route: "restore_start",
+                key: RateLimitKey::Ip,
+                limit: app_state.config.rate_limit_config.restore_start,
+            },
+            rate_limited,
+        )))
+        .routes(routes!(verify))
+        .routes(routes!(complete))
+        .with_state(app_state)
+}
+
+#[utoipa::path(
+        post,
+        path = "/start",
+        tag = "Restore",
+        request_body = StartRestoreRequest,
+        responses(
+            (status = 200, description = "OTP sent successfully", body = StartRestoreResponse),
+            (status = 404, description = "User not found"),
+            (status = 500, description = "Internal Server Error")
+        )
+    )]
+async fn start(
+    State(state): State<Arc<AppState>>,
+    ValidatedJSON(payload): ValidatedJSON<StartRestoreRequest>,
+) -> ApiResult<StartRestoreResponse> {
+    let res = state
+        .services
+        .restore_service
+        .start_restore(payload)
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+#[utoipa::path(
+        post,
+        path = "/verify-otp",
+        tag = "Restore",
+        request_body = VerifyRestoreOTPRequest,
+        responses(
+            (status = 200, description = "OTP verified successfully"),
+            (status = 401, description = "Wrong or expired OTP"),
+            (status = 429, description = "Too many wrong codes"),
+            (status = 500, description = "Internal Server Error")
+        )
+    )]
+async fn verify(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    ValidatedJSON(payload): ValidatedJSON<VerifyRestoreOTPRequest>,
+) -> ApiResult<()> {
+    state
+        .services
+        .restore_service
+        .verify_otp(
+            payload,
+            client_ip(&headers, peer, &state.config.trusted_proxies),
+        )
+        .await?;
+    Ok(ApiResponse::OK(None))
+}
+
+#[utoipa::path(
+        post,
+        path = "/complete",
+        tag = "Restore",
+        request_body = CompleteRestoreRequest,
+        responses(
+            (status = 200, description = "Password changed"),
+            (status = 401, description = "OTP not verified"),
+            (status = 500, description = "Internal Server Error")
+        )
+    )]
+async fn complete(
+    State(state): State<Arc<AppState>>,
+    ValidatedJSON(payload): ValidatedJSON<CompleteRestoreRequest>,
+) -> ApiResult<()> {
+    state
+        .services
+        .restore_service
+        .complete_restore(payload)
+        .await?;
+    Ok(ApiResponse::OK(None))
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, State},
+    middleware,
+};
+use tower_cookies::Cookies;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState,
+    api::handlers::{AuthData, auth_required},
+    dto::response::{ApiResponse, ApiResult, auth::session::SessionResponse},
+};
+
+pub fn sessions_router(app_state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(list_sessions, revoke_all_sessions))
+        .routes(routes!(revoke_session))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth_required,
+        ))
+        .with_state(app_state)
+}
+
+/// Browser sessions and Bearer token families of the user.
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "Sessions",
+    responses(
+        (status = 200, description = "Active sessions", body = Vec<SessionResponse>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+) -> ApiResult<Vec<SessionResponse>> {
+    session.require_user()?;
+    let res = state
+        .services
+        .auth_service
+        .list_sessions(session.user_id, session.session_id.as_deref())
+        .await?;
+    Ok(ApiResponse::OK(Some(res)))
+}
+
+/// Log out everywhere, including this session.
+#[utoipa::path(
+    delete,
+    path = "",
+    tag = "Sessions",
+    responses(
+        (status = 200, description = "All sessions revoked"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn revoke_all_sessions(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    cookies: Cookies,
+) -> ApiResult<()> {
+    session.require_user()?;
+    state
+        .services
+        .auth_service
+        .revoke_all_sessions(session.user_id, None)
+        .await?;
+    if let Some(cookie) = cookies.get("x-authenticated") {
+        state
+            .services
+            .auth_service
+            .delete_session(cookies.clone(), cookie.value().to_string())
+            .await?;
+    }
+    Ok(ApiResponse::OK(None))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "Sessions",
+    params(("id" = String, Path, description = "Session id from the list")),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Session not found")
+    )
+)]
+async fn revoke_session(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    Path(id): Path<String>,
+) -> ApiResult<()> {
+    session.require_user()?;
+    state
+        .services
+        .auth_service
+        .revoke_session(session.user_id, &id)
+        .await?;
+    Ok(ApiResponse::OK(None))
+}
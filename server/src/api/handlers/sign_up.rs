@@ -1,10 +1,18 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::extract::State;
-use utoipa_axum::{router::OpenApiRouter, routes};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, header},
+    middleware,
+};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
 
 use crate::{
     AppState,
+    api::handlers::{RateLimitKey, RouteRateLimit, client_ip, rate_limited},
     dto::{
         request::auth::sign_up::{
             ResendOTPRequest, SignUpCompleteRequest, SignUpStartRequest, VerifyOTPRequest,
@@ -14,11 +22,20 @@ use crate::{
             auth::sign_up::{ResendOTPResponse, SignUpStartResponse},
         },
     },
+    service::email_template::Locale,
 };
 
 pub fn sign_up_router(app_state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(start))
+        .routes(routes!(start).layer(middleware::from_fn_with_state(
+            RouteRateLimit {
+                state: app_state.clone(),
+                route: "sign_up_start",
+                key: RateLimitKey::Ip,
+                limit: app_state.config.rate_limit_config.sign_up_start,
+            },
+            rate_limited,
+        )))
         .routes(routes!(verify))
         .routes(routes!(resend))
         .routes(routes!(complete))
@@ -38,12 +55,18 @@ pub fn sign_up_router(app_state: Arc<AppState>) -> OpenApiRouter {
     )]
 async fn start(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     ValidatedJSON(payload): ValidatedJSON<SignUpStartRequest>,
 ) -> ApiResult<SignUpStartResponse> {
+    let locale = Locale::from_accept_language(
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok()),
+    );
     let res = state
         .services
         .sign_up_service
-        .start_sign_up(payload)
+        .start_sign_up(payload, locale)
         .await?;
     Ok(ApiResponse::OK(Some(res)))
 }
@@ -56,14 +79,24 @@ async fn start(
         responses(
             (status = 200, description = "OTP verified successfully"),
             (status = 400, description = "Bad Request"),
+            (status = 429, description = "Too many wrong codes"),
             (status = 500, description = "Internal Server Error")
         )
     )]
 async fn verify(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     ValidatedJSON(payload): ValidatedJSON<VerifyOTPRequest>,
 ) -> ApiResult<()> {
-    state.services.sign_up_service.verify_otp(payload).await?;
+    state
+        .services
+        .sign_up_service
+        .verify_otp(
+            payload,
+            client_ip(&headers, peer, &state.config.trusted_proxies),
+        )
+        .await?;
     Ok(ApiResponse::OK(None))
 }
 
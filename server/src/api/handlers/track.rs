@@ -1,21 +1,69 @@
 use std::sync::Arc;
 
-use axum::extract::{Extension, Query, State};
+use axum::extract::{DefaultBodyLimit, Extension, Multipart, Query, State};
 use axum::middleware;
 use serde::Deserialize;
-use utoipa_axum::{router::OpenApiRouter, routes};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
 
 use crate::AppState;
-use crate::api::handlers::{AuthData, auth_required};
-use crate::dto::request::track::UserSelectTrackRequest;
-use crate::dto::response::track::SearchTrackResponse;
+use crate::api::handlers::{
+    AuthData, RateLimitKey, RouteRateLimit, auth_required, rate_limited, scope_required,
+};
+use crate::dto::request::track::{UploadTrackRequest, UserSelectTrackRequest};
+use crate::dto::response::track::{SearchTrackResponse, UploadTrackResponse};
 use crate::dto::response::{ApiResponse, ApiResult, ValidatedJSON};
 use crate::error::app_error::AppError;
+use crate::service::api_key_service::ApiScope;
+use crate::service::track_service::DEFAULT_SEARCH_COUNT;
 
 pub fn track_router(app_state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(search_track))
-        .routes(routes!(select_track))
+        .routes(
+            routes!(search_track)
+                .layer(middleware::from_fn_with_state(
+                    RouteRateLimit {
+                        state: app_state.clone(),
+                        route: "track_search",
+                        key: RateLimitKey::IpAndUser,
+                        limit: app_state.config.rate_limit_config.track_search,
+                    },
+                    rate_limited,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    ApiScope::QueueRead,
+                    scope_required,
+                )),
+        )
+        .routes(routes!(select_track).layer(middleware::from_fn_with_state(
+            ApiScope::QueueWrite,
+            scope_required,
+        )))
+        .merge(
+            OpenApiRouter::new()
+                .routes(
+                    routes!(upload_track)
+                        .layer(middleware::from_fn_with_state(
+                            RouteRateLimit {
+                                state: app_state.clone(),
+                                route: "track_upload",
+                                key: RateLimitKey::User,
+                                limit: app_state.config.rate_limit_config.track_upload,
+                            },
+                            rate_limited,
+                        ))
+                        .layer(middleware::from_fn_with_state(
+                            ApiScope::TrackUpload,
+                            scope_required,
+                        )),
+                )
+                // Only the upload takes a file, the other routes keep the default limit
+                .route_layer(DefaultBodyLimit::max(
+                    app_state.config.songs_config.max_upload_bytes,
+                )),
+        )
         .layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth_required,
@@ -26,6 +74,8 @@ pub fn track_router(app_state: Arc<AppState>) -> OpenApiRouter {
 #[derive(Deserialize)]
 struct SearchTrackParams {
     track_name: Option<String>,
+    offset: Option<u32>,
+    count: Option<u32>,
 }
 
 #[utoipa::path(
@@ -33,7 +83,9 @@ struct SearchTrackParams {
     path = "/search",
     tag = "Track",
     params(
-        ("track_name" = Option<String>, Query, description = "Track name to search")
+        ("track_name" = Option<String>, Query, description = "Track name to search"),
+        ("offset" = Option<u32>, Query, description = "Number of results to skip, 0 by default"),
+        ("count" = Option<u32>, Query, description = "Page size from 1 to 50, 5 by default")
     ),
     responses(
         (status = 200, description = "Search tracks", body = SearchTrackResponse),
@@ -57,7 +109,11 @@ async fn search_track(
     let res = state
         .services
         .track_service
-        .search_track(track_name)
+        .search_track(
+            track_name,
+            params.offset.unwrap_or(0),
+            params.count.unwrap_or(DEFAULT_SEARCH_COUNT),
+        )
         .await?;
 
     Ok(ApiResponse::OK(Some(res)))
@@ -87,3 +143,45 @@ async fn select_track(
         .await?;
     Ok(ApiResponse::OK(None))
 }
+
+#[utoipa::path(
+    post,
+    path = "/upload",
+    tag = "Track",
+    request_body(content = UploadTrackRequest, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Track uploaded and queued", body = UploadTrackResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 413, description = "File too large"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+async fn upload_track(
+    State(state): State<Arc<AppState>>,
+    Extension(session): Extension<Arc<AuthData>>,
+    mut multipart: Multipart,
+) -> ApiResult<UploadTrackResponse> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(e.body_text(), None))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = field.file_name().unwrap_or_default().to_string();
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(e.body_text(), None))?;
+
+        let res = state
+            .services
+            .track_service
+            .upload_track(session.user_id, &file_name, data)
+            .await?;
+        return Ok(ApiResponse::OK(Some(res)));
+    }
+
+    Err(AppError::BadRequest("Missing file field".to_string(), None))
+}
@@ -1,16 +1,24 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        ConnectInfo, Query, State, WebSocketUpgrade,
     },
+    http::HeaderMap,
     response::Response,
 };
 use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
-use crate::{dto::response::websocket::WebSocketMessage, AppState};
+use crate::{
+    api::handlers::client_ip,
+    dto::response::websocket::{CurrentTrackData, WebSocketMessage},
+    service::playback::{DfpwmChannel, DfpwmPacket},
+    AppState,
+};
 
 pub fn websocket_router(app_state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
@@ -39,17 +47,9 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let mut rx = state.services.radio_service.subscribe_events();
 
     // Send current state immediately
-    if let Ok(current_track) = state.services.radio_service.get_current_track_ws().await {
-        let msg = WebSocketMessage::CurrentTrack(current_track);
-        if let Ok(json) = serde_json::to_string(&msg) {
-            let _ = sender.send(Message::Text(json.into())).await;
-        }
-    }
-
-    if let Ok(playlist) = state.services.playlist_service.get_playlist_ws().await {
-        let msg = WebSocketMessage::Playlist(playlist);
-        if let Ok(json) = serde_json::to_string(&msg) {
-            let _ = sender.send(Message::Text(json.into())).await;
+    for msg in current_state(&state).await {
+        if let Some(text) = text_message(&msg) {
+            let _ = sender.send(text).await;
         }
     }
 
@@ -78,10 +78,57 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     };
 }
 
+/// Current track and playlist, sent to clients as soon as they connect.
+async fn current_state(state: &AppState) -> Vec<WebSocketMessage> {
+    let mut messages = Vec::new();
+    if let Ok(current_track) = state.services.radio_service.get_current_track_ws().await {
+        messages.push(WebSocketMessage::CurrentTrack(current_track));
+    }
+    if let Ok(playlist) = state.services.playlist_service.get_playlist_ws().await {
+        messages.push(WebSocketMessage::Playlist(playlist));
+    }
+    messages.push(WebSocketMessage::PlaybackState(
+        state.services.radio_service.get_playback_state_ws().await,
+    ));
+    messages
+}
+
+fn text_message(msg: &WebSocketMessage) -> Option<Message> {
+    serde_json::to_string(msg)
+        .ok()
+        .map(|json| Message::Text(json.into()))
+}
+
+/// Next radio event, or never if the client did not ask for metadata.
+async fn next_event(
+    events: &mut Option<broadcast::Receiver<WebSocketMessage>>,
+) -> Option<WebSocketMessage> {
+    let Some(events) = events else {
+        return std::future::pending().await;
+    };
+    loop {
+        match events.recv().await {
+            Ok(msg) => return Some(msg),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DfpwmStreamParams {
+    channel: Option<DfpwmChannel>,
+    metadata: Option<bool>,
+}
+
 #[utoipa::path(
     get,
     path = "/stream-dfpwm",
     tag = "WebSocket",
+    params(
+        ("channel" = Option<DfpwmChannel>, Query, description = "Speaker to stream for: left, right or mono (default)"),
+        ("metadata" = Option<bool>, Query, description = "Interleave `current_track`, `playlist`, `playback_state` and `track_start` JSON text frames with the audio")
+    ),
     responses(
         (status = 101, description = "WebSocket connection established for DFPWM audio streaming"),
     )
@@ -89,39 +136,71 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
 async fn websocket_stream_dfpwm_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<DfpwmStreamParams>,
 ) -> Response {
     println!("[WebSocket DFPWM] Connection attempt");
-    ws.on_upgrade(|socket| handle_dfpwm_stream(socket, state))
+    let channel = params.channel.unwrap_or_default();
+    let metadata = params.metadata.unwrap_or(false);
+    let ip = client_ip(&headers, peer, &state.config.trusted_proxies);
+    let label = format!("WebSocket DFPWM {}", ip);
+    ws.on_upgrade(move |socket| handle_dfpwm_stream(socket, state, channel, metadata, label))
 }
 
-async fn handle_dfpwm_stream(socket: WebSocket, state: Arc<AppState>) {
-    println!("[WebSocket DFPWM] Connection established");
+async fn handle_dfpwm_stream(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    channel: DfpwmChannel,
+    metadata: bool,
+    label: String,
+) {
+    println!("[{}] Connection established ({:?})", label, channel);
     let (mut sender, mut receiver) = socket.split();
 
-    let mut rx = state.services.radio_service.subscribe_dfpwm();
+    let mut rx = state.services.radio_service.subscribe_dfpwm(channel, label);
+    let mut events = metadata.then(|| state.services.radio_service.subscribe_events());
 
-    // Send DFPWM audio chunks as binary messages
+    if metadata {
+        for msg in current_state(&state).await {
+            if let Some(text) = text_message(&msg) {
+                let _ = sender.send(text).await;
+            }
+        }
+    }
+
+    // Send DFPWM audio chunks as binary messages, and metadata as text if requested
     let mut send_task = tokio::spawn(async move {
         loop {
-            match rx.recv().await {
-                Ok(chunk) => {
-                    if sender.send(Message::Binary(chunk.into())).await.is_err() {
-                        println!("[WebSocket DFPWM] Failed to send chunk, client disconnected");
+            let message = tokio::select! {
+                // Lagging clients are moved to the live edge by the receiver
+                packet = rx.recv() => match packet {
+                    Some(DfpwmPacket::Audio(chunk)) => Message::Binary(chunk),
+                    Some(DfpwmPacket::TrackStart { name }) => {
+                        if !metadata {
+                            continue;
+                        }
+                        let msg =
+                            WebSocketMessage::TrackStart(CurrentTrackData { name: Some(name) });
+                        match text_message(&msg) {
+                            Some(text) => text,
+                            None => continue,
+                        }
+                    }
+                    None => {
+                        println!("[WebSocket DFPWM] Broadcast channel closed");
                         break;
                     }
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                    println!(
-                        "[WebSocket DFPWM] Client lagged, skipped {} chunks",
-                        skipped
-                    );
-                    // Продолжаем работу, пропуская отставшие чанки
-                    continue;
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    println!("[WebSocket DFPWM] Broadcast channel closed");
-                    break;
-                }
+                },
+                Some(event) = next_event(&mut events) => match text_message(&event) {
+                    Some(text) => text,
+                    None => continue,
+                },
+            };
+
+            if sender.send(message).await.is_err() {
+                println!("[WebSocket DFPWM] Failed to send chunk, client disconnected");
+                break;
             }
         }
         println!("[WebSocket DFPWM] Send task terminated");
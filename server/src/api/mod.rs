@@ -10,17 +10,30 @@ use crate::{
     infrastucture::{
         cache::client::Cache,
         database::pool::DbPool,
-        repositories::{track_repository::TrackRepository, users_repository::UsersRepository},
+        repositories::{
+            api_keys_repository::ApiKeysRepository,
+            email_outbox_repository::EmailOutboxRepository, track_repository::TrackRepository,
+            users_repository::UsersRepository,
+        },
     },
     service::{
+        account_service::AccountService,
+        api_key_service::ApiKeyService,
         auth::{
-            auth_service::AuthService, restore_service::RestoreService,
-            sign_up_service::SignUpService,
+            auth_service::AuthService, email_change_service::EmailChangeService,
+            login_guard_service::LoginGuardService, restore_service::RestoreService,
+            sign_up_service::SignUpService, token_auth_service::TokenAuthService,
         },
+        email_outbox::EmailOutboxWorker,
+        ingest_service::IngestService,
+        mail_transport::{self, memory::MemoryTransport},
+        music_provider::MusicProviders,
         otp_service::OTPService,
         playlist_service::PlaylistService,
         radio_service::RadioService,
+        rate_limiter::RateLimiter,
         smtp_service::SMTPService,
+        station_policy_service::StationPolicyService,
         token_service::TokenService,
         track_service::TrackService,
     },
@@ -29,10 +42,18 @@ use crate::{
 pub struct Services {
     pub sign_up_service: Arc<SignUpService>,
     pub restore_service: Arc<RestoreService>,
+    pub email_change_service: Arc<EmailChangeService>,
     pub auth_service: Arc<AuthService>,
+    pub account_service: Arc<AccountService>,
+    pub api_key_service: Arc<ApiKeyService>,
+    pub token_auth_service: Arc<TokenAuthService>,
     pub track_service: Arc<TrackService>,
     pub playlist_service: Arc<PlaylistService>,
     pub radio_service: Arc<RadioService>,
+    pub station_policy_service: Arc<StationPolicyService>,
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Emails sent so far when `MAIL_TRANSPORT=memory`.
+    pub sent_emails: Option<Arc<MemoryTransport>>,
 }
 
 pub struct AppState {
@@ -46,17 +67,45 @@ impl AppState {
         let config = Arc::new(config);
 
         // Shared services
-        let otp_service = Arc::new(OTPService::new());
-        let smtp_service = Arc::new(SMTPService::new(&config));
-        let token_service = Arc::new(TokenService::new(config.clone()));
+        let otp_service = Arc::new(OTPService::new(config.secret_config.otp_secret.clone()));
+        let token_service = Arc::new(TokenService::new(config.secret_config.clone()));
 
         let cache = Arc::new(Cache::new(&config.redis_config.url));
         let db_pool = Arc::new(db_pool);
 
         let users_repository = Arc::new(UsersRepository::new(db_pool.clone()));
         let track_repository = Arc::new(TrackRepository::new(db_pool.clone()));
+        let api_keys_repository = Arc::new(ApiKeysRepository::new(db_pool.clone()));
+        let email_outbox_repository = Arc::new(EmailOutboxRepository::new(db_pool.clone()));
+
+        let (mail_transport, sent_emails) = mail_transport::from_config(&config);
+        let outbox_notify = Arc::new(Notify::new());
+        tokio::spawn(
+            EmailOutboxWorker::new(
+                email_outbox_repository.clone(),
+                mail_transport,
+                outbox_notify.clone(),
+                config.smtp_config.from.clone(),
+            )
+            .run(),
+        );
+        let smtp_service = Arc::new(SMTPService::new(email_outbox_repository, outbox_notify));
 
         let playlist_service = Arc::new(PlaylistService::new(cache.clone()));
+        let music_providers = Arc::new(MusicProviders::from_config(&config));
+        if let Some(directory) = music_providers.directory() {
+            tokio::spawn(directory.clone().run_scanner());
+        }
+        let ingest_service = Arc::new(IngestService::new(
+            track_repository.clone(),
+            music_providers.clone(),
+            config.clone(),
+        ));
+
+        let login_guard_service = Arc::new(LoginGuardService::new(
+            cache.clone(),
+            config.login_guard_config.clone(),
+        ));
 
         let sign_up_service = Arc::new(SignUpService::new(
             cache.clone(),
@@ -64,6 +113,17 @@ impl AppState {
             smtp_service.clone(),
             users_repository.clone(),
             token_service.clone(),
+            login_guard_service.clone(),
+        ));
+
+        let token_auth_service =
+            Arc::new(TokenAuthService::new(cache.clone(), token_service.clone()));
+        let auth_service = Arc::new(AuthService::new(
+            cache.clone(),
+            users_repository.clone(),
+            login_guard_service.clone(),
+            smtp_service.clone(),
+            token_auth_service.clone(),
         ));
 
         let restore_service = Arc::new(RestoreService::new(
@@ -72,6 +132,24 @@ impl AppState {
             smtp_service.clone(),
             users_repository.clone(),
             token_service.clone(),
+            login_guard_service.clone(),
+            auth_service.clone(),
+        ));
+
+        let email_change_service = Arc::new(EmailChangeService::new(
+            cache.clone(),
+            otp_service.clone(),
+            smtp_service.clone(),
+            users_repository.clone(),
+            token_service.clone(),
+            login_guard_service.clone(),
+            auth_service.clone(),
+        ));
+
+        let station_policy_service = Arc::new(StationPolicyService::new(
+            cache.clone(),
+            playlist_service.clone(),
+            config.clone(),
         ));
 
         // Shared notify used to interrupt auto-play when a track is queued.
@@ -80,26 +158,39 @@ impl AppState {
         let track_service = Arc::new(TrackService::new(
             track_repository.clone(),
             playlist_service.clone(),
-            config.clone(),
+            ingest_service.clone(),
+            music_providers,
+            station_policy_service.clone(),
+            cache.clone(),
             queue_notify.clone(),
         ));
 
         let radio_service = RadioService::new(
             playlist_service.clone(),
             track_repository.clone(),
+            ingest_service,
+            station_policy_service.clone(),
             config.clone(),
             queue_notify,
         );
 
-        let auth_service = Arc::new(AuthService::new(cache.clone(), users_repository.clone()));
-
         let services = Services {
             sign_up_service,
             restore_service,
+            email_change_service,
+            account_service: Arc::new(AccountService::new(
+                users_repository.clone(),
+                auth_service.clone(),
+            )),
             auth_service,
+            api_key_service: Arc::new(ApiKeyService::new(api_keys_repository)),
+            token_auth_service,
             track_service,
             playlist_service,
             radio_service,
+            station_policy_service,
+            rate_limiter: Arc::new(RateLimiter::new(cache.clone())),
+            sent_emails,
         };
 
         AppState {
@@ -110,3 +201,74 @@ impl AppState {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        dto::request::auth::sign_up::{SignUpStartRequest, VerifyOTPRequest},
+        infrastucture::database::pool::create_pool,
+        service::email_template::Locale,
+    };
+
+    /// The state the server runs with, configured the same way.
+    async fn state() -> AppState {
+        dotenvy::dotenv().ok();
+        let config = AppConfig::new();
+        let db_pool = create_pool(&config.db_config.url).await.unwrap();
+        AppState::new(config, db_pool)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the server environment with MAIL_TRANSPORT=memory"]
+    async fn test_sign_up_code_arrives_by_email() {
+        let state = state().await;
+        let sent_emails = state
+            .services
+            .sent_emails
+            .clone()
+            .expect("MAIL_TRANSPORT must be memory");
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let email = format!("sign-up-{}@example.com", nanos);
+
+        let started = state
+            .services
+            .sign_up_service
+            .start_sign_up(
+                SignUpStartRequest {
+                    email: email.clone(),
+                },
+                Locale::En,
+            )
+            .await
+            .unwrap();
+
+        let message = sent_emails
+            .wait_for(&email, Duration::from_secs(10))
+            .await
+            .expect("the outbox worker should send the code");
+        let otp = message
+            .text_body
+            .split_whitespace()
+            .find(|word| word.len() == 6 && word.bytes().all(|b| b.is_ascii_digit()))
+            .expect("the email should contain the code")
+            .to_string();
+
+        state
+            .services
+            .sign_up_service
+            .verify_otp(
+                VerifyOTPRequest {
+                    token: started.token,
+                    otp,
+                },
+                "203.0.113.7".parse().unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+}
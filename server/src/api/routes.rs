@@ -16,6 +16,10 @@ pub fn create_router(state: AppState) -> Router {
             "/api/v1/sign-up",
             handlers::sign_up::sign_up_router(state.clone()),
         )
+        .nest(
+            "/api/v1/restore",
+            handlers::restore::restore_router(state.clone()),
+        )
         .nest(
             "/api/v1/track",
             handlers::track::track_router(state.clone()),
@@ -28,6 +32,26 @@ pub fn create_router(state: AppState) -> Router {
             "/api/v1/ws",
             handlers::websocket::websocket_router(state.clone()),
         )
+        .nest(
+            "/api/v1/api-keys",
+            handlers::api_keys::api_keys_router(state.clone()),
+        )
+        .nest(
+            "/api/v1/me",
+            handlers::account::account_router(state.clone()),
+        )
+        .nest(
+            "/api/v1/sessions",
+            handlers::sessions::sessions_router(state.clone()),
+        )
+        .nest(
+            "/api/v1/admin",
+            handlers::admin::admin_router(state.clone()),
+        )
+        .nest(
+            "/api/v1/cc",
+            handlers::computercraft::computercraft_router(state.clone()),
+        )
         .split_for_parts();
     if state.config.env == AppEnvironment::Development {
         let router = router.merge(SwaggerUi::new("/api-docs").url("/api-docs/openapi.json", api));
@@ -0,0 +1,35 @@
+/// Limits on failed password and OTP guesses.
+#[derive(Clone, Debug)]
+pub struct LoginGuardConfig {
+    /// Failed sign-ins after which the account is locked.
+    pub max_sign_in_failures: u32,
+    /// Wrong codes after which the OTP is invalidated.
+    pub max_otp_failures: u32,
+    /// Failed attempts of any email from a single IP before it is blocked.
+    pub max_ip_failures: u32,
+    /// Failures are forgotten this long after the last one, which is also how
+    /// long a lockout lasts.
+    pub lockout_sec: u32,
+}
+
+impl LoginGuardConfig {
+    pub fn new() -> Self {
+        Self {
+            max_sign_in_failures: Self::get_limit("AUTH_MAX_SIGN_IN_FAILURES", 5),
+            max_otp_failures: Self::get_limit("AUTH_MAX_OTP_FAILURES", 5),
+            max_ip_failures: Self::get_limit("AUTH_MAX_IP_FAILURES", 30),
+            lockout_sec: Self::get_limit("AUTH_LOCKOUT_SEC", 15 * 60),
+        }
+    }
+
+    fn get_limit(name: &str, default: u32) -> u32 {
+        let value = match std::env::var(name) {
+            Ok(value) => value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a valid u32", name)),
+            Err(_) => default,
+        };
+        assert!(value > 0, "{} must be positive", name);
+        value
+    }
+}
@@ -1,8 +1,17 @@
 mod database;
+pub mod login_guard;
+pub mod music;
+pub mod proxy;
+pub mod rate_limit;
 mod redis;
-mod secret;
-mod smtp;
+pub mod secret;
+pub mod smtp;
 mod songs;
+pub mod station;
+mod stream;
+
+/// Shown to listeners and in emails.
+pub const STATION_NAME: &str = "DJ Arbuzzz";
 
 #[derive(Clone, Debug)]
 pub enum AppEnvironment {
@@ -25,11 +34,22 @@ pub struct AppConfig {
     pub db_config: database::DatabaseConfig,
     pub redis_config: redis::RedisConfig,
     pub app_port: u16,
+    /// Externally reachable base URL, e.g. `https://radio.example.com`. When
+    /// unset it is derived from the request headers.
+    pub public_url: Option<String>,
+    /// Proxies allowed to report the client address.
+    pub trusted_proxies: proxy::TrustedProxies,
     pub music_api_url: String,
     pub music_api_token: String,
+    pub music_config: music::MusicConfig,
     pub smtp_config: smtp::SMTPConfig,
     pub secret_config: secret::SecretConfig,
     pub songs_config: songs::SongsConfig,
+    pub stream_config: stream::StreamConfig,
+    /// Defaults of the station policy, admins may override them at runtime.
+    pub station_policy: station::StationPolicy,
+    pub rate_limit_config: rate_limit::RateLimitConfig,
+    pub login_guard_config: login_guard::LoginGuardConfig,
 }
 
 impl AppConfig {
@@ -39,11 +59,18 @@ impl AppConfig {
             db_config: database::DatabaseConfig::new(),
             redis_config: redis::RedisConfig::new(),
             app_port: Self::get_app_port(),
+            public_url: Self::get_public_url(),
+            trusted_proxies: proxy::TrustedProxies::new(),
             music_api_url: Self::get_music_api_url(),
             music_api_token: Self::get_music_api_token(),
+            music_config: music::MusicConfig::new(),
             smtp_config: smtp::SMTPConfig::new(),
             secret_config: secret::SecretConfig::new(),
             songs_config: songs::SongsConfig::new(),
+            stream_config: stream::StreamConfig::new(),
+            station_policy: station::StationPolicy::new(),
+            rate_limit_config: rate_limit::RateLimitConfig::new(),
+            login_guard_config: login_guard::LoginGuardConfig::new(),
         }
     }
 
@@ -61,6 +88,13 @@ impl AppConfig {
             .expect("APP_PORT must be a valid u16")
     }
 
+    fn get_public_url() -> Option<String> {
+        std::env::var("PUBLIC_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| url.trim_end_matches('/').to_string())
+    }
+
     fn get_music_api_url() -> String {
         std::env::var("MUSIC_API_URL").expect("MUSIC_API_URL must be set")
     }
@@ -0,0 +1,29 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MusicProviderKind {
+    Vk,
+    Directory,
+}
+
+pub struct MusicConfig {
+    /// Provider used for search and track selection.
+    pub provider: MusicProviderKind,
+    /// Directory served by the directory provider.
+    pub dir_path: Option<String>,
+}
+
+impl MusicConfig {
+    pub fn new() -> Self {
+        let provider = match std::env::var("MUSIC_PROVIDER").as_deref() {
+            Ok("directory") => MusicProviderKind::Directory,
+            Ok("vk") | Err(_) => MusicProviderKind::Vk,
+            Ok(other) => panic!("MUSIC_PROVIDER must be vk or directory, got {}", other),
+        };
+        let dir_path = std::env::var("MUSIC_DIR_PATH")
+            .ok()
+            .filter(|path| !path.is_empty());
+        if provider == MusicProviderKind::Directory && dir_path.is_none() {
+            panic!("MUSIC_DIR_PATH must be set for the directory provider");
+        }
+        MusicConfig { provider, dir_path }
+    }
+}
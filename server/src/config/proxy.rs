@@ -0,0 +1,58 @@
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+/// Reverse proxies whose `X-Forwarded-For` and `X-Real-IP` headers are
+/// believed. Anyone else could put any address there.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrustedProxies {
+    nets: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    pub fn new() -> Self {
+        let value =
+            std::env::var("TRUSTED_PROXIES").unwrap_or_else(|_| "127.0.0.1/32,::1/128".to_string());
+        Self::parse(&value).unwrap_or_else(|e| panic!("TRUSTED_PROXIES is invalid: {}", e))
+    }
+
+    /// Parse a comma separated list of addresses and networks, e.g.
+    /// `127.0.0.1,172.16.0.0/12`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let nets = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("expected an address or a network, got {}", entry))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TrustedProxies { nets })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.nets.iter().any(|net| net.contains(&ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trusted_proxies() {
+        let proxies = TrustedProxies::parse("127.0.0.1, 172.16.0.0/12,::1/128").unwrap();
+
+        assert!(proxies.contains("127.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("172.18.0.5".parse().unwrap()));
+        assert!(proxies.contains("::ffff:172.18.0.5".parse().unwrap()));
+        assert!(proxies.contains("::1".parse().unwrap()));
+        assert!(!proxies.contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(TrustedProxies::parse(""), Ok(TrustedProxies::default()));
+        assert!(TrustedProxies::parse("localhost").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+/// Token bucket of `burst` requests that refills completely every `period`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Parse `<burst>/<seconds>`, e.g. `20/60`. `off` disables the limit.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        if value.trim() == "off" {
+            return Ok(None);
+        }
+        let (burst, seconds) = value
+            .split_once('/')
+            .ok_or_else(|| format!("expected <burst>/<seconds>, got {}", value))?;
+        let burst: u32 = burst
+            .trim()
+            .parse()
+            .map_err(|_| "invalid burst".to_string())?;
+        let seconds: u64 = seconds
+            .trim()
+            .parse()
+            .map_err(|_| "invalid period".to_string())?;
+        if burst == 0 || seconds == 0 {
+            return Err("burst and period must be positive".to_string());
+        }
+        Ok(Some(RateLimit {
+            burst,
+            period: Duration::from_secs(seconds),
+        }))
+    }
+}
+
+/// Limits of the routes that are expensive or send emails.
+pub struct RateLimitConfig {
+    pub track_search: Option<RateLimit>,
+    pub track_upload: Option<RateLimit>,
+    pub sign_in: Option<RateLimit>,
+    pub sign_up_start: Option<RateLimit>,
+    pub restore_start: Option<RateLimit>,
+    pub email_change_start: Option<RateLimit>,
+}
+
+impl RateLimitConfig {
+    pub fn new() -> Self {
+        Self {
+            track_search: Self::get_limit("RATE_LIMIT_TRACK_SEARCH", "20/60"),
+            track_upload: Self::get_limit("RATE_LIMIT_TRACK_UPLOAD", "5/600"),
+            sign_in: Self::get_limit("RATE_LIMIT_SIGN_IN", "10/60"),
+            sign_up_start: Self::get_limit("RATE_LIMIT_SIGN_UP_START", "3/600"),
+            restore_start: Self::get_limit("RATE_LIMIT_RESTORE_START", "3/600"),
+            email_change_start: Self::get_limit("RATE_LIMIT_EMAIL_CHANGE_START", "3/600"),
+        }
+    }
+
+    fn get_limit(name: &str, default: &str) -> Option<RateLimit> {
+        let value = std::env::var(name).unwrap_or_else(|_| default.to_string());
+        RateLimit::parse(&value).unwrap_or_else(|e| panic!("{} is invalid: {}", name, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(
+            RateLimit::parse("20/60"),
+            Ok(Some(RateLimit {
+                burst: 20,
+                period: Duration::from_secs(60)
+            }))
+        );
+        assert_eq!(RateLimit::parse("off"), Ok(None));
+        assert!(RateLimit::parse("20").is_err());
+        assert!(RateLimit::parse("0/60").is_err());
+        assert!(RateLimit::parse("20/x").is_err());
+    }
+}
@@ -1,19 +1,65 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MailTransportKind {
+    Smtp,
+    /// Writes every email as an `.eml` file into `MAIL_DIR`.
+    File,
+    /// Keeps sent emails in memory, for tests.
+    Memory,
+}
+
 pub struct SMTPConfig {
+    /// Where emails go, the SMTP settings are only required for `smtp`.
+    pub transport: MailTransportKind,
     pub from: String,
     pub host: String,
     pub port: u16,
     pub login: String,
     pub password: String,
+    /// Directory of the file transport.
+    pub mail_dir: Option<String>,
 }
 
 impl SMTPConfig {
     pub fn new() -> Self {
+        let transport = Self::get_transport();
+        let mail_dir = std::env::var("MAIL_DIR")
+            .ok()
+            .filter(|path| !path.is_empty());
+        if transport == MailTransportKind::File && mail_dir.is_none() {
+            panic!("MAIL_DIR must be set for the file mail transport");
+        }
+
+        if transport != MailTransportKind::Smtp {
+            return Self {
+                transport,
+                from: std::env::var("SMTP_FROM")
+                    .ok()
+                    .filter(|from| !from.is_empty())
+                    .unwrap_or_else(|| "noreply@localhost".to_string()),
+                host: String::new(),
+                port: 0,
+                login: String::new(),
+                password: String::new(),
+                mail_dir,
+            };
+        }
         Self {
+            transport,
             from: Self::get_from(),
             host: Self::get_host(),
             port: Self::get_port(),
             login: Self::get_login(),
             password: Self::get_password(),
+            mail_dir,
+        }
+    }
+
+    fn get_transport() -> MailTransportKind {
+        match std::env::var("MAIL_TRANSPORT").as_deref() {
+            Ok("smtp") | Err(_) => MailTransportKind::Smtp,
+            Ok("file") => MailTransportKind::File,
+            Ok("memory") => MailTransportKind::Memory,
+            Ok(other) => panic!("MAIL_TRANSPORT must be smtp, file or memory, got {}", other),
         }
     }
 
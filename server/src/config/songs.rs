@@ -1,10 +1,28 @@
 pub struct SongsConfig {
     pub songs_dir_path: String,
+    /// Largest audio file accepted by the upload endpoint.
+    pub max_upload_bytes: usize,
 }
 
 impl SongsConfig {
     pub fn new() -> Self {
         let songs_dir_path = std::env::var("SONGS_DIR_PATH").expect("SONGS_DIR_PATH must be set");
-        SongsConfig { songs_dir_path }
+        let max_upload_mb: usize = std::env::var("MAX_UPLOAD_SIZE_MB")
+            .unwrap_or_else(|_| "50".to_string())
+            .parse()
+            .expect("MAX_UPLOAD_SIZE_MB must be a valid usize");
+        SongsConfig {
+            songs_dir_path,
+            max_upload_bytes: max_upload_mb * 1024 * 1024,
+        }
+    }
+
+    pub fn track_file_path(&self, owner_id: i32, song_id: i32) -> String {
+        format!("{}/{}_{}.mp3", self.songs_dir_path, owner_id, song_id)
+    }
+
+    /// Path of an uploaded file, stored under its name in `tracks.download_url`.
+    pub fn local_file_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.songs_dir_path, file_name)
     }
 }
@@ -0,0 +1,33 @@
+/// Rules for queueing tracks. A limit of 0 disables that rule.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StationPolicy {
+    pub max_track_duration_sec: u32,
+    /// Tracks a single user may have waiting in the queue.
+    pub max_queued_per_user: u32,
+    /// Time a user has to wait between two requests.
+    pub user_cooldown_sec: u32,
+    pub max_queue_length: u32,
+    /// A track can't be requested again for this long after it was played.
+    pub no_repeat_window_sec: u32,
+}
+
+impl StationPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_track_duration_sec: Self::get_limit("STATION_MAX_TRACK_DURATION_SEC", 10 * 60),
+            max_queued_per_user: Self::get_limit("STATION_MAX_QUEUED_PER_USER", 3),
+            user_cooldown_sec: Self::get_limit("STATION_USER_COOLDOWN_SEC", 30),
+            max_queue_length: Self::get_limit("STATION_MAX_QUEUE_LENGTH", 50),
+            no_repeat_window_sec: Self::get_limit("STATION_NO_REPEAT_WINDOW_SEC", 60 * 60),
+        }
+    }
+
+    fn get_limit(name: &str, default: u32) -> u32 {
+        match std::env::var(name) {
+            Ok(value) => value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a valid u32", name)),
+            Err(_) => default,
+        }
+    }
+}
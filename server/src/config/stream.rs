@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+pub struct StreamConfig {
+    /// How far a listener may fall behind the live edge before it is moved
+    /// forward to it.
+    pub max_latency: Duration,
+}
+
+impl StreamConfig {
+    pub fn new() -> Self {
+        let max_latency_ms = std::env::var("STREAM_MAX_LATENCY_MS")
+            .unwrap_or_else(|_| "3000".to_string())
+            .parse()
+            .expect("STREAM_MAX_LATENCY_MS must be a valid u64");
+        StreamConfig {
+            max_latency: Duration::from_millis(max_latency_ms),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::service::email_template::Locale;
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct ChangeUsernameRequest {
+    #[validate(length(
+        min = 3,
+        max = 20,
+        message = "Имя пользователя должно быть от 3 до 20 символов"
+    ))]
+    pub username: String,
+}
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct ChangeLocaleRequest {
+    pub locale: Locale,
+}
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+
+    #[validate(length(min = 8, message = "Пароль должен быть не менее 8 символов"))]
+    pub new_password: String,
+}
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct StartEmailChangeRequest {
+    #[validate(email(message = "Неверный формат email"))]
+    pub new_email: String,
+
+    pub password: String,
+}
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct VerifyEmailChangeRequest {
+    pub token: String,
+
+    #[validate(length(min = 6, max = 6, message = "OTP должен состоять из 6 символов"))]
+    pub otp: String,
+}
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct ResendEmailChangeOTPRequest {
+    pub token: String,
+}
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
@@ -0,0 +1,13 @@
+use crate::service::api_key_service::ApiScope;
+
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Название должно быть от 1 до 64 символов"
+    ))]
+    pub name: String,
+    #[validate(length(min = 1, message = "Нужен хотя бы один scope"))]
+    pub scopes: Vec<ApiScope>,
+}
@@ -4,3 +4,8 @@ pub struct SignInRequest {
     pub email: String,
     pub password: String,
 }
+
+#[derive(serde::Deserialize, utoipa::ToSchema, validator::Validate)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
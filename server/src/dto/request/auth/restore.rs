@@ -5,19 +5,17 @@ pub struct StartRestoreRequest {
 }
 
 #[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
-pub struct VerifyOTPRequest {
-    #[validate(email(message = "Неверный формат email"))]
-    pub email: String,
-    pub hash: String,
+pub struct VerifyRestoreOTPRequest {
+    pub token: String,
+
     #[validate(length(min = 6, max = 6, message = "OTP должен состоять из 6 символов"))]
     pub otp: String,
 }
 
 #[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
 pub struct CompleteRestoreRequest {
-    #[validate(email(message = "Неверный формат email"))]
-    pub email: String,
     #[validate(length(min = 8, message = "Пароль должен быть не менее 8 символов"))]
     pub password: String,
-    pub hash: String,
+
+    pub token: String,
 }
@@ -1,2 +1,5 @@
+pub mod account;
+pub mod api_key;
 pub mod auth;
+pub mod station;
 pub mod track;
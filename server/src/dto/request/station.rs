@@ -0,0 +1,9 @@
+/// Fields left out keep their current value. 0 disables a limit.
+#[derive(serde::Deserialize, validator::Validate, utoipa::ToSchema)]
+pub struct UpdateStationPolicyRequest {
+    pub max_track_duration_sec: Option<u32>,
+    pub max_queued_per_user: Option<u32>,
+    pub user_cooldown_sec: Option<u32>,
+    pub max_queue_length: Option<u32>,
+    pub no_repeat_window_sec: Option<u32>,
+}
@@ -3,3 +3,12 @@ pub struct UserSelectTrackRequest {
     pub song_id: i32,
     pub owner_id: i32,
 }
+
+/// Form of the upload endpoint, used only for the OpenAPI description.
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct UploadTrackRequest {
+    /// MP3 file.
+    #[schema(format = Binary, content_media_type = "application/octet-stream")]
+    pub file: String,
+}
@@ -0,0 +1,33 @@
+use crate::{
+    infrastucture::database::models::{User, UserRole},
+    service::email_template::Locale,
+};
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct MeResponse {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    #[schema(value_type = String, example = "USER")]
+    pub role: UserRole,
+    /// Language of the emails.
+    pub locale: Locale,
+}
+
+impl From<User> for MeResponse {
+    fn from(user: User) -> Self {
+        MeResponse {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            role: user.role,
+            locale: Locale::parse(&user.locale).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct EmailChangeResponse {
+    pub token: String,
+    pub timeout_seconds: u16,
+}
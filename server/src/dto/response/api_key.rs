@@ -0,0 +1,40 @@
+use chrono::NaiveDateTime;
+
+use crate::{
+    infrastucture::database::models::ApiKey,
+    service::api_key_service::{ApiScope, parse_scopes},
+};
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub name: String,
+    /// Public part of the key, to tell keys apart.
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: NaiveDateTime,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct CreatedApiKeyResponse {
+    /// The full key. It can't be retrieved again.
+    pub key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyResponse,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        ApiKeyResponse {
+            id: key.id,
+            scopes: parse_scopes(&key.scopes),
+            name: key.name,
+            prefix: key.prefix,
+            created_at: key.created_at,
+            last_used_at: key.last_used_at,
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod restore;
+pub mod session;
 pub mod sign_up;
+pub mod token;
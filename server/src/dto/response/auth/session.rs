@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+#[derive(serde::Serialize, utoipa::ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionKind {
+    /// Browser session kept in a cookie.
+    Cookie,
+    /// Bearer token family.
+    Token,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct SessionResponse {
+    /// Id to revoke the session with. It can't be used to authenticate.
+    pub id: String,
+    pub kind: SessionKind,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Unix timestamp in seconds.
+    pub created_at: i64,
+    /// Unix timestamp in seconds.
+    pub last_active_at: i64,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+}
+
+impl SessionResponse {
+    /// Built from the cached hash of a session or token family.
+    pub fn from_cache(
+        id: String,
+        kind: SessionKind,
+        fields: &HashMap<String, String>,
+        current: bool,
+    ) -> Self {
+        let timestamp = |name: &str| {
+            fields
+                .get(name)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default()
+        };
+        SessionResponse {
+            kind,
+            user_agent: fields.get("user_agent").cloned(),
+            ip: fields.get("ip").cloned(),
+            created_at: timestamp("created_at"),
+            last_active_at: timestamp("last_updated"),
+            current,
+            id,
+        }
+    }
+}
@@ -10,8 +10,11 @@ use validator::Validate;
 
 use crate::error::app_error::AppError;
 
+pub mod account;
+pub mod api_key;
 pub mod auth;
 pub mod raido;
+pub mod station;
 pub mod track;
 pub mod websocket;
 
@@ -2,3 +2,8 @@
 pub struct GetCurrentTrackResponse {
     pub name: Option<String>,
 }
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct PlaybackStateResponse {
+    pub paused: bool,
+}
@@ -0,0 +1,22 @@
+use crate::config::station::StationPolicy;
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct StationPolicyResponse {
+    pub max_track_duration_sec: u32,
+    pub max_queued_per_user: u32,
+    pub user_cooldown_sec: u32,
+    pub max_queue_length: u32,
+    pub no_repeat_window_sec: u32,
+}
+
+impl From<StationPolicy> for StationPolicyResponse {
+    fn from(policy: StationPolicy) -> Self {
+        StationPolicyResponse {
+            max_track_duration_sec: policy.max_track_duration_sec,
+            max_queued_per_user: policy.max_queued_per_user,
+            user_cooldown_sec: policy.user_cooldown_sec,
+            max_queue_length: policy.max_queue_length,
+            no_repeat_window_sec: policy.no_repeat_window_sec,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(serde::Serialize, utoipa::ToSchema)]
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct SearchTrackDTO {
     pub name: String,
     pub duration: i32,
@@ -6,7 +6,18 @@ pub struct SearchTrackDTO {
     pub owner_id: i32,
 }
 
-#[derive(serde::Serialize, utoipa::ToSchema)]
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct SearchTrackResponse {
     pub tracks: Vec<SearchTrackDTO>,
+    /// Number of matches over all pages.
+    pub total: u32,
+    /// `offset` of the next page, absent on the last one.
+    pub next_offset: Option<u32>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct UploadTrackResponse {
+    pub id: i32,
+    pub name: String,
+    pub duration: i32,
 }
@@ -7,6 +7,11 @@ pub enum WebSocketMessage {
     CurrentTrack(CurrentTrackData),
     #[serde(rename = "playlist")]
     Playlist(PlaylistData),
+    /// Sent in-band on the DFPWM stream right before the first audio frame of a track.
+    #[serde(rename = "track_start")]
+    TrackStart(CurrentTrackData),
+    #[serde(rename = "playback_state")]
+    PlaybackState(PlaybackStateData),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +19,11 @@ pub struct CurrentTrackData {
     pub name: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PlaybackStateData {
+    pub paused: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlaylistData {
     pub items: Vec<PlaylistItemData>,
@@ -20,7 +20,16 @@ pub enum ErrorCode {
     ResendOTPTooManyRequests,
     SignUpFailed,
     JWTInvalid,
+    TooManyFailedAttempts,
+    AccountLocked,
+    OTPAttemptsExceeded,
+    RefreshTokenReused,
     TrackDurationLimit,
+    UnsupportedAudioFile,
+    UserQueueLimit,
+    TrackRequestCooldown,
+    QueueFull,
+    TrackRecentlyPlayed,
 }
 
 #[derive(serde::Serialize)]
@@ -43,6 +52,9 @@ pub enum AppError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String, Option<ErrorCode>),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String, Option<ErrorCode>),
+
     #[error("Bad request: {0}")]
     BadRequest(String, Option<ErrorCode>),
 
@@ -70,6 +82,7 @@ impl IntoResponse for AppError {
             AppError::NotFound(msg, code) => (StatusCode::NOT_FOUND, msg, code),
             AppError::Validation(msg, code) => (StatusCode::BAD_REQUEST, msg, code),
             AppError::Unauthorized(msg, code) => (StatusCode::UNAUTHORIZED, msg, code),
+            AppError::Forbidden(msg, code) => (StatusCode::FORBIDDEN, msg, code),
             AppError::BadRequest(msg, code) => (StatusCode::BAD_REQUEST, msg, code),
             AppError::Internal(err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -89,8 +102,17 @@ impl IntoResponse for AppError {
             Some(ErrorCode::JWTExpired) => 1005,
             Some(ErrorCode::ResendOTPTooManyRequests) => 1006,
             Some(ErrorCode::JWTInvalid) => 1007,
+            Some(ErrorCode::TooManyFailedAttempts) => 1008,
+            Some(ErrorCode::AccountLocked) => 1009,
+            Some(ErrorCode::OTPAttemptsExceeded) => 1010,
+            Some(ErrorCode::RefreshTokenReused) => 1011,
             Some(ErrorCode::SignUpFailed) => 1102,
             Some(ErrorCode::TrackDurationLimit) => 1201,
+            Some(ErrorCode::UnsupportedAudioFile) => 1202,
+            Some(ErrorCode::UserQueueLimit) => 1203,
+            Some(ErrorCode::TrackRequestCooldown) => 1204,
+            Some(ErrorCode::QueueFull) => 1205,
+            Some(ErrorCode::TrackRecentlyPlayed) => 1206,
             None => 1000,
         };
         let body = Json(json!({
@@ -151,3 +173,9 @@ impl From<reqwest::Error> for AppError {
         AppError::Internal(anyhow::anyhow!("HTTP request error: {}", value))
     }
 }
+
+impl From<mail_send::Error> for AppError {
+    fn from(value: mail_send::Error) -> Self {
+        AppError::Internal(anyhow::anyhow!("SMTP error: {}", value))
+    }
+}
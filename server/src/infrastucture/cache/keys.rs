@@ -1,15 +1,57 @@
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 pub enum AppCacheKey<'a> {
     SESSION(&'a str),
+    /// Hash of a user's cookie sessions, public id to session id.
+    USER_SESSIONS(i32),
+    /// Set of a user's refresh token families.
+    USER_TOKEN_FAMILIES(i32),
+    /// Id of a chain of rotated refresh tokens.
+    REFRESH_TOKEN_FAMILY(&'a str),
     SIGN_UP_OTP(&'a str),
+    RESTORE_OTP(&'a str),
+    /// Pending email change of a user.
+    EMAIL_CHANGE_OTP(i32),
     PLAYLIST(),
+    /// Provider name, normalized query, offset and count of a search page.
+    TRACK_SEARCH(&'a str, &'a str, u32, u32),
+    STATION_POLICY(),
+    /// Set while the user has to wait before requesting another track.
+    TRACK_REQUEST_COOLDOWN(i32),
+    /// Sorted set of track ids scored by when they were last played.
+    RECENT_TRACKS(),
+    /// Route name and subject (`ip:...` or `user:...`) of a token bucket.
+    RATE_LIMIT(&'a str, &'a str),
+    /// Attempt kind and subject (`email:...` or `ip:...`) of failed guesses.
+    AUTH_FAILURES(&'a str, &'a str),
 }
 
 impl<'a> AppCacheKey<'a> {
     pub fn build_key(&self) -> String {
         match self {
             AppCacheKey::SESSION(session_id) => format!("AUTH_SESSION_{}", session_id),
+            AppCacheKey::USER_SESSIONS(user_id) => format!("USER_SESSIONS_{}", user_id),
+            AppCacheKey::USER_TOKEN_FAMILIES(user_id) => {
+                format!("USER_TOKEN_FAMILIES_{}", user_id)
+            }
+            AppCacheKey::REFRESH_TOKEN_FAMILY(family) => format!("REFRESH_TOKEN_FAMILY_{}", family),
             AppCacheKey::SIGN_UP_OTP(email) => format!("SIGN_UP_OTP_{}", email),
+            AppCacheKey::RESTORE_OTP(email) => format!("RESTORE_OTP_{}", email),
+            AppCacheKey::EMAIL_CHANGE_OTP(user_id) => format!("EMAIL_CHANGE_OTP_{}", user_id),
             AppCacheKey::PLAYLIST() => "PLAYLIST".to_string(),
+            AppCacheKey::TRACK_SEARCH(provider, query, offset, count) => {
+                format!("TRACK_SEARCH_{}_{}_{}_{}", provider, offset, count, query)
+            }
+            AppCacheKey::STATION_POLICY() => "STATION_POLICY".to_string(),
+            AppCacheKey::TRACK_REQUEST_COOLDOWN(user_id) => {
+                format!("TRACK_REQUEST_COOLDOWN_{}", user_id)
+            }
+            AppCacheKey::RECENT_TRACKS() => "RECENT_TRACKS".to_string(),
+            AppCacheKey::RATE_LIMIT(route, subject) => {
+                format!("RATE_LIMIT_{}_{}", route, subject)
+            }
+            AppCacheKey::AUTH_FAILURES(kind, subject) => {
+                format!("AUTH_FAILURES_{}_{}", kind, subject)
+            }
         }
     }
 }
@@ -18,6 +18,8 @@ pub struct User {
     pub password: String,
     pub email: String,
     pub role: UserRole,
+    /// `ru` or `en`.
+    pub locale: String,
 }
 
 #[derive(Debug, Insertable)]
@@ -26,8 +28,12 @@ pub struct NewUser {
     pub username: String,
     pub password: String,
     pub email: String,
+    pub locale: String,
 }
 
+/// `tracks.source` of files uploaded directly to the server.
+pub const LOCAL_TRACK_SOURCE: &str = "local";
+
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, QueryableByName, Selectable, AsChangeset)]
 #[diesel(table_name = crate::schema::tracks)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -41,6 +47,15 @@ pub struct Track {
     pub duration_sec: i32,
     pub likes_count: i32,
     pub listens_count: i32,
+    pub loudness_lufs: Option<f32>,
+    pub true_peak_dbtp: Option<f32>,
+    pub duration_ms: Option<i32>,
+    pub sample_rate: Option<i32>,
+    pub channels: Option<i32>,
+    pub bitrate_kbps: Option<i32>,
+    pub is_vbr: Option<bool>,
+    pub file_size: Option<i64>,
+    pub source: String,
 }
 
 #[derive(Debug, Insertable)]
@@ -54,6 +69,7 @@ pub struct NewTrack {
     pub duration_sec: i32,
     pub likes_count: Option<i32>,
     pub listens_count: Option<i32>,
+    pub source: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
@@ -86,3 +102,64 @@ pub struct NewUserLike {
     pub user_id: i32,
     pub track_id: i32,
 }
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::api_keys)]
+pub struct NewApiKey {
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(diesel_derive_enum::DbEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[db_enum(existing_type_path = "crate::schema::sql_types::EmailStatus")]
+pub enum EmailStatus {
+    Pending,
+    Sent,
+    /// Given up on after too many attempts or once expired.
+    Failed,
+}
+
+#[derive(Debug, Clone, Queryable, QueryableByName, Selectable)]
+#[diesel(table_name = crate::schema::email_outbox)]
+pub struct OutboxEmail {
+    pub id: i32,
+    pub recipient: String,
+    pub template: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub status: EmailStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::email_outbox)]
+pub struct NewOutboxEmail {
+    pub recipient: String,
+    pub template: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::{
+    error::app_error::{AppError, AppResult},
+    infrastucture::database::{
+        models::{ApiKey, NewApiKey},
+        pool::DbPool,
+    },
+};
+
+pub struct ApiKeysRepository {
+    db_pool: Arc<DbPool>,
+}
+
+impl ApiKeysRepository {
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        ApiKeysRepository { db_pool }
+    }
+
+    pub async fn create(&self, new_key: &NewApiKey) -> AppResult<ApiKey> {
+        use crate::schema::api_keys::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let key = diesel::insert_into(api_keys)
+            .values(new_key)
+            .get_result::<ApiKey>(&mut conn)
+            .await?;
+        Ok(key)
+    }
+
+    pub async fn list_by_user(&self, owner_id: i32) -> AppResult<Vec<ApiKey>> {
+        use crate::schema::api_keys::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let keys = api_keys
+            .filter(user_id.eq(owner_id))
+            .order(created_at.desc())
+            .load::<ApiKey>(&mut conn)
+            .await?;
+        Ok(keys)
+    }
+
+    pub async fn count_by_user(&self, owner_id: i32) -> AppResult<i64> {
+        use crate::schema::api_keys::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let count = api_keys
+            .filter(user_id.eq(owner_id))
+            .count()
+            .get_result::<i64>(&mut conn)
+            .await?;
+        Ok(count)
+    }
+
+    pub async fn find_by_prefix(&self, key_prefix: &str) -> AppResult<Option<ApiKey>> {
+        use crate::schema::api_keys::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let key = api_keys
+            .filter(prefix.eq(key_prefix))
+            .first::<ApiKey>(&mut conn)
+            .await
+            .optional()?;
+        Ok(key)
+    }
+
+    /// Deletes the key if it belongs to the user.
+    pub async fn delete(&self, owner_id: i32, key_id: i32) -> AppResult<()> {
+        use crate::schema::api_keys::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let deleted = diesel::delete(api_keys.filter(id.eq(key_id).and(user_id.eq(owner_id))))
+            .execute(&mut conn)
+            .await?;
+        if deleted == 0 {
+            return Err(AppError::NotFound("API key not found".to_string(), None));
+        }
+        Ok(())
+    }
+
+    pub async fn set_last_used(&self, key_id: i32, used_at: NaiveDateTime) -> AppResult<()> {
+        use crate::schema::api_keys::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        diesel::update(api_keys.find(key_id))
+            .set(last_used_at.eq(used_at))
+            .execute(&mut conn)
+            .await?;
+        Ok(())
+    }
+}
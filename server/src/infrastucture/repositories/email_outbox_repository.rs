@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use diesel::{
+    dsl::{IntervalDsl, now},
+    prelude::*,
+    sql_types::Integer,
+};
+use diesel_async::RunQueryDsl;
+
+use crate::{
+    error::app_error::AppResult,
+    infrastucture::database::{
+        models::{EmailStatus, NewOutboxEmail, OutboxEmail},
+        pool::DbPool,
+    },
+};
+
+pub struct EmailOutboxRepository {
+    db_pool: Arc<DbPool>,
+}
+
+impl EmailOutboxRepository {
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        EmailOutboxRepository { db_pool }
+    }
+
+    /// Queue an email, which is given up on `lifetime_sec` from now if set.
+    pub async fn enqueue(
+        &self,
+        email: &NewOutboxEmail,
+        lifetime_sec: Option<i32>,
+    ) -> AppResult<i32> {
+        use crate::schema::email_outbox::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let insert = diesel::insert_into(email_outbox);
+        let email_id = match lifetime_sec {
+            Some(lifetime_sec) => {
+                insert
+                    .values((
+                        email,
+                        expires_at.eq((now + lifetime_sec.seconds()).nullable()),
+                    ))
+                    .returning(id)
+                    .get_result::<i32>(&mut conn)
+                    .await?
+            }
+            None => {
+                insert
+                    .values(email)
+                    .returning(id)
+                    .get_result::<i32>(&mut conn)
+                    .await?
+            }
+        };
+        Ok(email_id)
+    }
+
+    /// Gives up on the pending emails that expired. Returns how many there were.
+    pub async fn fail_expired(&self) -> AppResult<usize> {
+        use crate::schema::email_outbox::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let failed = diesel::update(
+            email_outbox
+                .filter(status.eq(EmailStatus::Pending))
+                .filter(expires_at.le(now.nullable())),
+        )
+        .set((
+            status.eq(EmailStatus::Failed),
+            last_error.eq("Expired before it could be sent"),
+            text_body.eq(""),
+            html_body.eq(""),
+        ))
+        .execute(&mut conn)
+        .await?;
+        Ok(failed)
+    }
+
+    /// Takes up to `limit` due emails and hides them from other workers for
+    /// `lease_sec`, so an email whose worker died is picked up again later.
+    pub async fn claim_due(&self, limit: i32, lease_sec: i32) -> AppResult<Vec<OutboxEmail>> {
+        let mut conn = self.db_pool.get().await?;
+        let emails = diesel::sql_query(
+            "UPDATE email_outbox SET next_attempt_at = NOW() + make_interval(secs => $1) \
+             WHERE id IN ( \
+                 SELECT id FROM email_outbox \
+                 WHERE status = 'pending' AND next_attempt_at <= NOW() \
+                 AND (expires_at IS NULL OR expires_at > NOW()) \
+                 ORDER BY next_attempt_at LIMIT $2 \
+                 FOR UPDATE SKIP LOCKED \
+             ) RETURNING *",
+        )
+        .bind::<Integer, _>(lease_sec)
+        .bind::<Integer, _>(limit)
+        .load::<OutboxEmail>(&mut conn)
+        .await?;
+        Ok(emails)
+    }
+
+    pub async fn mark_sent(&self, email_id: i32) -> AppResult<()> {
+        use crate::schema::email_outbox::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        diesel::update(email_outbox.find(email_id))
+            .set((
+                status.eq(EmailStatus::Sent),
+                attempts.eq(attempts + 1),
+                sent_at.eq(now.nullable()),
+                text_body.eq(""),
+                html_body.eq(""),
+            ))
+            .execute(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn mark_retry(&self, email_id: i32, delay_sec: i32, error: &str) -> AppResult<()> {
+        use crate::schema::email_outbox::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        diesel::update(email_outbox.find(email_id))
+            .set((
+                attempts.eq(attempts + 1),
+                last_error.eq(error),
+                next_attempt_at.eq(now + delay_sec.seconds()),
+            ))
+            .execute(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn mark_failed(&self, email_id: i32, error: &str) -> AppResult<()> {
+        use crate::schema::email_outbox::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        diesel::update(email_outbox.find(email_id))
+            .set((
+                status.eq(EmailStatus::Failed),
+                attempts.eq(attempts + 1),
+                last_error.eq(error),
+                text_body.eq(""),
+                html_body.eq(""),
+            ))
+            .execute(&mut conn)
+            .await?;
+        Ok(())
+    }
+}
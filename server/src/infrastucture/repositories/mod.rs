@@ -1,3 +1,5 @@
+pub mod api_keys_repository;
+pub mod email_outbox_repository;
 pub mod track_repository;
 pub mod user_track_repository;
 pub mod users_repository;
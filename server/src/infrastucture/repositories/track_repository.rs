@@ -6,6 +6,7 @@ use crate::{
         models::{NewTrack, NewUserTrack, Track, UserTrack},
         pool::DbPool,
     },
+    service::{audio_file::AudioProperties, loudness::TrackLoudness},
 };
 
 use diesel::prelude::*;
@@ -47,15 +48,14 @@ impl TrackRepository {
                 let new_track_ref = new_track;
                 Box::pin(async move {
                     let track = match tracks
+                        .filter(source.eq(&new_track_ref.source))
                         .filter(owner_id.eq(new_track_ref.owner_id))
                         .filter(song_id.eq(new_track_ref.song_id))
                         .first::<Track>(tx_conn)
                         .await
                     {
                         Ok(found_track) => {
-                            diesel::update(tracks)
-                                .filter(owner_id.eq(new_track_ref.owner_id))
-                                .filter(song_id.eq(new_track_ref.song_id))
+                            diesel::update(tracks.find(found_track.id))
                                 .set(listens_count.eq(found_track.listens_count + 1))
                                 .get_result::<Track>(tx_conn)
                                 .await?
@@ -87,6 +87,17 @@ impl TrackRepository {
         Ok(result)
     }
 
+    /// Song id for an uploaded file, unique among local tracks.
+    pub async fn next_local_song_id(&self) -> AppResult<i32> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::Integer;
+        let mut con = self.db_pool.get().await?;
+        let song_id = diesel::select(sql::<Integer>("nextval('local_song_id_seq')::INT4"))
+            .get_result::<i32>(&mut con)
+            .await?;
+        Ok(song_id)
+    }
+
     pub async fn find_track_by_track_owner(
         &self,
         track_id_val: i32,
@@ -103,12 +114,31 @@ impl TrackRepository {
         Ok(track)
     }
 
+    pub async fn find_provider_track(
+        &self,
+        source_val: &str,
+        owner_id_val: i32,
+        song_id_val: i32,
+    ) -> AppResult<Option<Track>> {
+        use crate::schema::tracks::dsl::*;
+        let mut con = self.db_pool.get().await?;
+        let track = tracks
+            .filter(source.eq(source_val))
+            .filter(owner_id.eq(owner_id_val))
+            .filter(song_id.eq(song_id_val))
+            .first::<Track>(&mut con)
+            .await
+            .optional()?;
+        Ok(track)
+    }
+
     pub async fn find_random_track(&self) -> AppResult<Track> {
         use diesel::sql_query;
         let mut con = self.db_pool.get().await?;
         let track = sql_query(
             "SELECT id, song_id, owner_id, download_url, title, artist, \
-             duration_sec, likes_count, listens_count \
+             duration_sec, likes_count, listens_count, loudness_lufs, true_peak_dbtp, \
+             duration_ms, sample_rate, channels, bitrate_kbps, is_vbr, file_size, source \
              FROM tracks ORDER BY RANDOM() LIMIT 1",
         )
         .get_result::<Track>(&mut con)
@@ -122,4 +152,80 @@ impl TrackRepository {
         })?;
         Ok(track)
     }
+
+    pub async fn find_track_by_id(&self, track_id_val: i32) -> AppResult<Track> {
+        use crate::schema::tracks::dsl::*;
+        let mut con = self.db_pool.get().await?;
+        let track = tracks
+            .filter(id.eq(track_id_val))
+            .first::<Track>(&mut con)
+            .await
+            .optional()?
+            .ok_or_else(|| {
+                crate::error::app_error::AppError::NotFound("Track not found".to_string(), None)
+            })?;
+        Ok(track)
+    }
+
+    pub async fn find_tracks_without_loudness(&self) -> AppResult<Vec<Track>> {
+        use crate::schema::tracks::dsl::*;
+        let mut con = self.db_pool.get().await?;
+        let result = tracks
+            .filter(loudness_lufs.is_null())
+            .order(id.asc())
+            .load::<Track>(&mut con)
+            .await?;
+        Ok(result)
+    }
+
+    pub async fn update_loudness(
+        &self,
+        track_id_val: i32,
+        loudness: &TrackLoudness,
+    ) -> AppResult<()> {
+        use crate::schema::tracks::dsl::*;
+        let mut con = self.db_pool.get().await?;
+        diesel::update(tracks.filter(id.eq(track_id_val)))
+            .set((
+                loudness_lufs.eq(loudness.integrated_lufs),
+                true_peak_dbtp.eq(loudness.true_peak_dbtp),
+            ))
+            .execute(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_download_url(&self, track_id_val: i32, url: &str) -> AppResult<()> {
+        use crate::schema::tracks::dsl::*;
+        let mut con = self.db_pool.get().await?;
+        diesel::update(tracks.filter(id.eq(track_id_val)))
+            .set(download_url.eq(url))
+            .execute(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    /// Store the properties measured from the downloaded file. The measured
+    /// duration replaces the one reported by the music API.
+    pub async fn update_audio_properties(
+        &self,
+        track_id_val: i32,
+        properties: &AudioProperties,
+    ) -> AppResult<()> {
+        use crate::schema::tracks::dsl::*;
+        let mut con = self.db_pool.get().await?;
+        diesel::update(tracks.filter(id.eq(track_id_val)))
+            .set((
+                duration_sec.eq(properties.duration_sec()),
+                duration_ms.eq(properties.duration_ms as i32),
+                sample_rate.eq(properties.sample_rate as i32),
+                channels.eq(properties.channels as i32),
+                bitrate_kbps.eq(properties.bitrate_kbps as i32),
+                is_vbr.eq(properties.is_vbr),
+                file_size.eq(properties.file_size as i64),
+            ))
+            .execute(&mut con)
+            .await?;
+        Ok(())
+    }
 }
@@ -49,6 +49,28 @@ impl UsersRepository {
         Ok(user)
     }
 
+    pub async fn get_user_by_id(&self, user_id: i32) -> AppResult<User> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let user = users.filter(id.eq(user_id)).first::<User>(&mut conn).await;
+
+        let user = match user {
+            Ok(user) => user,
+            Err(e) => {
+                return Err(match e {
+                    diesel::result::Error::NotFound => {
+                        AppError::NotFound("User not found".to_string(), None)
+                    }
+                    other => {
+                        AppError::Database(format!("Failed to retrieve user: {}", other), None)
+                    }
+                });
+            }
+        };
+
+        Ok(user)
+    }
+
     pub async fn get_user_by_username(&self, user_name: &str) -> AppResult<User> {
         use crate::schema::users::dsl::*;
         let mut conn = self.db_pool.get().await?;
@@ -98,4 +120,93 @@ impl UsersRepository {
 
         Ok(user)
     }
+
+    pub async fn update_password(&self, user_id: i32, password_hash: &str) -> AppResult<()> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let updated = diesel::update(users.find(user_id))
+            .set(password.eq(password_hash))
+            .execute(&mut conn)
+            .await?;
+        if updated == 0 {
+            return Err(AppError::NotFound("User not found".to_string(), None));
+        }
+        Ok(())
+    }
+
+    pub async fn update_username(&self, user_id: i32, new_username: &str) -> AppResult<User> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let user = diesel::update(users.find(user_id))
+            .set(username.eq(new_username))
+            .get_result::<User>(&mut conn)
+            .await;
+
+        match user {
+            Ok(user) => Ok(user),
+            Err(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => Err(AppError::Validation(
+                "Пользователь с таким именем уже существует".to_string(),
+                Some(ErrorCode::UserAlreadyExists),
+            )),
+            Err(diesel::result::Error::NotFound) => {
+                Err(AppError::NotFound("User not found".to_string(), None))
+            }
+            Err(other) => Err(AppError::Database(
+                format!("Failed to update user: {}", other),
+                None,
+            )),
+        }
+    }
+
+    pub async fn update_locale(&self, user_id: i32, new_locale: &str) -> AppResult<User> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let user = diesel::update(users.find(user_id))
+            .set(locale.eq(new_locale))
+            .get_result::<User>(&mut conn)
+            .await
+            .optional()?;
+        user.ok_or_else(|| AppError::NotFound("User not found".to_string(), None))
+    }
+
+    pub async fn update_email(&self, user_id: i32, new_email: &str) -> AppResult<()> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let updated = diesel::update(users.find(user_id))
+            .set(email.eq(new_email))
+            .execute(&mut conn)
+            .await;
+
+        match updated {
+            Ok(0) => Err(AppError::NotFound("User not found".to_string(), None)),
+            Ok(_) => Ok(()),
+            Err(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => Err(AppError::Validation(
+                "Пользователь с такой электронной почтой уже существует".to_string(),
+                Some(ErrorCode::UserAlreadyExists),
+            )),
+            Err(other) => Err(AppError::Database(
+                format!("Failed to update user: {}", other),
+                None,
+            )),
+        }
+    }
+
+    /// Tracks and likes of the user are removed by `ON DELETE CASCADE`.
+    pub async fn delete_user(&self, user_id: i32) -> AppResult<()> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.db_pool.get().await?;
+        let deleted = diesel::delete(users.find(user_id))
+            .execute(&mut conn)
+            .await?;
+        if deleted == 0 {
+            return Err(AppError::NotFound("User not found".to_string(), None));
+        }
+        Ok(())
+    }
 }
@@ -9,6 +9,9 @@ mod service;
 pub use crate::api::AppState;
 use crate::config::AppConfig;
 use crate::infrastucture::database::pool::create_pool;
+use crate::infrastucture::repositories::track_repository::TrackRepository;
+use crate::service::ingest_service::track_file_path;
+use crate::service::loudness::analyze_file;
 
 async fn server_start<'a>(addr: &str, state: AppState) {
     println!("🌐 Binding server to: {}", addr);
@@ -29,7 +32,9 @@ async fn server_start<'a>(addr: &str, state: AppState) {
 
     println!("✅ Server is ready and listening on {}", addr);
 
-    if let Err(e) = axum::serve(listener, router.into_make_service()).await {
+    // Peer addresses are needed to tell listeners apart in stream logs
+    let service = router.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    if let Err(e) = axum::serve(listener, service).await {
         eprintln!("❌ Server error: {}", e);
         panic!("Server crashed");
     }
@@ -74,3 +79,62 @@ pub async fn bootstrap() {
 
     server_start(&addr, app_state).await;
 }
+
+/// Analyze loudness for every track in the library that has none stored yet.
+pub async fn backfill_loudness() {
+    println!("📋 Loading application configuration...");
+    let config = AppConfig::new();
+
+    println!("🔌 Connecting to database...");
+    let db_pool = create_pool(&config.db_config.url)
+        .await
+        .expect("Database connection failed");
+    let track_repository = TrackRepository::new(std::sync::Arc::new(db_pool));
+
+    let tracks = track_repository
+        .find_tracks_without_loudness()
+        .await
+        .expect("Failed to load tracks");
+    println!("🎚  {} tracks without loudness data", tracks.len());
+
+    let (mut analyzed, mut skipped) = (0, 0);
+    for track in tracks {
+        let file_path = track_file_path(&config, &track);
+        if !std::path::Path::new(&file_path).exists() {
+            // Not downloaded yet, it will be analyzed on first play
+            skipped += 1;
+            continue;
+        }
+
+        let path = file_path.clone();
+        let loudness = match tokio::task::spawn_blocking(move || analyze_file(&path)).await {
+            Ok(Ok(loudness)) => loudness,
+            Ok(Err(e)) => {
+                eprintln!("❌ {}: {}", file_path, e);
+                skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("❌ {}: {}", file_path, e);
+                skipped += 1;
+                continue;
+            }
+        };
+
+        if let Err(e) = track_repository.update_loudness(track.id, &loudness).await {
+            eprintln!("❌ Failed to store loudness for track {}: {}", track.id, e);
+            skipped += 1;
+            continue;
+        }
+        println!(
+            "✓ {} - {}: {:.1} LUFS, {:.1} dBTP",
+            track.artist, track.title, loudness.integrated_lufs, loudness.true_peak_dbtp
+        );
+        analyzed += 1;
+    }
+
+    println!(
+        "✅ Backfill finished: {} analyzed, {} skipped",
+        analyzed, skipped
+    );
+}
@@ -1,4 +1,4 @@
-use backend_rust::bootstrap;
+use backend_rust::{backfill_loudness, bootstrap};
 
 #[tokio::main]
 async fn main() {
@@ -11,6 +11,12 @@ async fn main() {
     let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
     println!("✓ TLS provider initialized");
 
+    if std::env::args().nth(1).as_deref() == Some("backfill-loudness") {
+        println!("🎚  Starting loudness backfill...");
+        backfill_loudness().await;
+        return;
+    }
+
     println!("⚙️  Starting bootstrap...");
     bootstrap().await;
 
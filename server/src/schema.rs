@@ -1,11 +1,49 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "email_status"))]
+    pub struct EmailStatus;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "user_role"))]
     pub struct UserRole;
 }
 
+diesel::table! {
+    api_keys (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Varchar,
+        prefix -> Varchar,
+        secret_hash -> Varchar,
+        scopes -> Array<Text>,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::EmailStatus;
+
+    email_outbox (id) {
+        id -> Int4,
+        recipient -> Varchar,
+        template -> Varchar,
+        subject -> Varchar,
+        text_body -> Text,
+        html_body -> Text,
+        status -> EmailStatus,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Timestamp,
+        created_at -> Timestamp,
+        sent_at -> Nullable<Timestamp>,
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     tracks (id) {
         id -> Int4,
@@ -17,6 +55,15 @@ diesel::table! {
         duration_sec -> Int4,
         likes_count -> Int4,
         listens_count -> Int4,
+        loudness_lufs -> Nullable<Float4>,
+        true_peak_dbtp -> Nullable<Float4>,
+        duration_ms -> Nullable<Int4>,
+        sample_rate -> Nullable<Int4>,
+        channels -> Nullable<Int4>,
+        bitrate_kbps -> Nullable<Int4>,
+        is_vbr -> Nullable<Bool>,
+        file_size -> Nullable<Int8>,
+        source -> Varchar,
     }
 }
 
@@ -48,12 +95,21 @@ diesel::table! {
         password -> Varchar,
         email -> Varchar,
         role -> UserRole,
+        locale -> Varchar,
     }
 }
 
+diesel::joinable!(api_keys -> users (user_id));
 diesel::joinable!(user_likes -> tracks (track_id));
 diesel::joinable!(user_likes -> users (user_id));
 diesel::joinable!(user_tracks -> tracks (track_id));
 diesel::joinable!(user_tracks -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(tracks, user_likes, user_tracks, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    api_keys,
+    email_outbox,
+    tracks,
+    user_likes,
+    user_tracks,
+    users,
+);
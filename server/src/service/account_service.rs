@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use crate::{
+    dto::{
+        request::account::{
+            ChangeLocaleRequest, ChangePasswordRequest, ChangeUsernameRequest, DeleteAccountRequest,
+        },
+        response::account::MeResponse,
+    },
+    error::app_error::AppResult,
+    infrastucture::repositories::users_repository::UsersRepository,
+    service::auth::auth_service::{AuthService, hash_password},
+};
+
+/// Profile of the signed in user.
+pub struct AccountService {
+    users_repository: Arc<UsersRepository>,
+    auth_service: Arc<AuthService>,
+}
+
+impl AccountService {
+    pub fn new(users_repository: Arc<UsersRepository>, auth_service: Arc<AuthService>) -> Self {
+        AccountService {
+            users_repository,
+            auth_service,
+        }
+    }
+
+    pub async fn get_me(&self, user_id: i32) -> AppResult<MeResponse> {
+        let user = self.users_repository.get_user_by_id(user_id).await?;
+        Ok(user.into())
+    }
+
+    pub async fn change_username(
+        &self,
+        user_id: i32,
+        payload: ChangeUsernameRequest,
+    ) -> AppResult<MeResponse> {
+        let user = self
+            .users_repository
+            .update_username(user_id, &payload.username)
+            .await?;
+        Ok(user.into())
+    }
+
+    pub async fn change_locale(
+        &self,
+        user_id: i32,
+        payload: ChangeLocaleRequest,
+    ) -> AppResult<MeResponse> {
+        let user = self
+            .users_repository
+            .update_locale(user_id, payload.locale.as_str())
+            .await?;
+        Ok(user.into())
+    }
+
+    /// Signs out every other session; `current` is the session id or token
+    /// family the change was made with.
+    pub async fn change_password(
+        &self,
+        user_id: i32,
+        payload: ChangePasswordRequest,
+        current: Option<&str>,
+    ) -> AppResult<()> {
+        let user = self.users_repository.get_user_by_id(user_id).await?;
+        self.auth_service
+            .verify_password(&payload.current_password, &user.password)?;
+
+        let password_hash = hash_password(&payload.new_password)?;
+        self.users_repository
+            .update_password(user_id, &password_hash)
+            .await?;
+        self.auth_service
+            .revoke_all_sessions(user_id, current)
+            .await
+    }
+
+    pub async fn delete_account(
+        &self,
+        user_id: i32,
+        payload: DeleteAccountRequest,
+    ) -> AppResult<()> {
+        let user = self.users_repository.get_user_by_id(user_id).await?;
+        self.auth_service
+            .verify_password(&payload.password, &user.password)?;
+
+        self.auth_service.revoke_all_sessions(user_id, None).await?;
+        self.users_repository.delete_user(user_id).await
+    }
+}
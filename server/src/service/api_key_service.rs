@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use rand::{Rng, distributions::Alphanumeric, thread_rng};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    dto::{
+        request::api_key::CreateApiKeyRequest,
+        response::api_key::{ApiKeyResponse, CreatedApiKeyResponse},
+    },
+    error::app_error::{AppError, AppResult},
+    infrastucture::{
+        database::models::NewApiKey, repositories::api_keys_repository::ApiKeysRepository,
+    },
+    service::token_service::tokens_match,
+};
+
+const KEY_PREFIX: &str = "djk";
+const PREFIX_LEN: usize = 12;
+const SECRET_LEN: usize = 32;
+const MAX_KEYS_PER_USER: i64 = 20;
+/// `last_used_at` is written at most this often per key.
+const LAST_USED_RESOLUTION_SEC: i64 = 60;
+
+/// What a request authenticated by an API key may do.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema,
+)]
+pub enum ApiScope {
+    /// Listen to the radio. The streams are public, so this only marks the
+    /// keys of listening clients.
+    #[serde(rename = "stream")]
+    Stream,
+    /// Search tracks.
+    #[serde(rename = "queue:read")]
+    QueueRead,
+    /// Add tracks to the queue.
+    #[serde(rename = "queue:write")]
+    QueueWrite,
+    #[serde(rename = "track:upload")]
+    TrackUpload,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Stream => "stream",
+            ApiScope::QueueRead => "queue:read",
+            ApiScope::QueueWrite => "queue:write",
+            ApiScope::TrackUpload => "track:upload",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            ApiScope::Stream,
+            ApiScope::QueueRead,
+            ApiScope::QueueWrite,
+            ApiScope::TrackUpload,
+        ]
+        .into_iter()
+        .find(|scope| scope.as_str() == value)
+    }
+}
+
+/// Long-lived keys of machine clients, `djk_<prefix>_<secret>`. Only a hash of
+/// the secret is stored.
+pub struct ApiKeyService {
+    api_keys_repository: Arc<ApiKeysRepository>,
+}
+
+impl ApiKeyService {
+    pub fn new(api_keys_repository: Arc<ApiKeysRepository>) -> Self {
+        ApiKeyService {
+            api_keys_repository,
+        }
+    }
+
+    pub async fn create_key(
+        &self,
+        user_id: i32,
+        payload: CreateApiKeyRequest,
+    ) -> AppResult<CreatedApiKeyResponse> {
+        if self.api_keys_repository.count_by_user(user_id).await? >= MAX_KEYS_PER_USER {
+            return Err(AppError::BadRequest(
+                format!("A user can't have more than {} API keys", MAX_KEYS_PER_USER),
+                None,
+            ));
+        }
+        let mut scopes = payload.scopes;
+        scopes.sort_by_key(|scope| scope.as_str());
+        scopes.dedup();
+
+        let prefix = random_string(PREFIX_LEN);
+        let secret = random_string(SECRET_LEN);
+        let key = self
+            .api_keys_repository
+            .create(&NewApiKey {
+                user_id,
+                name: payload.name,
+                prefix: prefix.clone(),
+                secret_hash: hash_secret(&secret),
+                scopes: scopes
+                    .iter()
+                    .map(|scope| scope.as_str().to_string())
+                    .collect(),
+            })
+            .await?;
+
+        Ok(CreatedApiKeyResponse {
+            key: format!("{}_{}_{}", KEY_PREFIX, prefix, secret),
+            info: key.into(),
+        })
+    }
+
+    pub async fn list_keys(&self, user_id: i32) -> AppResult<Vec<ApiKeyResponse>> {
+        let keys = self.api_keys_repository.list_by_user(user_id).await?;
+        Ok(keys.into_iter().map(ApiKeyResponse::from).collect())
+    }
+
+    pub async fn revoke_key(&self, user_id: i32, key_id: i32) -> AppResult<()> {
+        self.api_keys_repository.delete(user_id, key_id).await
+    }
+
+    /// Owner and scopes of a key presented by a client.
+    pub async fn authenticate(&self, raw_key: &str) -> AppResult<(i32, Vec<ApiScope>)> {
+        let invalid = || AppError::Unauthorized("Invalid API key".to_string(), None);
+
+        let (prefix, secret) = split_key(raw_key).ok_or_else(invalid)?;
+        let key = self
+            .api_keys_repository
+            .find_by_prefix(prefix)
+            .await?
+            .ok_or_else(invalid)?;
+        if !tokens_match(&hash_secret(secret), &key.secret_hash) {
+            return Err(invalid());
+        }
+
+        let now = Utc::now().naive_utc();
+        if key
+            .last_used_at
+            .is_none_or(|at| (now - at).num_seconds() >= LAST_USED_RESOLUTION_SEC)
+        {
+            self.api_keys_repository.set_last_used(key.id, now).await?;
+        }
+        Ok((key.user_id, parse_scopes(&key.scopes)))
+    }
+}
+
+pub fn parse_scopes(scopes: &[String]) -> Vec<ApiScope> {
+    scopes.iter().filter_map(|s| ApiScope::parse(s)).collect()
+}
+
+fn split_key(raw_key: &str) -> Option<(&str, &str)> {
+    let rest = raw_key.trim().strip_prefix(KEY_PREFIX)?.strip_prefix('_')?;
+    let (prefix, secret) = rest.split_once('_')?;
+    (prefix.len() == PREFIX_LEN && secret.len() == SECRET_LEN).then_some((prefix, secret))
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn random_string(len: usize) -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_key() {
+        let prefix = "a".repeat(PREFIX_LEN);
+        let secret = "b".repeat(SECRET_LEN);
+        let key = format!("djk_{}_{}", prefix, secret);
+
+        assert_eq!(split_key(&key), Some((prefix.as_str(), secret.as_str())));
+        assert_eq!(split_key(&format!("xyz_{}_{}", prefix, secret)), None);
+        assert_eq!(split_key(&format!("djk_{}_{}", prefix, "short")), None);
+        assert_eq!(split_key("djk_"), None);
+    }
+
+    #[test]
+    fn test_scope_names_match_serde() {
+        for scope in [
+            ApiScope::Stream,
+            ApiScope::QueueRead,
+            ApiScope::QueueWrite,
+            ApiScope::TrackUpload,
+        ] {
+            let json = serde_json::to_string(&scope).unwrap();
+            assert_eq!(json, format!("\"{}\"", scope.as_str()));
+            assert_eq!(ApiScope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(ApiScope::parse("admin"), None);
+    }
+}
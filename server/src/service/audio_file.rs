@@ -0,0 +1,272 @@
+use symphonia::core::codecs::{
+    CodecType, Decoder, DecoderOptions, CODEC_TYPE_MP3, CODEC_TYPE_NULL,
+};
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use crate::error::app_error::AppResult;
+
+/// File extensions of the tracks that can be played. `/stream` forwards MP3
+/// frames as they are, so only MP3 is supported and symphonia is built with
+/// just the MP3 reader and decoder.
+pub const SUPPORTED_EXTENSIONS: [&str; 1] = ["mp3"];
+
+/// An audio file opened with symphonia, ready to be read packet by packet.
+pub struct AudioFile {
+    pub format: Box<dyn FormatReader>,
+    pub decoder: Box<dyn Decoder>,
+    pub track_id: u32,
+    pub codec: CodecType,
+}
+
+impl AudioFile {
+    pub fn open(file_path: &str) -> AppResult<Self> {
+        let probed = probe_format(file_path)?;
+
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow::anyhow!("No supported audio tracks"))?;
+
+        let dec_opts: DecoderOptions = Default::default();
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &dec_opts)
+            .map_err(|e| anyhow::anyhow!("Failed to create decoder: {}", e))?;
+
+        Ok(Self {
+            track_id: track.id,
+            codec: track.codec_params.codec,
+            format,
+            decoder,
+        })
+    }
+
+    /// Fails unless the file holds MP3, the only format every output can play.
+    pub fn require_mp3(&self) -> AppResult<()> {
+        if self.codec != CODEC_TYPE_MP3 {
+            return Err(anyhow::anyhow!("Not an MP3 file").into());
+        }
+        Ok(())
+    }
+}
+
+fn probe_format(file_path: &str) -> AppResult<ProbeResult> {
+    let file = std::fs::File::open(file_path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = std::path::Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        hint.with_extension(extension);
+    }
+
+    let meta_opts: MetadataOptions = Default::default();
+    let fmt_opts: FormatOptions = Default::default();
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &fmt_opts, &meta_opts)
+        .map_err(|e| anyhow::anyhow!("Failed to probe format: {}", e))?;
+    Ok(probed)
+}
+
+/// Real properties of an audio file, measured rather than taken from the provider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioProperties {
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Average bitrate over the whole file.
+    pub bitrate_kbps: u32,
+    pub is_vbr: bool,
+    pub file_size: u64,
+}
+
+impl AudioProperties {
+    pub fn duration_sec(&self) -> i32 {
+        self.duration_ms.div_ceil(1000) as i32
+    }
+}
+
+/// Read every packet without decoding to measure the exact duration and the
+/// bitrate of each frame. This is blocking and should be run on a blocking thread.
+pub fn probe_file(file_path: &str) -> AppResult<AudioProperties> {
+    let file_size = std::fs::metadata(file_path)?.len();
+    let mut audio = AudioFile::open(file_path)?;
+    audio.require_mp3()?;
+
+    let params = audio
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.id == audio.track_id)
+        .map(|t| t.codec_params.clone())
+        .ok_or_else(|| anyhow::anyhow!("No supported audio tracks"))?;
+    let sample_rate = params
+        .sample_rate
+        .ok_or_else(|| anyhow::anyhow!("Unknown sample rate"))?;
+    let channels = params.channels.map_or(0, |c| c.count()) as u16;
+
+    let mut frames: u64 = 0;
+    let mut audio_bytes: u64 = 0;
+    let mut frame_bitrate: Option<u64> = None;
+    let mut is_vbr = false;
+
+    while let Ok(packet) = audio.format.next_packet() {
+        if packet.track_id() != audio.track_id || packet.dur == 0 {
+            continue;
+        }
+        frames += packet.dur;
+        audio_bytes += packet.buf().len() as u64;
+
+        // Round to whole kbps so MP3 padding bytes don't count as VBR
+        let kbps = (packet.buf().len() as u64 * 8 * sample_rate as u64 / packet.dur + 500) / 1000;
+        match frame_bitrate {
+            None => frame_bitrate = Some(kbps),
+            Some(previous) if previous != kbps => is_vbr = true,
+            _ => {}
+        }
+    }
+
+    if frames == 0 {
+        return Err(anyhow::anyhow!("No audio frames in {}", file_path).into());
+    }
+
+    Ok(AudioProperties {
+        duration_ms: frames * 1000 / sample_rate as u64,
+        sample_rate,
+        channels,
+        bitrate_kbps: (audio_bytes * 8 * sample_rate as u64 / frames / 1000) as u32,
+        is_vbr,
+        file_size,
+    })
+}
+
+/// Title and artist from the ID3 tags of a file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl AudioTags {
+    fn fill_from(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let slot = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                _ => continue,
+            };
+            let value = tag.value.to_string().trim().to_string();
+            if slot.is_none() && !value.is_empty() {
+                *slot = Some(value);
+            }
+        }
+    }
+}
+
+/// Read the ID3v2 tags in front of the MP3 stream.
+pub fn read_tags(file_path: &str) -> AppResult<AudioTags> {
+    let mut probed = probe_format(file_path)?;
+    let mut tags = AudioTags::default();
+
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        tags.fill_from(revision);
+    }
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::playback::silent_mp3_frame;
+
+    fn id3_frame(id: &[u8; 4], text: &str) -> Vec<u8> {
+        // ISO-8859-1 encoded text frame
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0]);
+        frame.extend_from_slice(text.as_bytes());
+        frame
+    }
+
+    /// One second of a 16 bit mono PCM tone in a WAV container.
+    fn wav_file() -> Vec<u8> {
+        let sample_rate: u32 = 8000;
+        let samples: Vec<u8> = (0..sample_rate)
+            .flat_map(|i| (((i % 40) as i16 - 20) * 1000).to_le_bytes())
+            .collect();
+
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        file.extend_from_slice(b"WAVEfmt ");
+        file.extend_from_slice(&16u32.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        file.extend_from_slice(&1u16.to_le_bytes()); // mono
+        file.extend_from_slice(&sample_rate.to_le_bytes());
+        file.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        file.extend_from_slice(&2u16.to_le_bytes());
+        file.extend_from_slice(&16u16.to_le_bytes());
+        file.extend_from_slice(b"data");
+        file.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        file.extend(samples);
+        file
+    }
+
+    #[test]
+    fn test_only_mp3_can_be_played() {
+        let wav_path = std::env::temp_dir().join(format!("tone_{}.wav", std::process::id()));
+        std::fs::write(&wav_path, wav_file()).unwrap();
+        let mp3_path = std::env::temp_dir().join(format!("tone_{}.mp3", std::process::id()));
+        let (silence, _) = silent_mp3_frame(44100, 2).unwrap();
+        std::fs::write(&mp3_path, silence.repeat(20)).unwrap();
+
+        // Only the MP3 reader is built in, a WAV file is not even recognized
+        let wav = AudioFile::open(wav_path.to_str().unwrap());
+        let wav_probe = probe_file(wav_path.to_str().unwrap());
+        let mp3 = AudioFile::open(mp3_path.to_str().unwrap());
+        std::fs::remove_file(&wav_path).unwrap();
+        std::fs::remove_file(&mp3_path).unwrap();
+
+        assert!(wav.is_err());
+        assert!(wav_probe.is_err());
+        assert!(mp3.unwrap().require_mp3().is_ok());
+    }
+
+    #[test]
+    fn test_read_id3_tags() {
+        let mut frames = id3_frame(b"TIT2", "Arbuz");
+        frames.extend(id3_frame(b"TPE1", "DJ Arbuzzz"));
+
+        // ID3v2.3 header with a syncsafe size
+        let size = frames.len() as u32;
+        let mut file = b"ID3\x03\x00\x00".to_vec();
+        file.extend_from_slice(&[
+            (size >> 21 & 0x7f) as u8,
+            (size >> 14 & 0x7f) as u8,
+            (size >> 7 & 0x7f) as u8,
+            (size & 0x7f) as u8,
+        ]);
+        file.extend(frames);
+        let (silence, _) = silent_mp3_frame(44100, 2).unwrap();
+        for _ in 0..50 {
+            file.extend_from_slice(&silence);
+        }
+
+        let path = std::env::temp_dir().join(format!("tags_{}.mp3", std::process::id()));
+        std::fs::write(&path, &file).unwrap();
+        let tags = read_tags(path.to_str().unwrap());
+        let properties = probe_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let tags = tags.unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Arbuz"));
+        assert_eq!(tags.artist.as_deref(), Some("DJ Arbuzzz"));
+        assert_eq!(properties.unwrap().sample_rate, 44100);
+    }
+}
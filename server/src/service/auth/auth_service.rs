@@ -1,51 +1,121 @@
+use std::net::IpAddr;
 use std::sync::Arc;
 
-use argon2::{Argon2, PasswordVerifier, password_hash};
-use rand::distributions::Alphanumeric;
-use rand::{Rng, thread_rng};
-use redis::AsyncCommands;
+use argon2::{Argon2, PasswordHasher, PasswordVerifier, password_hash};
 use tower_cookies::{Cookie, Cookies};
 
-const COOKIE_LIFETIME_SEC: i64 = 60 * 60 * 24 * 7;
-
-use crate::infrastucture::cache::keys::AppCacheKey;
 use crate::{
-    dto::request::auth::auth::SignInRequest,
+    dto::{request::auth::auth::SignInRequest, response::auth::session::SessionResponse},
     error::app_error::{AppError, AppResult, ErrorCode},
-    infrastucture::{cache::client::Cache, repositories::users_repository::UsersRepository},
+    infrastucture::{
+        cache::client::Cache,
+        database::models::{User, UserRole},
+        repositories::users_repository::UsersRepository,
+    },
+    service::{
+        auth::{
+            login_guard_service::{AttemptKind, LoginGuardService},
+            session_store::{CachedSession, SessionStore},
+            token_auth_service::TokenAuthService,
+        },
+        email_template::Locale,
+        smtp_service::SMTPService,
+    },
 };
 
-pub struct CachedSession {
-    pub session_id: String,
-    pub created_at: i64,
-    pub last_updated: i64,
-    pub user_id: i32,
+/// Argon2 hash of a new password.
+pub fn hash_password(password: &str) -> AppResult<String> {
+    let salt = password_hash::SaltString::generate(&mut rand::rngs::OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to hash password: {}", e)))
+}
+
+pub fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Where a session was started from, shown in the list of sessions.
+pub struct SessionMeta {
+    pub ip: IpAddr,
+    pub user_agent: Option<String>,
 }
 
 pub struct AuthService {
-    cache: Arc<Cache>,
+    session_store: SessionStore,
     users_repository: Arc<UsersRepository>,
+    login_guard_service: Arc<LoginGuardService>,
+    smtp_service: Arc<SMTPService>,
+    token_auth_service: Arc<TokenAuthService>,
 }
 
 impl AuthService {
-    pub fn new(cache: Arc<Cache>, users_repository: Arc<UsersRepository>) -> Self {
+    pub fn new(
+        cache: Arc<Cache>,
+        users_repository: Arc<UsersRepository>,
+        login_guard_service: Arc<LoginGuardService>,
+        smtp_service: Arc<SMTPService>,
+        token_auth_service: Arc<TokenAuthService>,
+    ) -> Self {
         AuthService {
-            cache,
+            session_store: SessionStore::new(cache),
             users_repository,
+            login_guard_service,
+            smtp_service,
+            token_auth_service,
         }
     }
 
-    pub async fn sign_in(&self, payload: SignInRequest, cookies: Cookies) -> AppResult<()> {
+    pub async fn sign_in(
+        &self,
+        payload: SignInRequest,
+        meta: SessionMeta,
+        cookies: Cookies,
+    ) -> AppResult<()> {
+        let user = self.authenticate(&payload, meta.ip).await?;
+        self.create_session(cookies, user.id, &meta).await?;
+        Ok(())
+    }
+
+    /// Check the credentials, counting failures against the email and IP.
+    pub async fn authenticate(&self, payload: &SignInRequest, ip: IpAddr) -> AppResult<User> {
+        let attempt = self
+            .login_guard_service
+            .reserve(AttemptKind::SignIn, &payload.email, ip)
+            .await?;
+
+        // Unknown emails count as failures too
         let user = self
             .users_repository
             .get_user_by_email(&payload.email)
             .await?;
-        self.verify_password(&payload.password, &user.password)?;
-        self.create_session(cookies, user.id).await?;
-        Ok(())
+        if let Err(e) = self.verify_password(&payload.password, &user.password) {
+            if attempt.is_last() {
+                let minutes = self.login_guard_service.lockout_minutes();
+                if let Err(err) = self
+                    .smtp_service
+                    .send_account_locked(
+                        &user.email,
+                        minutes,
+                        Locale::parse(&user.locale).unwrap_or_default(),
+                    )
+                    .await
+                {
+                    eprintln!("Failed to notify {} about the lockout: {}", user.email, err);
+                }
+            }
+            return Err(e);
+        }
+
+        self.login_guard_service.succeed(attempt).await?;
+        Ok(user)
     }
 
-    fn verify_password(&self, password: &str, hash: &str) -> AppResult<()> {
+    pub fn verify_password(&self, password: &str, hash: &str) -> AppResult<()> {
         let parsed_hash = match password_hash::PasswordHash::new(hash) {
             Ok(hash) => hash,
             Err(_) => {
@@ -67,36 +137,13 @@ impl AuthService {
         Ok(())
     }
 
-    async fn create_session(&self, cookies: Cookies, user_id: i32) -> AppResult<()> {
-        let session_id: String = thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(32)
-            .map(char::from)
-            .collect();
-
-        let mut cache_con = self.cache.get_async_conn().await?;
-        let cache_key = AppCacheKey::SESSION(&session_id).build_key();
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            .to_string();
-
-        let _: () = cache_con
-            .hset_multiple(
-                &cache_key,
-                &[
-                    ("session_id", session_id.clone()),
-                    ("created_at", timestamp.clone()),
-                    ("last_updated", timestamp),
-                    ("user_id", user_id.to_string()),
-                ],
-            )
-            .await?;
-
-        let _ = cache_con
-            .expire::<_, ()>(&cache_key, COOKIE_LIFETIME_SEC)
-            .await?;
+    async fn create_session(
+        &self,
+        cookies: Cookies,
+        user_id: i32,
+        meta: &SessionMeta,
+    ) -> AppResult<()> {
+        let session_id = self.session_store.create(user_id, meta).await?;
 
         let mut cookie = Cookie::new("x-authenticated", session_id);
         cookie.set_secure(true);
@@ -107,13 +154,7 @@ impl AuthService {
     }
 
     pub async fn delete_session(&self, cookies: Cookies, sid: String) -> AppResult<()> {
-        let mut con = self.cache.get_async_conn().await?;
-        let key = AppCacheKey::SESSION(&sid).build_key();
-
-        match con.del::<_, ()>(key).await {
-            Ok(_) => (),
-            Err(_) => (),
-        };
+        self.session_store.delete(&sid).await?;
 
         let mut cookie = Cookie::new("x-authenticated", sid);
         cookie.set_secure(true);
@@ -125,29 +166,50 @@ impl AuthService {
     }
 
     pub async fn get_session_from_cache_and_update(&self, sid: String) -> AppResult<CachedSession> {
-        let mut con = self.cache.get_async_conn().await?;
-        let key = AppCacheKey::SESSION(&sid).build_key();
-        let created_at: i64 = con.hget(&key, "created_at").await?;
-        let last_updated: i64 = con.hget(&key, "last_updated").await?;
-        let user_id = con.hget(&key, "user_id").await?;
-
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            .to_string();
-
-        let _: () = con
-            .hset(&key, "last_updated", timestamp.to_string())
-            .await?;
+        self.session_store.touch(&sid).await
+    }
 
-        let _ = con.expire::<_, ()>(&key, COOKIE_LIFETIME_SEC).await?;
-        Ok(CachedSession {
-            session_id: sid,
-            created_at,
-            last_updated,
-            user_id,
-        })
+    /// Cookie sessions and token families of the user, most recently active
+    /// first. `current` is the session id or token family of the request.
+    pub async fn list_sessions(
+        &self,
+        user_id: i32,
+        current: Option<&str>,
+    ) -> AppResult<Vec<SessionResponse>> {
+        let mut sessions = self.session_store.list(user_id, current).await?;
+        sessions.extend(
+            self.token_auth_service
+                .list_sessions(user_id, current)
+                .await?,
+        );
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_active_at));
+        Ok(sessions)
+    }
+
+    /// Revokes a session by the id shown in the list of sessions.
+    pub async fn revoke_session(&self, user_id: i32, id: &str) -> AppResult<()> {
+        if self.session_store.revoke(user_id, id).await? {
+            return Ok(());
+        }
+        if self.token_auth_service.revoke_family(user_id, id).await? {
+            return Ok(());
+        }
+        Err(AppError::NotFound("Session not found".to_string(), None))
+    }
+
+    /// Revokes every session and token family of the user except `keep`, the
+    /// session id or token family to stay signed in with.
+    pub async fn revoke_all_sessions(&self, user_id: i32, keep: Option<&str>) -> AppResult<()> {
+        self.session_store.revoke_all(user_id, keep).await?;
+        self.token_auth_service.revoke_all(user_id, keep).await
+    }
+
+    pub async fn require_admin(&self, user_id: i32) -> AppResult<()> {
+        let user = self.users_repository.get_user_by_id(user_id).await?;
+        match user.role {
+            UserRole::ADMIN => Ok(()),
+            UserRole::USER => Err(AppError::Forbidden("Admin role required".to_string(), None)),
+        }
     }
 
     pub fn get_session_id_from_req<Body>(
@@ -0,0 +1,337 @@
+use std::{net::IpAddr, sync::Arc};
+
+use jsonwebtoken::get_current_timestamp;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::{
+        request::account::{
+            ResendEmailChangeOTPRequest, StartEmailChangeRequest, VerifyEmailChangeRequest,
+        },
+        response::account::EmailChangeResponse,
+    },
+    error::app_error::{AppError, AppResult, ErrorCode},
+    infrastucture::{
+        cache::{client::Cache, keys::AppCacheKey},
+        repositories::users_repository::UsersRepository,
+    },
+    service::{
+        auth::{
+            auth_service::AuthService,
+            login_guard_service::{AttemptKind, LoginGuardService, OtpFlow, otp_attempts_exceeded},
+        },
+        email_template::Locale,
+        otp_service::OTPService,
+        smtp_service::SMTPService,
+        token_service::{Token, TokenService, TokenType, new_token_id, tokens_match},
+    },
+};
+
+const TOKEN_LIFETIME_SEC: u64 = 60 * 11;
+const CACHE_LIFETIME_SEC: i64 = 60 * 10;
+const RESEND_OTP_TIMEOUT_SEC: u64 = 60;
+
+#[derive(Serialize, Deserialize)]
+struct TokenData {
+    user_id: i32,
+    new_email: String,
+    /// Id the stored OTP hash is bound to.
+    jti: String,
+    exp: u64,
+    token_type: TokenType,
+    created_at: u64,
+    locale: Locale,
+}
+
+impl Token for TokenData {
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+    fn token_type(&self) -> TokenType {
+        self.token_type.clone()
+    }
+}
+
+struct CachedEmailChangeParams {
+    otp_hash: String,
+    new_email: String,
+    token: String,
+}
+
+/// Moves an account to a new email once a code sent there is confirmed. The
+/// old address only gets a notice.
+pub struct EmailChangeService {
+    cache: Arc<Cache>,
+    otp_service: Arc<OTPService>,
+    smtp_service: Arc<SMTPService>,
+    users_repository: Arc<UsersRepository>,
+    token_service: Arc<TokenService>,
+    login_guard_service: Arc<LoginGuardService>,
+    auth_service: Arc<AuthService>,
+}
+
+impl EmailChangeService {
+    pub fn new(
+        cache: Arc<Cache>,
+        otp_service: Arc<OTPService>,
+        smtp_service: Arc<SMTPService>,
+        users_repository: Arc<UsersRepository>,
+        token_service: Arc<TokenService>,
+        login_guard_service: Arc<LoginGuardService>,
+        auth_service: Arc<AuthService>,
+    ) -> Self {
+        EmailChangeService {
+            cache,
+            otp_service,
+            smtp_service,
+            users_repository,
+            token_service,
+            login_guard_service,
+            auth_service,
+        }
+    }
+
+    pub async fn start_email_change(
+        &self,
+        user_id: i32,
+        payload: StartEmailChangeRequest,
+    ) -> AppResult<EmailChangeResponse> {
+        let user = self.users_repository.get_user_by_id(user_id).await?;
+        self.auth_service
+            .verify_password(&payload.password, &user.password)?;
+
+        if payload.new_email == user.email {
+            return Err(AppError::BadRequest(
+                "This is already your email".to_string(),
+                None,
+            ));
+        }
+        match self
+            .users_repository
+            .get_user_by_email(&payload.new_email)
+            .await
+        {
+            Ok(_) => {
+                return Err(AppError::BadRequest(
+                    format!("User with email {} already exists", &payload.new_email),
+                    Some(ErrorCode::UserAlreadyExists),
+                ));
+            }
+            Err(AppError::NotFound(..)) => (),
+            Err(e) => return Err(e),
+        }
+
+        // A code that was already sent to this address stays valid until it
+        // expires
+        if let Some(cached) = self.get_cached_data(user_id).await? {
+            if cached.new_email == payload.new_email {
+                if let Ok(token_data) = self
+                    .token_service
+                    .get_claims_from_jwt::<TokenData>(&cached.token, TokenType::EmailChange)
+                {
+                    return Ok(EmailChangeResponse {
+                        token: cached.token,
+                        timeout_seconds: token_data.exp.saturating_sub(get_current_timestamp())
+                            as u16,
+                    });
+                }
+            }
+        }
+
+        let locale = Locale::parse(&user.locale).unwrap_or_default();
+        let token = self.send_otp(user_id, &payload.new_email, locale).await?;
+        if let Err(err) = self
+            .smtp_service
+            .send_email_change_notice(&user.email, &payload.new_email, locale)
+            .await
+        {
+            eprintln!(
+                "Failed to notify {} about the email change: {}",
+                user.email, err
+            );
+        }
+
+        Ok(EmailChangeResponse {
+            token,
+            timeout_seconds: TOKEN_LIFETIME_SEC as u16,
+        })
+    }
+
+    pub async fn resend_otp(
+        &self,
+        user_id: i32,
+        payload: ResendEmailChangeOTPRequest,
+    ) -> AppResult<EmailChangeResponse> {
+        let token_data = self.get_token_data(user_id, &payload.token)?;
+        self.get_current_data(user_id, &payload.token).await?;
+
+        if token_data.created_at + RESEND_OTP_TIMEOUT_SEC > get_current_timestamp() {
+            return Err(AppError::TooManyRequests(
+                "OTP was sent recently. Please wait before requesting a new one.".to_string(),
+                Some(ErrorCode::ResendOTPTooManyRequests),
+            ));
+        }
+
+        let token = self
+            .send_otp(user_id, &token_data.new_email, token_data.locale)
+            .await?;
+        Ok(EmailChangeResponse {
+            token,
+            timeout_seconds: TOKEN_LIFETIME_SEC as u16,
+        })
+    }
+
+    /// Checks the code and commits the change. Every other session is signed
+    /// out; `current` is the session id or token family the change was made with.
+    pub async fn verify_otp(
+        &self,
+        user_id: i32,
+        payload: VerifyEmailChangeRequest,
+        ip: IpAddr,
+        current: Option<&str>,
+    ) -> AppResult<()> {
+        let token_data = self.get_token_data(user_id, &payload.token)?;
+        let cached = self.get_current_data(user_id, &payload.token).await?;
+
+        let attempt = self
+            .login_guard_service
+            .reserve(AttemptKind::Otp(OtpFlow::EmailChange), &token_data.jti, ip)
+            .await?;
+        if !self.otp_service.verify_otp_hash(
+            &token_data.new_email,
+            &token_data.jti,
+            &payload.otp,
+            &cached.otp_hash,
+        ) {
+            if attempt.is_last() {
+                self.clear_cache(user_id).await?;
+                return Err(otp_attempts_exceeded());
+            }
+            return Err(AppError::Unauthorized(
+                "Неверный OTP".to_string(),
+                Some(ErrorCode::WrongOTP),
+            ));
+        }
+
+        self.login_guard_service.succeed(attempt).await?;
+        let user = self.users_repository.get_user_by_id(user_id).await?;
+        // The code is only spent once the change is stored
+        self.users_repository
+            .update_email(user_id, &token_data.new_email)
+            .await?;
+        self.clear_cache(user_id).await?;
+        self.auth_service
+            .revoke_all_sessions(user_id, current)
+            .await?;
+
+        let locale = Locale::parse(&user.locale).unwrap_or_default();
+        if let Err(err) = self
+            .smtp_service
+            .send_email_changed(&user.email, &token_data.new_email, locale)
+            .await
+        {
+            eprintln!(
+                "Failed to notify {} about the email change: {}",
+                user.email, err
+            );
+        }
+        Ok(())
+    }
+
+    fn get_token_data(&self, user_id: i32, token: &str) -> AppResult<TokenData> {
+        let token_data = self
+            .token_service
+            .get_claims_from_jwt::<TokenData>(token, TokenType::EmailChange)?;
+        if token_data.user_id != user_id {
+            return Err(AppError::Unauthorized(
+                "Ошибка при проверке OTP".to_string(),
+                Some(ErrorCode::WrongOTPToken),
+            ));
+        }
+        Ok(token_data)
+    }
+
+    async fn send_otp(&self, user_id: i32, new_email: &str, locale: Locale) -> AppResult<String> {
+        let otp = self.otp_service.generate(6)?;
+        let jti = new_token_id();
+        let now = get_current_timestamp();
+        let token = self.token_service.create_jwt(TokenData {
+            user_id,
+            new_email: new_email.to_string(),
+            jti: jti.clone(),
+            exp: now + TOKEN_LIFETIME_SEC,
+            token_type: TokenType::EmailChange,
+            created_at: now,
+            locale,
+        })?;
+
+        self.smtp_service
+            .send_email_change_otp(new_email, otp, locale)
+            .await?;
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::EMAIL_CHANGE_OTP(user_id).build_key();
+        let otp_hash = self
+            .otp_service
+            .make_otp_hash(new_email, &jti, &otp.to_string());
+        let _: () = con
+            .hset_multiple(
+                &key,
+                &[
+                    ("otp_hash", otp_hash),
+                    ("new_email", new_email.to_string()),
+                    ("token", token.clone()),
+                ],
+            )
+            .await?;
+        let _: () = con.expire(&key, CACHE_LIFETIME_SEC).await?;
+        Ok(token)
+    }
+
+    /// Cached data of the user, if `token` is the one the code was sent with.
+    async fn get_current_data(
+        &self,
+        user_id: i32,
+        token: &str,
+    ) -> AppResult<CachedEmailChangeParams> {
+        let cached = self.get_cached_data(user_id).await?.ok_or_else(|| {
+            AppError::Unauthorized("OTP expired".to_string(), Some(ErrorCode::OTPExpired))
+        })?;
+        if !tokens_match(token, &cached.token) {
+            return Err(AppError::Unauthorized(
+                "Ошибка при проверке OTP".to_string(),
+                Some(ErrorCode::WrongOTPToken),
+            ));
+        }
+        Ok(cached)
+    }
+
+    async fn get_cached_data(&self, user_id: i32) -> AppResult<Option<CachedEmailChangeParams>> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::EMAIL_CHANGE_OTP(user_id).build_key();
+        let (otp_hash, new_email, token): (Option<String>, Option<String>, Option<String>) =
+            redis::cmd("HMGET")
+                .arg(&key)
+                .arg("otp_hash")
+                .arg("new_email")
+                .arg("token")
+                .query_async(&mut con)
+                .await?;
+        Ok(match (otp_hash, new_email, token) {
+            (Some(otp_hash), Some(new_email), Some(token)) => Some(CachedEmailChangeParams {
+                otp_hash,
+                new_email,
+                token,
+            }),
+            _ => None,
+        })
+    }
+
+    async fn clear_cache(&self, user_id: i32) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::EMAIL_CHANGE_OTP(user_id).build_key();
+        let _: () = con.del(&key).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+use std::{net::IpAddr, sync::Arc};
+
+use crate::{
+    config::login_guard::LoginGuardConfig,
+    error::app_error::{AppError, AppResult, ErrorCode},
+    infrastucture::cache::{client::Cache, keys::AppCacheKey},
+};
+
+/// Upper bound of the delay between two failed attempts.
+const MAX_DELAY_SEC: u64 = 30;
+
+/// Counts an attempt against the IP (`KEYS[1]`) and the subject (`KEYS[2]`),
+/// unless one of them has to wait or is locked out. Checking and counting in
+/// one step keeps parallel guesses from all passing the check before any of
+/// them is counted.
+///
+/// `ARGV`: now, lockout seconds, IP limit, subject limit, then the delay
+/// after 0, 1, 2... failures. Returns `{0, subject failures}` when the
+/// attempt is allowed, `{1, seconds}` to wait, or `{2 | 3, seconds}` while
+/// the IP or the subject is locked.
+const RESERVE_SCRIPT: &str = r"
+local now = tonumber(ARGV[1])
+local delays = #ARGV - 4
+for i, key in ipairs(KEYS) do
+    local state = redis.call('HMGET', key, 'failures', 'last_failed_at')
+    local failures = tonumber(state[1]) or 0
+    if failures >= tonumber(ARGV[2 + i]) then
+        return {i + 1, math.max(redis.call('TTL', key), 1)}
+    end
+    local ready_at = (tonumber(state[2]) or 0) + tonumber(ARGV[5 + math.min(failures, delays - 1)])
+    if now < ready_at then
+        return {1, ready_at - now}
+    end
+end
+local failures = 0
+for _, key in ipairs(KEYS) do
+    failures = redis.call('HINCRBY', key, 'failures', 1)
+    redis.call('HSET', key, 'last_failed_at', now)
+    redis.call('EXPIRE', key, ARGV[2])
+end
+return {0, failures}
+";
+
+/// Gives back the attempt counted against the IP (`KEYS[1]`) and forgets the
+/// failures of the subject (`KEYS[2]`).
+const SUCCEED_SCRIPT: &str = r"
+if redis.call('HINCRBY', KEYS[1], 'failures', -1) <= 0 then
+    redis.call('DEL', KEYS[1])
+end
+redis.call('DEL', KEYS[2])
+";
+
+/// What is being guessed. Sign-ins are counted per email, OTP guesses per
+/// flow and code, so a code only ever shares its counter with itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttemptKind {
+    SignIn,
+    Otp(OtpFlow),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OtpFlow {
+    SignUp,
+    Restore,
+    EmailChange,
+}
+
+impl AttemptKind {
+    fn name(&self) -> &'static str {
+        match self {
+            AttemptKind::SignIn => "sign_in",
+            AttemptKind::Otp(_) => "otp",
+        }
+    }
+}
+
+impl OtpFlow {
+    fn name(&self) -> &'static str {
+        match self {
+            OtpFlow::SignUp => "sign_up",
+            OtpFlow::Restore => "restore",
+            OtpFlow::EmailChange => "email_change",
+        }
+    }
+}
+
+/// An attempt that is already counted as failed. It is given back with
+/// [`LoginGuardService::succeed`] if the guess turns out right.
+#[derive(Debug)]
+pub struct Attempt {
+    kind: AttemptKind,
+    subject: String,
+    ip: IpAddr,
+    failures: u32,
+    max_failures: u32,
+}
+
+impl Attempt {
+    /// Whether failing this attempt locks the subject out.
+    pub fn is_last(&self) -> bool {
+        self.failures >= self.max_failures
+    }
+}
+
+/// Counts failed password and OTP guesses per subject and per IP, slowing
+/// them down progressively and blocking them after the configured limit.
+pub struct LoginGuardService {
+    cache: Arc<Cache>,
+    config: LoginGuardConfig,
+    reserve_script: redis::Script,
+    succeed_script: redis::Script,
+}
+
+impl LoginGuardService {
+    pub fn new(cache: Arc<Cache>, config: LoginGuardConfig) -> Self {
+        LoginGuardService {
+            cache,
+            config,
+            reserve_script: redis::Script::new(RESERVE_SCRIPT),
+            succeed_script: redis::Script::new(SUCCEED_SCRIPT),
+        }
+    }
+
+    /// Count an attempt before the guess is compared. The subject is the email
+    /// for a sign-in and the jti of the code for an OTP. Fails while the
+    /// subject or the IP has to wait or is locked out.
+    pub async fn reserve(
+        &self,
+        kind: AttemptKind,
+        subject: &str,
+        ip: IpAddr,
+    ) -> AppResult<Attempt> {
+        let max_failures = self.max_failures(kind);
+        let max_ip_failures = self.config.max_ip_failures;
+
+        let mut con = self.cache.get_async_conn().await?;
+        let mut invocation = self.reserve_script.prepare_invoke();
+        invocation
+            .key(ip_key(kind, ip))
+            .key(subject_key(kind, subject))
+            .arg(now())
+            .arg(self.config.lockout_sec)
+            .arg(max_ip_failures)
+            .arg(max_failures);
+        for failures in 0..max_failures.max(max_ip_failures) {
+            invocation.arg(progressive_delay(failures));
+        }
+        let (status, value): (u8, u64) = invocation.invoke_async(&mut con).await?;
+
+        match status {
+            0 => Ok(Attempt {
+                kind,
+                subject: subject.to_string(),
+                ip,
+                failures: value as u32,
+                max_failures,
+            }),
+            1 => Err(wait_error(value)),
+            2 => Err(AppError::TooManyRequests(
+                format!(
+                    "Too many failed attempts from this address, retry in {} seconds",
+                    value
+                ),
+                Some(ErrorCode::TooManyFailedAttempts),
+            )),
+            _ => Err(locked_error(kind, value)),
+        }
+    }
+
+    /// The guess was right: give the attempt back and forget the failures of
+    /// the subject.
+    pub async fn succeed(&self, attempt: Attempt) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let _: () = self
+            .succeed_script
+            .key(ip_key(attempt.kind, attempt.ip))
+            .key(subject_key(attempt.kind, &attempt.subject))
+            .invoke_async(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    pub fn lockout_minutes(&self) -> u32 {
+        self.config.lockout_sec.div_ceil(60)
+    }
+
+    fn max_failures(&self, kind: AttemptKind) -> u32 {
+        match kind {
+            AttemptKind::SignIn => self.config.max_sign_in_failures,
+            AttemptKind::Otp(_) => self.config.max_otp_failures,
+        }
+    }
+}
+
+fn ip_key(kind: AttemptKind, ip: IpAddr) -> String {
+    AppCacheKey::AUTH_FAILURES(kind.name(), &format!("ip:{}", ip)).build_key()
+}
+
+fn subject_key(kind: AttemptKind, subject: &str) -> String {
+    let subject = match kind {
+        AttemptKind::SignIn => format!("email:{}", subject),
+        AttemptKind::Otp(flow) => format!("{}:{}", flow.name(), subject),
+    };
+    AppCacheKey::AUTH_FAILURES(kind.name(), &subject).build_key()
+}
+
+/// Seconds to wait after the given number of consecutive failures: none after
+/// the first, then doubling up to `MAX_DELAY_SEC`.
+fn progressive_delay(failures: u32) -> u64 {
+    if failures < 2 {
+        return 0;
+    }
+    (1u64 << (failures - 2).min(6)).min(MAX_DELAY_SEC)
+}
+
+fn wait_error(seconds: u64) -> AppError {
+    AppError::TooManyRequests(
+        format!("Too many failed attempts, retry in {} seconds", seconds),
+        Some(ErrorCode::TooManyFailedAttempts),
+    )
+}
+
+fn locked_error(kind: AttemptKind, remaining_sec: u64) -> AppError {
+    match kind {
+        AttemptKind::SignIn => AppError::TooManyRequests(
+            format!(
+                "Account is locked after too many failed sign-ins, retry in {} minutes",
+                remaining_sec.div_ceil(60)
+            ),
+            Some(ErrorCode::AccountLocked),
+        ),
+        AttemptKind::Otp(_) => otp_attempts_exceeded(),
+    }
+}
+
+pub fn otp_attempts_exceeded() -> AppError {
+    AppError::Unauthorized(
+        "Too many wrong codes, request a new one".to_string(),
+        Some(ErrorCode::OTPAttemptsExceeded),
+    )
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::AsyncCommands;
+
+    use super::*;
+
+    const OTP: AttemptKind = AttemptKind::Otp(OtpFlow::SignUp);
+
+    #[test]
+    fn test_progressive_delay() {
+        let delays: Vec<u64> = (0..=8).map(progressive_delay).collect();
+        assert_eq!(delays, vec![0, 0, 1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(progressive_delay(u32::MAX), MAX_DELAY_SEC);
+    }
+
+    #[test]
+    fn test_otp_keys_are_scoped_to_flow_and_code() {
+        let sign_up = AttemptKind::Otp(OtpFlow::SignUp);
+        let restore = AttemptKind::Otp(OtpFlow::Restore);
+
+        assert_ne!(subject_key(sign_up, "jti"), subject_key(restore, "jti"));
+        assert_ne!(subject_key(sign_up, "jti"), subject_key(sign_up, "other"));
+        // The IP limit still covers every flow
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert_eq!(ip_key(sign_up, ip), ip_key(restore, ip));
+    }
+
+    fn guard(max_otp_failures: u32, max_ip_failures: u32) -> LoginGuardService {
+        let url = std::env::var("TEST_REDIS_URL")
+            .unwrap_or_else(|_| "redis://localhost:6379".to_string());
+        LoginGuardService::new(
+            Arc::new(Cache::new(&url)),
+            LoginGuardConfig {
+                max_sign_in_failures: 5,
+                max_otp_failures,
+                max_ip_failures,
+                lockout_sec: 60,
+            },
+        )
+    }
+
+    fn unique_jti() -> String {
+        format!("guard_{}", now_nanos())
+    }
+
+    fn unique_ip() -> IpAddr {
+        IpAddr::from((now_nanos() as u32).to_be_bytes())
+    }
+
+    fn now_nanos() -> u128 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Redis at TEST_REDIS_URL"]
+    async fn test_concurrent_guesses_are_limited() {
+        let guard = Arc::new(guard(5, 1000));
+        let jti = unique_jti();
+        let ip = unique_ip();
+
+        let guesses = (0..50).map(|_| {
+            let guard = guard.clone();
+            let jti = jti.clone();
+            tokio::spawn(async move { guard.reserve(OTP, &jti, ip).await })
+        });
+        let evaluated = futures::future::join_all(guesses)
+            .await
+            .into_iter()
+            .filter(|result| result.as_ref().unwrap().is_ok())
+            .count();
+
+        assert!(evaluated >= 1);
+        assert!(evaluated <= 5, "{} guesses were evaluated", evaluated);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Redis at TEST_REDIS_URL"]
+    async fn test_last_attempt_locks_and_success_refunds() {
+        let guard = guard(2, 1000);
+        let jti = unique_jti();
+        let ip = unique_ip();
+
+        let first = guard.reserve(OTP, &jti, ip).await.unwrap();
+        assert!(!first.is_last());
+        guard.succeed(first).await.unwrap();
+
+        // The right guess counted neither against the code nor the IP
+        let mut con = guard.cache.get_async_conn().await.unwrap();
+        let exists: bool = con.exists(ip_key(OTP, ip)).await.unwrap();
+        assert!(!exists);
+
+        let first = guard.reserve(OTP, &jti, ip).await.unwrap();
+        assert!(!first.is_last());
+        let second = guard.reserve(OTP, &jti, ip).await.unwrap();
+        assert!(second.is_last());
+        assert!(matches!(
+            guard.reserve(OTP, &jti, ip).await,
+            Err(AppError::Unauthorized(
+                _,
+                Some(ErrorCode::OTPAttemptsExceeded)
+            ))
+        ));
+
+        // A new code, or the same jti in another flow, starts from scratch
+        assert!(guard.reserve(OTP, &unique_jti(), ip).await.is_ok());
+        assert!(
+            guard
+                .reserve(AttemptKind::Otp(OtpFlow::Restore), &jti, ip)
+                .await
+                .is_ok()
+        );
+    }
+}
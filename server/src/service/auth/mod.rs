@@ -1,3 +1,7 @@
 pub mod auth_service;
+pub mod email_change_service;
+pub mod login_guard_service;
 pub mod restore_service;
+pub mod session_store;
 pub mod sign_up_service;
+pub mod token_auth_service;
@@ -1,31 +1,51 @@
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
+use jsonwebtoken::get_current_timestamp;
+use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     dto::{
-        request::auth::restore::StartRestoreRequest, response::auth::restore::StartRestoreResponse,
+        request::auth::restore::{
+            CompleteRestoreRequest, StartRestoreRequest, VerifyRestoreOTPRequest,
+        },
+        response::auth::restore::StartRestoreResponse,
+    },
+    error::app_error::{AppError, AppResult, ErrorCode},
+    infrastucture::{
+        cache::{client::Cache, keys::AppCacheKey},
+        repositories::users_repository::UsersRepository,
     },
-    error::app_error::AppResult,
-    infrastucture::{cache::client::Cache, repositories::users_repository::UsersRepository},
     service::{
+        auth::{
+            auth_service::{AuthService, hash_password},
+            login_guard_service::{AttemptKind, LoginGuardService, OtpFlow, otp_attempts_exceeded},
+        },
+        email_template::Locale,
         otp_service::OTPService,
         smtp_service::SMTPService,
-        token_service::{Token, TokenService, TokenType},
+        token_service::{Token, TokenService, TokenType, new_token_id, tokens_match},
     },
 };
 
+const TOKEN_LIFETIME_SEC: u64 = 60 * 11;
+const CACHE_LIFETIME_SEC: i64 = 60 * 10;
+
 pub struct RestoreService {
     cache: Arc<Cache>,
     otp_service: Arc<OTPService>,
     smtp_service: Arc<SMTPService>,
     users_repository: Arc<UsersRepository>,
     token_service: Arc<TokenService>,
+    login_guard_service: Arc<LoginGuardService>,
+    auth_service: Arc<AuthService>,
 }
 
 #[derive(Serialize, Deserialize)]
 struct TokenData {
     email: String,
+    /// Id the stored OTP hash is bound to.
+    jti: String,
     exp: u64,
     token_type: TokenType,
 }
@@ -39,6 +59,12 @@ impl Token for TokenData {
     }
 }
 
+struct CachedRestoreOtpParams {
+    otp_hash: String,
+    token: String,
+    verified: bool,
+}
+
 impl RestoreService {
     pub fn new(
         cache: Arc<Cache>,
@@ -46,6 +72,8 @@ impl RestoreService {
         smtp_service: Arc<SMTPService>,
         users_repository: Arc<UsersRepository>,
         token_service: Arc<TokenService>,
+        login_guard_service: Arc<LoginGuardService>,
+        auth_service: Arc<AuthService>,
     ) -> Self {
         RestoreService {
             cache,
@@ -53,6 +81,176 @@ impl RestoreService {
             smtp_service,
             users_repository,
             token_service,
+            login_guard_service,
+            auth_service,
+        }
+    }
+
+    pub async fn start_restore(
+        &self,
+        payload: StartRestoreRequest,
+    ) -> AppResult<StartRestoreResponse> {
+        let user = self
+            .users_repository
+            .get_user_by_email(&payload.email)
+            .await?;
+
+        // A code that was already sent stays valid until it expires
+        if let Some(cached) = self.get_cached_data(&user.email).await? {
+            if let Ok(token_data) = self
+                .token_service
+                .get_claims_from_jwt::<TokenData>(&cached.token, TokenType::Restore)
+            {
+                return Ok(StartRestoreResponse {
+                    token: cached.token,
+                    timeout_seconds: token_data.exp.saturating_sub(get_current_timestamp()) as u16,
+                });
+            }
+        }
+
+        let otp = self.otp_service.generate(6)?;
+        let jti = new_token_id();
+        let token = self.token_service.create_jwt(TokenData {
+            email: user.email.clone(),
+            jti: jti.clone(),
+            exp: get_current_timestamp() + TOKEN_LIFETIME_SEC,
+            token_type: TokenType::Restore,
+        })?;
+
+        let locale = Locale::parse(&user.locale).unwrap_or_default();
+        self.smtp_service
+            .send_restore_otp(&user.email, otp, locale)
+            .await?;
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::RESTORE_OTP(&user.email).build_key();
+        let otp_hash = self
+            .otp_service
+            .make_otp_hash(&user.email, &jti, &otp.to_string());
+        let _: () = con
+            .hset_multiple(
+                &key,
+                &[
+                    ("otp_hash", otp_hash),
+                    ("token", token.clone()),
+                    ("verified", "false".to_string()),
+                ],
+            )
+            .await?;
+        let _: () = con.expire(&key, CACHE_LIFETIME_SEC).await?;
+
+        Ok(StartRestoreResponse {
+            token,
+            timeout_seconds: TOKEN_LIFETIME_SEC as u16,
+        })
+    }
+
+    pub async fn verify_otp(&self, payload: VerifyRestoreOTPRequest, ip: IpAddr) -> AppResult<()> {
+        let token_data = self
+            .token_service
+            .get_claims_from_jwt::<TokenData>(&payload.token, TokenType::Restore)?;
+        let cached = self
+            .get_current_data(&token_data.email, &payload.token)
+            .await?;
+
+        let attempt = self
+            .login_guard_service
+            .reserve(AttemptKind::Otp(OtpFlow::Restore), &token_data.jti, ip)
+            .await?;
+        if !self.otp_service.verify_otp_hash(
+            &token_data.email,
+            &token_data.jti,
+            &payload.otp,
+            &cached.otp_hash,
+        ) {
+            if attempt.is_last() {
+                self.clear_cache(&token_data.email).await?;
+                return Err(otp_attempts_exceeded());
+            }
+            return Err(AppError::Unauthorized(
+                "Неверный OTP".to_string(),
+                Some(ErrorCode::WrongOTP),
+            ));
+        }
+
+        self.login_guard_service.succeed(attempt).await?;
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::RESTORE_OTP(&token_data.email).build_key();
+        let _: () = con.hset(&key, "verified", "true").await?;
+        Ok(())
+    }
+
+    pub async fn complete_restore(&self, payload: CompleteRestoreRequest) -> AppResult<()> {
+        let token_data = self
+            .token_service
+            .get_claims_from_jwt::<TokenData>(&payload.token, TokenType::Restore)?;
+        let cached = self
+            .get_current_data(&token_data.email, &payload.token)
+            .await?;
+        if !cached.verified {
+            return Err(AppError::Unauthorized(
+                "OTP not verified".to_string(),
+                Some(ErrorCode::OTPNotVerified),
+            ));
+        }
+
+        let user = self
+            .users_repository
+            .get_user_by_email(&token_data.email)
+            .await?;
+        let password_hash = hash_password(&payload.password)?;
+        self.users_repository
+            .update_password(user.id, &password_hash)
+            .await?;
+        // Whoever knew the old password is signed out
+        self.auth_service.revoke_all_sessions(user.id, None).await?;
+        self.clear_cache(&token_data.email).await?;
+        Ok(())
+    }
+
+    /// Cached data of the email, if `token` is the one it was sent with.
+    async fn get_current_data(
+        &self,
+        email: &str,
+        token: &str,
+    ) -> AppResult<CachedRestoreOtpParams> {
+        let cached = self.get_cached_data(email).await?.ok_or_else(|| {
+            AppError::Unauthorized("OTP expired".to_string(), Some(ErrorCode::OTPExpired))
+        })?;
+        if !tokens_match(token, &cached.token) {
+            return Err(AppError::Unauthorized(
+                "Ошибка при проверке OTP".to_string(),
+                Some(ErrorCode::WrongOTPToken),
+            ));
         }
+        Ok(cached)
+    }
+
+    async fn get_cached_data(&self, email: &str) -> AppResult<Option<CachedRestoreOtpParams>> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::RESTORE_OTP(email).build_key();
+        let (otp_hash, token, verified): (Option<String>, Option<String>, Option<String>) =
+            redis::cmd("HMGET")
+                .arg(&key)
+                .arg("otp_hash")
+                .arg("token")
+                .arg("verified")
+                .query_async(&mut con)
+                .await?;
+        Ok(match (otp_hash, token) {
+            (Some(otp_hash), Some(token)) => Some(CachedRestoreOtpParams {
+                otp_hash,
+                token,
+                verified: verified.as_deref() == Some("true"),
+            }),
+            _ => None,
+        })
+    }
+
+    async fn clear_cache(&self, email: &str) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::RESTORE_OTP(email).build_key();
+        let _: () = con.del(&key).await?;
+        Ok(())
     }
 }
@@ -0,0 +1,297 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rand::{Rng, distributions::Alphanumeric, thread_rng};
+use redis::AsyncCommands;
+
+use crate::{
+    dto::response::auth::session::{SessionKind, SessionResponse},
+    error::app_error::{AppError, AppResult},
+    infrastucture::cache::{client::Cache, keys::AppCacheKey},
+    service::auth::auth_service::{SessionMeta, unix_timestamp},
+};
+
+pub const SESSION_LIFETIME_SEC: i64 = 60 * 60 * 24 * 7;
+
+/// Slide the expiry of a session (`KEYS[1]`). Sessions started before they
+/// had a public id get `ARGV[3]`. Returns nil when the session is gone.
+const TOUCH_SCRIPT: &str = r"
+local state = redis.call('HMGET', KEYS[1], 'created_at', 'last_updated', 'user_id', 'public_id')
+if not state[3] then
+    return false
+end
+local public_id = state[4]
+if not public_id then
+    public_id = ARGV[3]
+end
+redis.call('HSET', KEYS[1], 'last_updated', ARGV[1], 'public_id', public_id)
+redis.call('EXPIRE', KEYS[1], ARGV[2])
+return {state[1], state[2], state[3], public_id}
+";
+
+pub struct CachedSession {
+    pub session_id: String,
+    pub created_at: i64,
+    pub last_updated: i64,
+    pub user_id: i32,
+}
+
+/// Cookie sessions and the index of them per user, which the list of
+/// sessions and "log out everywhere" go through. The index lives as long as
+/// the most recently used session of the user.
+pub struct SessionStore {
+    cache: Arc<Cache>,
+    touch_script: redis::Script,
+}
+
+impl SessionStore {
+    pub fn new(cache: Arc<Cache>) -> Self {
+        SessionStore {
+            cache,
+            touch_script: redis::Script::new(TOUCH_SCRIPT),
+        }
+    }
+
+    /// Start a session and return its id, the value of the cookie.
+    pub async fn create(&self, user_id: i32, meta: &SessionMeta) -> AppResult<String> {
+        let session_id = random_id(32);
+        // The session id is a credential, the list of sessions shows this one
+        let public_id = random_id(16);
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::SESSION(&session_id).build_key();
+        let timestamp = unix_timestamp().to_string();
+        let mut fields = vec![
+            ("session_id", session_id.clone()),
+            ("public_id", public_id.clone()),
+            ("created_at", timestamp.clone()),
+            ("last_updated", timestamp),
+            ("user_id", user_id.to_string()),
+            ("ip", meta.ip.to_string()),
+        ];
+        if let Some(user_agent) = &meta.user_agent {
+            fields.push(("user_agent", user_agent.clone()));
+        }
+        let _: () = con.hset_multiple(&key, &fields).await?;
+        let _: () = con.expire(&key, SESSION_LIFETIME_SEC).await?;
+
+        self.index(user_id, &public_id, &session_id).await?;
+        Ok(session_id)
+    }
+
+    /// The session behind a cookie, marked as used just now.
+    pub async fn touch(&self, session_id: &str) -> AppResult<CachedSession> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::SESSION(session_id).build_key();
+        let state: Option<(i64, i64, i32, String)> = self
+            .touch_script
+            .key(&key)
+            .arg(unix_timestamp())
+            .arg(SESSION_LIFETIME_SEC)
+            .arg(random_id(16))
+            .invoke_async(&mut con)
+            .await?;
+        let Some((created_at, last_updated, user_id, public_id)) = state else {
+            return Err(AppError::Unauthorized("Session expired".to_string(), None));
+        };
+
+        self.index(user_id, &public_id, session_id).await?;
+        Ok(CachedSession {
+            session_id: session_id.to_string(),
+            created_at,
+            last_updated,
+            user_id,
+        })
+    }
+
+    pub async fn delete(&self, session_id: &str) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::SESSION(session_id).build_key();
+        let (user_id, public_id): (Option<i32>, Option<String>) = redis::cmd("HMGET")
+            .arg(&key)
+            .arg("user_id")
+            .arg("public_id")
+            .query_async(&mut con)
+            .await
+            .unwrap_or((None, None));
+
+        let _ = con.del::<_, ()>(&key).await;
+        if let (Some(user_id), Some(public_id)) = (user_id, public_id) {
+            let index_key = AppCacheKey::USER_SESSIONS(user_id).build_key();
+            let _: () = con.hdel(&index_key, &public_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Sessions of the user. Sessions that expired are dropped from the index
+    /// on the way.
+    pub async fn list(
+        &self,
+        user_id: i32,
+        current: Option<&str>,
+    ) -> AppResult<Vec<SessionResponse>> {
+        let mut con = self.cache.get_async_conn().await?;
+        let index_key = AppCacheKey::USER_SESSIONS(user_id).build_key();
+        let index: HashMap<String, String> = con.hgetall(&index_key).await?;
+
+        let mut sessions = Vec::with_capacity(index.len());
+        for (public_id, session_id) in index {
+            let key = AppCacheKey::SESSION(&session_id).build_key();
+            let fields: HashMap<String, String> = con.hgetall(&key).await?;
+            if fields.is_empty() {
+                let _: () = con.hdel(&index_key, &public_id).await?;
+                continue;
+            }
+            let is_current = current == Some(session_id.as_str());
+            sessions.push(SessionResponse::from_cache(
+                public_id,
+                SessionKind::Cookie,
+                &fields,
+                is_current,
+            ));
+        }
+        Ok(sessions)
+    }
+
+    /// Revokes a session by its public id. Returns whether there was one.
+    pub async fn revoke(&self, user_id: i32, public_id: &str) -> AppResult<bool> {
+        let mut con = self.cache.get_async_conn().await?;
+        let index_key = AppCacheKey::USER_SESSIONS(user_id).build_key();
+        let session_id: Option<String> = con.hget(&index_key, public_id).await?;
+        let Some(session_id) = session_id else {
+            return Ok(false);
+        };
+        let key = AppCacheKey::SESSION(&session_id).build_key();
+        let _: () = con.del(&key).await?;
+        let _: () = con.hdel(&index_key, public_id).await?;
+        Ok(true)
+    }
+
+    /// Revokes every session of the user except `keep`.
+    pub async fn revoke_all(&self, user_id: i32, keep: Option<&str>) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let index_key = AppCacheKey::USER_SESSIONS(user_id).build_key();
+        let index: HashMap<String, String> = con.hgetall(&index_key).await?;
+        for (public_id, session_id) in index {
+            if keep == Some(session_id.as_str()) {
+                continue;
+            }
+            let key = AppCacheKey::SESSION(&session_id).build_key();
+            let _: () = con.del(&key).await?;
+            let _: () = con.hdel(&index_key, &public_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn index(&self, user_id: i32, public_id: &str, session_id: &str) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let index_key = AppCacheKey::USER_SESSIONS(user_id).build_key();
+        let _: () = redis::pipe()
+            .atomic()
+            .hset(&index_key, public_id, session_id)
+            .ignore()
+            .expire(&index_key, SESSION_LIFETIME_SEC)
+            .ignore()
+            .query_async(&mut con)
+            .await?;
+        Ok(())
+    }
+}
+
+fn random_id(len: usize) -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> SessionStore {
+        let url = std::env::var("TEST_REDIS_URL")
+            .unwrap_or_else(|_| "redis://localhost:6379".to_string());
+        SessionStore::new(Arc::new(Cache::new(&url)))
+    }
+
+    fn meta() -> SessionMeta {
+        SessionMeta {
+            ip: "203.0.113.7".parse().unwrap(),
+            user_agent: Some("test".to_string()),
+        }
+    }
+
+    /// Negative, so it never matches a real user.
+    fn unique_user_id() -> i32 {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        -1 - nanos as i32
+    }
+
+    async fn drop_index(store: &SessionStore, user_id: i32) {
+        let mut con = store.cache.get_async_conn().await.unwrap();
+        let _: () = con
+            .del(AppCacheKey::USER_SESSIONS(user_id).build_key())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Redis at TEST_REDIS_URL"]
+    async fn test_used_session_is_indexed_again_after_the_index_expired() {
+        let store = store();
+        let user_id = unique_user_id();
+        let kept = store.create(user_id, &meta()).await.unwrap();
+        let other = store.create(user_id, &meta()).await.unwrap();
+
+        drop_index(&store, user_id).await;
+        assert!(store.list(user_id, None).await.unwrap().is_empty());
+
+        store.touch(&kept).await.unwrap();
+        store.touch(&other).await.unwrap();
+        assert_eq!(store.list(user_id, None).await.unwrap().len(), 2);
+
+        store.revoke_all(user_id, Some(&kept)).await.unwrap();
+        assert!(store.touch(&kept).await.is_ok());
+        assert!(matches!(
+            store.touch(&other).await,
+            Err(AppError::Unauthorized(..))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Redis at TEST_REDIS_URL"]
+    async fn test_sessions_without_public_id_get_one_when_used() {
+        let store = store();
+        let user_id = unique_user_id();
+        let session_id = random_id(32);
+
+        // Stored the way sessions were before they were indexed
+        let mut con = store.cache.get_async_conn().await.unwrap();
+        let key = AppCacheKey::SESSION(&session_id).build_key();
+        let _: () = con
+            .hset_multiple(
+                &key,
+                &[
+                    ("session_id", session_id.clone()),
+                    ("created_at", "1".to_string()),
+                    ("last_updated", "1".to_string()),
+                    ("user_id", user_id.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        store.touch(&session_id).await.unwrap();
+        let sessions = store.list(user_id, Some(&session_id)).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].current);
+
+        store.revoke_all(user_id, None).await.unwrap();
+        let exists: bool = con.exists(&key).await.unwrap();
+        assert!(!exists);
+    }
+}
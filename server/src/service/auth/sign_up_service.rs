@@ -1,4 +1,5 @@
 use std::{
+    net::IpAddr,
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -16,13 +17,17 @@ use crate::{
         database::models::NewUser,
         repositories::users_repository::UsersRepository,
     },
-    service::token_service::{Token, TokenService, TokenType},
+    service::{
+        auth::auth_service::hash_password,
+        auth::login_guard_service::{
+            AttemptKind, LoginGuardService, OtpFlow, otp_attempts_exceeded,
+        },
+        email_template::Locale,
+        token_service::{Token, TokenService, TokenType, new_token_id, tokens_match},
+    },
 };
 
-use argon2::{Argon2, PasswordHasher, password_hash::SaltString};
-
 use jsonwebtoken::get_current_timestamp;
-use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -38,9 +43,14 @@ const RESEND_OTP_TIMEOUT_SEC: u64 = 60;
 #[derive(Serialize, Deserialize)]
 struct TokenData {
     email: String,
+    /// Id the stored OTP hash is bound to.
+    jti: String,
     exp: u64,
     token_type: TokenType,
     created_at: u64,
+    /// Language the sign-up started in, kept for the account.
+    #[serde(default)]
+    locale: Locale,
 }
 
 impl Token for TokenData {
@@ -55,7 +65,7 @@ impl Token for TokenData {
 
 #[derive(Serialize, Deserialize)]
 struct CachedSignUpOtpParams {
-    otp_value: String,
+    otp_hash: String,
     send_timestamp_seconds: u64,
     verified: bool,
     token: String,
@@ -67,6 +77,7 @@ pub struct SignUpService {
     smtp_service: Arc<SMTPService>,
     users_repository: Arc<UsersRepository>,
     token_service: Arc<TokenService>,
+    login_guard_service: Arc<LoginGuardService>,
 }
 
 impl SignUpService {
@@ -76,6 +87,7 @@ impl SignUpService {
         smtp_service: Arc<SMTPService>,
         users_repository: Arc<UsersRepository>,
         token_service: Arc<TokenService>,
+        login_guard_service: Arc<LoginGuardService>,
     ) -> Self {
         SignUpService {
             cache,
@@ -83,12 +95,14 @@ impl SignUpService {
             smtp_service,
             users_repository,
             token_service,
+            login_guard_service,
         }
     }
 
     pub async fn start_sign_up(
         &self,
         payload: SignUpStartRequest,
+        locale: Locale,
     ) -> AppResult<SignUpStartResponse> {
         if self.is_user_exists_in_database(&payload.email).await? {
             return Err(AppError::BadRequest(
@@ -110,7 +124,7 @@ impl SignUpService {
             Err(_) => (),
         }
 
-        let token = self.send_otp(payload.email).await?;
+        let token = self.send_otp(payload.email, locale).await?;
 
         Ok(SignUpStartResponse {
             token,
@@ -118,7 +132,7 @@ impl SignUpService {
         })
     }
 
-    pub async fn verify_otp(&self, payload: VerifyOTPRequest) -> AppResult<()> {
+    pub async fn verify_otp(&self, payload: VerifyOTPRequest, ip: IpAddr) -> AppResult<()> {
         let token_data = self
             .token_service
             .get_claims_from_jwt::<TokenData>(&payload.token, TokenType::SignUp)?;
@@ -133,13 +147,28 @@ impl SignUpService {
         };
 
         self.compare_tokens(&payload.token, &cached_data.token)?;
-        if cached_data.otp_value != payload.otp {
+        let attempt = self
+            .login_guard_service
+            .reserve(AttemptKind::Otp(OtpFlow::SignUp), &token_data.jti, ip)
+            .await?;
+        if !self.otp_service.verify_otp_hash(
+            &token_data.email,
+            &token_data.jti,
+            &payload.otp,
+            &cached_data.otp_hash,
+        ) {
+            if attempt.is_last() {
+                // The code can't be guessed any more, a new one has to be requested
+                self.clear_cache(&token_data.email).await?;
+                return Err(otp_attempts_exceeded());
+            }
             return Err(crate::error::app_error::AppError::Unauthorized(
                 "Неверный OTP".to_string(),
                 Some(ErrorCode::WrongOTP),
             ));
         }
 
+        self.login_guard_service.succeed(attempt).await?;
         self.verify_otp_and_update_cache(&token_data.email).await?;
 
         Ok(())
@@ -169,7 +198,9 @@ impl SignUpService {
             ));
         }
 
-        let token = self.send_otp(token_data.email.clone()).await?;
+        let token = self
+            .send_otp(token_data.email.clone(), token_data.locale)
+            .await?;
 
         Ok(ResendOTPResponse {
             token,
@@ -200,12 +231,7 @@ impl SignUpService {
             ));
         }
 
-        let mut rng = OsRng;
-        let salt = SaltString::generate(&mut rng);
-        let hashed_password = Argon2::default()
-            .hash_password(payload.password.as_bytes(), &salt)
-            .unwrap()
-            .to_string();
+        let hashed_password = hash_password(&payload.password)?;
 
         match self
             .users_repository
@@ -213,6 +239,7 @@ impl SignUpService {
                 email: token_data.email.clone(),
                 username: payload.username,
                 password: hashed_password,
+                locale: token_data.locale.as_str().to_string(),
             })
             .await
         {
@@ -232,7 +259,7 @@ impl SignUpService {
             .hset_multiple(
                 &key,
                 &[
-                    ("otp_value", data.otp_value.to_string()),
+                    ("otp_hash", data.otp_hash.clone()),
                     (
                         "send_timestamp_seconds",
                         data.send_timestamp_seconds.to_string(),
@@ -249,7 +276,7 @@ impl SignUpService {
     async fn get_cached_data(&self, email: &str) -> AppResult<CachedSignUpOtpParams> {
         let mut con = self.cache.get_async_conn().await?;
         let key = AppCacheKey::SIGN_UP_OTP(email).build_key();
-        let otp_value: String = con.hget(&key, "otp_value").await?;
+        let otp_hash: String = con.hget(&key, "otp_hash").await?;
         let send_timestamp_seconds: u64 = con.hget(&key, "send_timestamp_seconds").await?;
         let token: String = con.hget(&key, "token").await?;
         let verified_str: String = con.hget(&key, "verified").await?;
@@ -264,7 +291,7 @@ impl SignUpService {
         };
 
         Ok(CachedSignUpOtpParams {
-            otp_value,
+            otp_hash,
             send_timestamp_seconds,
             token,
             verified: _verified,
@@ -296,23 +323,28 @@ impl SignUpService {
         }
     }
 
-    async fn send_otp(&self, email: String) -> AppResult<String> {
+    async fn send_otp(&self, email: String, locale: Locale) -> AppResult<String> {
         let otp = self.otp_service.generate(6)?;
+        let jti = new_token_id();
         let token = self.token_service.create_jwt(TokenData {
             email: email.clone(),
+            jti: jti.clone(),
             exp: get_current_timestamp() + TOKEN_LIFETIME_SEC,
             token_type: TokenType::SignUp,
             created_at: get_current_timestamp(),
+            locale,
         })?;
 
         self.smtp_service
-            .send_registration_otp(email.as_str(), otp)
+            .send_registration_otp(email.as_str(), otp, locale)
             .await?;
 
         self.cache_sign_up_data(
             email.as_str(),
             &CachedSignUpOtpParams {
-                otp_value: otp.to_string(),
+                otp_hash: self
+                    .otp_service
+                    .make_otp_hash(&email, &jti, &otp.to_string()),
                 send_timestamp_seconds: SystemTime::now()
                     .duration_since(UNIX_EPOCH)
                     .unwrap()
@@ -327,7 +359,7 @@ impl SignUpService {
     }
 
     fn compare_tokens(&self, token1: &str, token2: &str) -> AppResult<()> {
-        if token1 != token2 {
+        if !tokens_match(token1, token2) {
             return Err(AppError::Unauthorized(
                 "Ошибка при проверке OTP".to_string(),
                 Some(ErrorCode::WrongOTPToken),
@@ -0,0 +1,311 @@
+use std::{collections::HashMap, sync::Arc};
+
+use jsonwebtoken::get_current_timestamp;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::response::auth::{
+        session::{SessionKind, SessionResponse},
+        token::TokenResponse,
+    },
+    error::app_error::{AppError, AppResult, ErrorCode},
+    infrastucture::cache::{client::Cache, keys::AppCacheKey},
+    service::{
+        auth::auth_service::{SessionMeta, unix_timestamp},
+        token_service::{Token, TokenService, TokenType, new_token_id},
+    },
+};
+
+const ACCESS_TOKEN_LIFETIME_SEC: u64 = 60 * 15;
+const REFRESH_TOKEN_LIFETIME_SEC: u64 = 60 * 60 * 24 * 30;
+
+/// Replace the current refresh token id of a family, but only if it is still
+/// the one being presented. The family stays in the user's index
+/// (`KEYS[2]`) for as long as it lives. Returns 1 on success, 0 on reuse and
+/// -1 when the family is gone.
+const ROTATE_SCRIPT: &str = r"
+local current = redis.call('HGET', KEYS[1], 'current_jti')
+if not current then
+    return -1
+end
+if current ~= ARGV[1] then
+    return 0
+end
+redis.call('HSET', KEYS[1], 'current_jti', ARGV[2], 'last_updated', ARGV[4])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+redis.call('SADD', KEYS[2], ARGV[5])
+redis.call('EXPIRE', KEYS[2], ARGV[3])
+return 1
+";
+
+#[derive(Serialize, Deserialize)]
+struct AccessTokenData {
+    sub: i32,
+    /// Family of the refresh token it was issued with; revoking the family
+    /// revokes the access token too.
+    family: String,
+    exp: u64,
+    token_type: TokenType,
+}
+
+impl Token for AccessTokenData {
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+    fn token_type(&self) -> TokenType {
+        self.token_type.clone()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenData {
+    sub: i32,
+    family: String,
+    jti: String,
+    exp: u64,
+    token_type: TokenType,
+}
+
+impl Token for RefreshTokenData {
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+    fn token_type(&self) -> TokenType {
+        self.token_type.clone()
+    }
+}
+
+/// Bearer tokens for clients that can't keep cookies. Refresh tokens rotate
+/// on every use; presenting an already used one revokes its whole family.
+pub struct TokenAuthService {
+    cache: Arc<Cache>,
+    token_service: Arc<TokenService>,
+    rotate_script: redis::Script,
+}
+
+impl TokenAuthService {
+    pub fn new(cache: Arc<Cache>, token_service: Arc<TokenService>) -> Self {
+        TokenAuthService {
+            cache,
+            token_service,
+            rotate_script: redis::Script::new(ROTATE_SCRIPT),
+        }
+    }
+
+    /// Start a new token family for the user.
+    pub async fn issue_tokens(&self, user_id: i32, meta: &SessionMeta) -> AppResult<TokenResponse> {
+        let family = new_token_id();
+        let jti = new_token_id();
+        let timestamp = unix_timestamp().to_string();
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::REFRESH_TOKEN_FAMILY(&family).build_key();
+        let mut fields = vec![
+            ("user_id", user_id.to_string()),
+            ("current_jti", jti.clone()),
+            ("created_at", timestamp.clone()),
+            ("last_updated", timestamp),
+            ("ip", meta.ip.to_string()),
+        ];
+        if let Some(user_agent) = &meta.user_agent {
+            fields.push(("user_agent", user_agent.clone()));
+        }
+        let _: () = con.hset_multiple(&key, &fields).await?;
+        let _: () = con.expire(&key, REFRESH_TOKEN_LIFETIME_SEC as i64).await?;
+
+        let index_key = AppCacheKey::USER_TOKEN_FAMILIES(user_id).build_key();
+        let _: () = con.sadd(&index_key, &family).await?;
+        let _: () = con
+            .expire(&index_key, REFRESH_TOKEN_LIFETIME_SEC as i64)
+            .await?;
+
+        self.create_tokens(user_id, family, jti)
+    }
+
+    pub async fn refresh(&self, refresh_token: &str) -> AppResult<TokenResponse> {
+        let claims = self
+            .token_service
+            .get_claims_from_jwt::<RefreshTokenData>(refresh_token, TokenType::Refresh)?;
+        let next_jti = new_token_id();
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::REFRESH_TOKEN_FAMILY(&claims.family).build_key();
+        let index_key = AppCacheKey::USER_TOKEN_FAMILIES(claims.sub).build_key();
+        let rotated: i32 = self
+            .rotate_script
+            .key(&key)
+            .key(&index_key)
+            .arg(&claims.jti)
+            .arg(&next_jti)
+            .arg(REFRESH_TOKEN_LIFETIME_SEC)
+            .arg(unix_timestamp())
+            .arg(&claims.family)
+            .invoke_async(&mut con)
+            .await?;
+
+        match rotated {
+            1 => self.create_tokens(claims.sub, claims.family, next_jti),
+            0 => {
+                // Someone holds a copy of the token, so nothing issued from
+                // this family can be trusted any more
+                self.delete_family(claims.sub, &claims.family).await?;
+                Err(AppError::Unauthorized(
+                    "Refresh token was already used, sign in again".to_string(),
+                    Some(ErrorCode::RefreshTokenReused),
+                ))
+            }
+            _ => Err(AppError::Unauthorized(
+                "Refresh token was revoked, sign in again".to_string(),
+                Some(ErrorCode::JWTInvalid),
+            )),
+        }
+    }
+
+    /// Id of the user the access token belongs to and its family.
+    pub async fn verify_access_token(&self, access_token: &str) -> AppResult<(i32, String)> {
+        let claims = self
+            .token_service
+            .get_claims_from_jwt::<AccessTokenData>(access_token, TokenType::Access)?;
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::REFRESH_TOKEN_FAMILY(&claims.family).build_key();
+        let active: bool = con.exists(&key).await?;
+        if !active {
+            return Err(AppError::Unauthorized(
+                "Access token was revoked".to_string(),
+                Some(ErrorCode::JWTInvalid),
+            ));
+        }
+        Ok((claims.sub, claims.family))
+    }
+
+    /// Active token families of the user. Families that expired are dropped
+    /// from the index on the way.
+    pub async fn list_sessions(
+        &self,
+        user_id: i32,
+        current_family: Option<&str>,
+    ) -> AppResult<Vec<SessionResponse>> {
+        let mut con = self.cache.get_async_conn().await?;
+        let index_key = AppCacheKey::USER_TOKEN_FAMILIES(user_id).build_key();
+        let families: Vec<String> = con.smembers(&index_key).await?;
+
+        let mut sessions = Vec::with_capacity(families.len());
+        for family in families {
+            let key = AppCacheKey::REFRESH_TOKEN_FAMILY(&family).build_key();
+            let fields: HashMap<String, String> = con.hgetall(&key).await?;
+            if fields.is_empty() {
+                let _: () = con.srem(&index_key, &family).await?;
+                continue;
+            }
+            let current = current_family == Some(family.as_str());
+            sessions.push(SessionResponse::from_cache(
+                family,
+                SessionKind::Token,
+                &fields,
+                current,
+            ));
+        }
+        Ok(sessions)
+    }
+
+    /// Revokes the family if it belongs to the user. Returns whether it did.
+    pub async fn revoke_family(&self, user_id: i32, family: &str) -> AppResult<bool> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::REFRESH_TOKEN_FAMILY(family).build_key();
+        let owner: Option<i32> = con.hget(&key, "user_id").await?;
+        if owner != Some(user_id) {
+            return Ok(false);
+        }
+        self.delete_family(user_id, family).await?;
+        Ok(true)
+    }
+
+    /// Revokes every family of the user except `keep`.
+    pub async fn revoke_all(&self, user_id: i32, keep: Option<&str>) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let index_key = AppCacheKey::USER_TOKEN_FAMILIES(user_id).build_key();
+        let families: Vec<String> = con.smembers(&index_key).await?;
+        for family in families.iter().filter(|f| Some(f.as_str()) != keep) {
+            self.delete_family(user_id, family).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_family(&self, user_id: i32, family: &str) -> AppResult<()> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::REFRESH_TOKEN_FAMILY(family).build_key();
+        let index_key = AppCacheKey::USER_TOKEN_FAMILIES(user_id).build_key();
+        let _: () = redis::pipe()
+            .atomic()
+            .del(&key)
+            .ignore()
+            .srem(&index_key, family)
+            .ignore()
+            .query_async(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    fn create_tokens(&self, user_id: i32, family: String, jti: String) -> AppResult<TokenResponse> {
+        let now = get_current_timestamp();
+        let access_token = self.token_service.create_jwt(AccessTokenData {
+            sub: user_id,
+            family: family.clone(),
+            exp: now + ACCESS_TOKEN_LIFETIME_SEC,
+            token_type: TokenType::Access,
+        })?;
+        let refresh_token = self.token_service.create_jwt(RefreshTokenData {
+            sub: user_id,
+            family,
+            jti,
+            exp: now + REFRESH_TOKEN_LIFETIME_SEC,
+            token_type: TokenType::Refresh,
+        })?;
+        Ok(TokenResponse {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: ACCESS_TOKEN_LIFETIME_SEC,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::secret::SecretConfig;
+
+    /// Access and refresh tokens signed with the same secret, as in the
+    /// default configuration.
+    fn service() -> TokenAuthService {
+        let secret = "secret".to_string();
+        let token_service = TokenService::new(SecretConfig {
+            sign_up_secret: secret.clone(),
+            restore_secret: secret.clone(),
+            email_change_secret: secret.clone(),
+            access_secret: secret.clone(),
+            refresh_secret: secret.clone(),
+            otp_secret: secret,
+        });
+        // The token type is checked before Redis is asked anything
+        TokenAuthService::new(
+            Arc::new(Cache::new("redis://localhost:6379")),
+            Arc::new(token_service),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_is_not_an_access_token() {
+        let service = service();
+        let tokens = service
+            .create_tokens(1, new_token_id(), new_token_id())
+            .unwrap();
+
+        assert!(matches!(
+            service.verify_access_token(&tokens.refresh_token).await,
+            Err(AppError::Unauthorized(_, Some(ErrorCode::JWTInvalid)))
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    error::app_error::{AppError, AppResult},
+    service::playback::DfpwmChannel,
+};
+
+const CLIENT_TEMPLATE: &str = include_str!("../../templates/cc/radio.lua");
+const INSTALLER_TEMPLATE: &str = include_str!("../../templates/cc/install.lua");
+
+/// File name the installer saves the client under.
+const CLIENT_PROGRAM: &str = "radio.lua";
+/// `speaker.playAudio` accepts volumes from 0 to 3.
+const MAX_VOLUME: f32 = 3.0;
+const DEFAULT_VOLUME: f32 = 1.0;
+
+/// Speaker settings baked into the generated client.
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub channel: DfpwmChannel,
+    pub volume: f32,
+    /// Peripheral name of a single speaker; every attached speaker if `None`.
+    pub speaker: Option<String>,
+}
+
+impl ClientOptions {
+    pub fn new(
+        channel: Option<DfpwmChannel>,
+        volume: Option<f32>,
+        speaker: Option<String>,
+    ) -> AppResult<Self> {
+        let volume = volume.unwrap_or(DEFAULT_VOLUME);
+        if !(0.0..=MAX_VOLUME).contains(&volume) {
+            return Err(AppError::BadRequest(
+                format!("volume must be between 0 and {}", MAX_VOLUME),
+                None,
+            ));
+        }
+
+        Ok(ClientOptions {
+            channel: channel.unwrap_or_default(),
+            volume,
+            speaker: speaker.filter(|name| !name.is_empty()),
+        })
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("channel", self.channel.as_str().to_string()),
+            ("volume", self.volume.to_string()),
+        ];
+        if let Some(speaker) = &self.speaker {
+            query.push(("speaker", speaker.clone()));
+        }
+        query
+    }
+}
+
+/// Render the Lua client program for a station reachable at `station_url`.
+pub fn render_client(station_url: &str, options: &ClientOptions) -> AppResult<String> {
+    let ws_base = if let Some(rest) = station_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = station_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        return Err(AppError::Internal(anyhow::anyhow!(
+            "Station URL must be http(s): {}",
+            station_url
+        )));
+    };
+
+    let stream_url = build_url(
+        &ws_base,
+        "/api/v1/ws/stream-dfpwm",
+        &[
+            ("channel", options.channel.as_str().to_string()),
+            ("metadata", "true".to_string()),
+        ],
+    )?;
+    let client_url = build_url(station_url, "/api/v1/cc/radio.lua", &options.query())?;
+
+    Ok(render(
+        CLIENT_TEMPLATE,
+        &[
+            ("client_url", client_url),
+            ("stream_url", lua_string(&stream_url)),
+            ("volume", options.volume.to_string()),
+            (
+                "speaker",
+                options
+                    .speaker
+                    .as_deref()
+                    .map_or_else(|| "nil".to_string(), lua_string),
+            ),
+        ],
+    ))
+}
+
+/// Render an installer that downloads the client with the same options.
+/// Meant to be run with `wget run <url>` from a CC computer.
+pub fn render_installer(
+    station_url: &str,
+    options: &ClientOptions,
+    startup: bool,
+) -> AppResult<String> {
+    let client_url = build_url(station_url, "/api/v1/cc/radio.lua", &options.query())?;
+
+    let mut install_query = options.query();
+    if startup {
+        install_query.push(("startup", "true".to_string()));
+    }
+    let install_url = build_url(station_url, "/api/v1/cc/install", &install_query)?;
+
+    Ok(render(
+        INSTALLER_TEMPLATE,
+        &[
+            ("install_url", install_url),
+            ("client_url", lua_string(&client_url)),
+            ("program", lua_string(CLIENT_PROGRAM)),
+            ("startup", startup.to_string()),
+        ],
+    ))
+}
+
+fn build_url(base: &str, path: &str, query: &[(&str, String)]) -> AppResult<String> {
+    let url = reqwest::Url::parse_with_params(&format!("{}{}", base, path), query)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
+    Ok(url.to_string())
+}
+
+/// Substitute `{{name}}` placeholders.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |rendered, (name, value)| {
+            rendered.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+/// Quote `value` as a Lua string literal.
+fn lua_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\{:03}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ClientOptions {
+        ClientOptions::new(Some(DfpwmChannel::Left), Some(2.0), None).unwrap()
+    }
+
+    #[test]
+    fn test_client_points_at_websocket() {
+        let lua = render_client("https://radio.example.com", &options()).unwrap();
+
+        assert!(lua.contains(
+            r#"local streamUrl = "wss://radio.example.com/api/v1/ws/stream-dfpwm?channel=left&metadata=true""#
+        ));
+        assert!(lua.contains("local volume = 2\n"));
+        assert!(lua.contains("local speakerName = nil\n"));
+        assert!(!lua.contains("{{"));
+    }
+
+    #[test]
+    fn test_speaker_name_is_escaped() {
+        let options =
+            ClientOptions::new(None, None, Some("top\"\n os.shutdown() --".to_string())).unwrap();
+        let lua = render_client("http://localhost:8080", &options).unwrap();
+
+        assert!(lua.contains(r#"local speakerName = "top\"\n os.shutdown() --""#));
+        assert!(
+            lua.contains("ws://localhost:8080/api/v1/ws/stream-dfpwm?channel=mono&metadata=true")
+        );
+    }
+
+    #[test]
+    fn test_installer_keeps_options() {
+        let lua = render_installer("https://radio.example.com", &options(), true).unwrap();
+
+        assert!(lua.contains(
+            r#"local clientUrl = "https://radio.example.com/api/v1/cc/radio.lua?channel=left&volume=2""#
+        ));
+        assert!(lua.contains(
+            "wget run https://radio.example.com/api/v1/cc/install?channel=left&volume=2&startup=true"
+        ));
+        assert!(lua.contains("local startup = true\n"));
+    }
+
+    #[test]
+    fn test_volume_is_validated() {
+        assert!(ClientOptions::new(None, Some(3.5), None).is_err());
+        assert!(ClientOptions::new(None, Some(-1.0), None).is_err());
+    }
+}
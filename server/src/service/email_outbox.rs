@@ -0,0 +1,143 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Notify;
+
+use crate::{
+    error::app_error::{AppError, AppResult},
+    infrastucture::{
+        database::models::OutboxEmail, repositories::email_outbox_repository::EmailOutboxRepository,
+    },
+    service::mail_transport::{MailMessage, MailTransport},
+};
+
+const BATCH_SIZE: i32 = 20;
+/// Emails due for a retry are noticed at most this late.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// A claimed email is tried again after this long if its worker died.
+const CLAIM_LEASE_SEC: i32 = 5 * 60;
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_DELAY_SEC: i32 = 30;
+const MAX_RETRY_DELAY_SEC: i32 = 60 * 60;
+
+/// Delay before the next try of an email that failed `attempts` times.
+pub fn retry_delay_sec(attempts: i32) -> i32 {
+    let doublings = (attempts - 1).clamp(0, 16) as u32;
+    BASE_RETRY_DELAY_SEC
+        .saturating_mul(1 << doublings)
+        .min(MAX_RETRY_DELAY_SEC)
+}
+
+/// Delivers the emails queued in `email_outbox` through the configured
+/// transport.
+pub struct EmailOutboxWorker {
+    repository: Arc<EmailOutboxRepository>,
+    transport: Arc<dyn MailTransport>,
+    notify: Arc<Notify>,
+    from: String,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        repository: Arc<EmailOutboxRepository>,
+        transport: Arc<dyn MailTransport>,
+        notify: Arc<Notify>,
+        from: String,
+    ) -> Self {
+        EmailOutboxWorker {
+            repository,
+            transport,
+            notify,
+            from,
+        }
+    }
+
+    pub async fn run(self) {
+        println!("📧 Delivering emails through {}", self.transport.name());
+        loop {
+            match self.repository.fail_expired().await {
+                Ok(0) => {}
+                Ok(expired) => {
+                    eprintln!("⚠️  {} emails expired before they could be sent", expired)
+                }
+                Err(e) => eprintln!("❌ Failed to expire outbox emails: {}", e),
+            }
+
+            let batch = match self.repository.claim_due(BATCH_SIZE, CLAIM_LEASE_SEC).await {
+                Ok(batch) => batch,
+                Err(e) => {
+                    eprintln!("❌ Failed to read the email outbox: {}", e);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            if batch.is_empty() {
+                tokio::select! {
+                    _ = self.notify.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+                continue;
+            }
+
+            for email in batch {
+                let result = self.deliver(&email).await;
+                if let Err(e) = self.record(&email, result).await {
+                    eprintln!("❌ Failed to update outbox email {}: {}", email.id, e);
+                }
+            }
+        }
+    }
+
+    async fn deliver(&self, email: &OutboxEmail) -> AppResult<()> {
+        self.transport
+            .send(&MailMessage {
+                from: self.from.clone(),
+                to: email.recipient.clone(),
+                subject: email.subject.clone(),
+                text_body: email.text_body.clone(),
+                html_body: email.html_body.clone(),
+            })
+            .await
+    }
+
+    async fn record(&self, email: &OutboxEmail, result: AppResult<()>) -> AppResult<()> {
+        let error = match result {
+            Ok(()) => return self.repository.mark_sent(email.id).await,
+            // Without the "Internal server error" prefix
+            Err(AppError::Internal(e)) => e.to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        let attempts = email.attempts + 1;
+        if attempts >= MAX_ATTEMPTS {
+            eprintln!(
+                "❌ Giving up on {} email {} to {}: {}",
+                email.template, email.id, email.recipient, error
+            );
+            return self.repository.mark_failed(email.id, &error).await;
+        }
+        eprintln!(
+            "⚠️  {} email {} to {} failed (attempt {}): {}",
+            email.template, email.id, email.recipient, attempts, error
+        );
+        self.repository
+            .mark_retry(email.id, retry_delay_sec(attempts), &error)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backs_off_up_to_the_cap() {
+        assert_eq!(retry_delay_sec(1), 30);
+        assert_eq!(retry_delay_sec(2), 60);
+        assert_eq!(retry_delay_sec(3), 120);
+        assert_eq!(retry_delay_sec(7), 1920);
+        assert_eq!(retry_delay_sec(8), MAX_RETRY_DELAY_SEC);
+        assert_eq!(retry_delay_sec(100), MAX_RETRY_DELAY_SEC);
+        assert_eq!(retry_delay_sec(0), 30);
+    }
+}
@@ -0,0 +1,393 @@
+const HTML_LAYOUT: &str = include_str!("../../templates/email/layout.html");
+const TEXT_LAYOUT: &str = include_str!("../../templates/email/layout.txt");
+/// One-time codes are kept for 10 minutes after they are sent.
+const OTP_EMAIL_LIFETIME_SEC: i32 = 60 * 10;
+
+/// Language of the emails a user gets.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::Ru => "ru",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ru" => Some(Locale::Ru),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// First supported language of an `Accept-Language` header, `ru` if there
+    /// is none.
+    pub fn from_accept_language(header: Option<&str>) -> Self {
+        header
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .filter_map(|tag| {
+                let tag = tag.split(';').next()?.trim();
+                Locale::parse(&tag.split('-').next()?.to_ascii_lowercase())
+            })
+            .next()
+            .unwrap_or_default()
+    }
+}
+
+/// Transactional emails and the values they are rendered with.
+#[derive(Clone, Debug)]
+pub enum EmailTemplate<'a> {
+    SignUpOtp {
+        otp: u32,
+    },
+    RestoreOtp {
+        otp: u32,
+    },
+    AccountLocked {
+        minutes: u32,
+    },
+    EmailChangeOtp {
+        otp: u32,
+    },
+    /// Sent to the old address.
+    EmailChangeNotice {
+        new_email: &'a str,
+    },
+    /// Sent to the old address once the change is done.
+    EmailChanged {
+        new_email: &'a str,
+    },
+}
+
+impl EmailTemplate<'_> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::SignUpOtp { .. } => "sign_up_otp",
+            EmailTemplate::RestoreOtp { .. } => "restore_otp",
+            EmailTemplate::AccountLocked { .. } => "account_locked",
+            EmailTemplate::EmailChangeOtp { .. } => "email_change_otp",
+            EmailTemplate::EmailChangeNotice { .. } => "email_change_notice",
+            EmailTemplate::EmailChanged { .. } => "email_changed",
+        }
+    }
+
+    /// How long the email is worth sending, for emails with a one-time code.
+    pub fn lifetime_sec(&self) -> Option<i32> {
+        match self {
+            EmailTemplate::SignUpOtp { .. }
+            | EmailTemplate::RestoreOtp { .. }
+            | EmailTemplate::EmailChangeOtp { .. } => Some(OTP_EMAIL_LIFETIME_SEC),
+            EmailTemplate::AccountLocked { .. }
+            | EmailTemplate::EmailChangeNotice { .. }
+            | EmailTemplate::EmailChanged { .. } => None,
+        }
+    }
+
+    fn subject(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (EmailTemplate::SignUpOtp { .. }, Locale::Ru) => "Код для подтверждения регистрации",
+            (EmailTemplate::SignUpOtp { .. }, Locale::En) => "Your sign-up code",
+            (EmailTemplate::RestoreOtp { .. }, Locale::Ru) => "Код для восстановления пароля",
+            (EmailTemplate::RestoreOtp { .. }, Locale::En) => "Your password reset code",
+            (EmailTemplate::AccountLocked { .. }, Locale::Ru) => {
+                "Вход в аккаунт временно заблокирован"
+            }
+            (EmailTemplate::AccountLocked { .. }, Locale::En) => "Sign-in temporarily locked",
+            (EmailTemplate::EmailChangeOtp { .. }, Locale::Ru) => {
+                "Код для подтверждения нового email"
+            }
+            (EmailTemplate::EmailChangeOtp { .. }, Locale::En) => "Your email change code",
+            (EmailTemplate::EmailChangeNotice { .. }, Locale::Ru) => "Запрошена смена email",
+            (EmailTemplate::EmailChangeNotice { .. }, Locale::En) => "Email change requested",
+            (EmailTemplate::EmailChanged { .. }, Locale::Ru) => "Email аккаунта изменён",
+            (EmailTemplate::EmailChanged { .. }, Locale::En) => "Your account email was changed",
+        }
+    }
+
+    /// HTML and text bodies.
+    fn sources(&self, locale: Locale) -> (&'static str, &'static str) {
+        macro_rules! localized {
+            ($name:literal) => {
+                match locale {
+                    Locale::Ru => (
+                        include_str!(concat!("../../templates/email/ru/", $name, ".html")),
+                        include_str!(concat!("../../templates/email/ru/", $name, ".txt")),
+                    ),
+                    Locale::En => (
+                        include_str!(concat!("../../templates/email/en/", $name, ".html")),
+                        include_str!(concat!("../../templates/email/en/", $name, ".txt")),
+                    ),
+                }
+            };
+        }
+        match self {
+            EmailTemplate::SignUpOtp { .. } => localized!("sign_up_otp"),
+            EmailTemplate::RestoreOtp { .. } => localized!("restore_otp"),
+            EmailTemplate::AccountLocked { .. } => localized!("account_locked"),
+            EmailTemplate::EmailChangeOtp { .. } => localized!("email_change_otp"),
+            EmailTemplate::EmailChangeNotice { .. } => localized!("email_change_notice"),
+            EmailTemplate::EmailChanged { .. } => localized!("email_changed"),
+        }
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        match self {
+            EmailTemplate::SignUpOtp { otp }
+            | EmailTemplate::RestoreOtp { otp }
+            | EmailTemplate::EmailChangeOtp { otp } => vec![("otp", otp.to_string())],
+            EmailTemplate::AccountLocked { minutes } => vec![("minutes", minutes.to_string())],
+            EmailTemplate::EmailChangeNotice { new_email }
+            | EmailTemplate::EmailChanged { new_email } => {
+                vec![("new_email", new_email.to_string())]
+            }
+        }
+    }
+}
+
+fn footer(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ru => include_str!("../../templates/email/ru/footer.txt"),
+        Locale::En => include_str!("../../templates/email/en/footer.txt"),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Renders the templates under `templates/email`, wrapped in the station
+/// layout.
+pub struct EmailRenderer {
+    station_name: String,
+}
+
+impl EmailRenderer {
+    pub fn new(station_name: &str) -> Self {
+        EmailRenderer {
+            station_name: station_name.to_string(),
+        }
+    }
+
+    pub fn render(&self, template: &EmailTemplate, locale: Locale) -> RenderedEmail {
+        let subject = template.subject(locale);
+        let (html_source, text_source) = template.sources(locale);
+
+        let mut values = template.values();
+        values.push(("station_name", self.station_name.clone()));
+        let footer = render(footer(locale).trim_end(), &values);
+
+        let html_values: Vec<(&str, String)> = values
+            .iter()
+            .map(|(name, value)| (*name, escape_html(value)))
+            .collect();
+        let html_content = render(html_source.trim_end(), &html_values);
+        let html = render(
+            HTML_LAYOUT,
+            &[
+                ("lang", locale.as_str().to_string()),
+                ("subject", escape_html(subject)),
+                ("station_name", escape_html(&self.station_name)),
+                ("content", html_content),
+                ("footer", escape_html(&footer)),
+            ],
+        );
+
+        let text = render(
+            TEXT_LAYOUT,
+            &[
+                ("station_name", self.station_name.clone()),
+                ("content", render(text_source.trim_end(), &values)),
+                ("footer", footer),
+            ],
+        );
+
+        RenderedEmail {
+            subject: subject.to_string(),
+            html,
+            text,
+        }
+    }
+}
+
+/// Substitute `{{name}}` placeholders in one pass, so values that look like
+/// placeholders are left as they are.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            let name = &after[..end];
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_templates() -> Vec<EmailTemplate<'static>> {
+        vec![
+            EmailTemplate::SignUpOtp { otp: 123456 },
+            EmailTemplate::RestoreOtp { otp: 123456 },
+            EmailTemplate::AccountLocked { minutes: 15 },
+            EmailTemplate::EmailChangeOtp { otp: 123456 },
+            EmailTemplate::EmailChangeNotice {
+                new_email: "new@example.com",
+            },
+            EmailTemplate::EmailChanged {
+                new_email: "new@example.com",
+            },
+        ]
+    }
+
+    #[test]
+    fn test_every_template_renders_in_every_locale() {
+        let renderer = EmailRenderer::new("DJ Arbuzzz");
+        for template in all_templates() {
+            for locale in [Locale::Ru, Locale::En] {
+                let email = renderer.render(&template, locale);
+
+                assert!(!email.subject.is_empty());
+                for body in [&email.html, &email.text] {
+                    assert!(!body.contains("{{"), "{} {:?}", template.name(), locale);
+                    assert!(body.contains("DJ Arbuzzz"));
+                    for (_, value) in template.values() {
+                        assert!(
+                            body.contains(&value),
+                            "{} misses {}",
+                            template.name(),
+                            value
+                        );
+                    }
+                }
+                assert!(
+                    email
+                        .html
+                        .contains(&format!("<html lang=\"{}\">", locale.as_str()))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_locales_differ() {
+        let renderer = EmailRenderer::new("DJ Arbuzzz");
+        let template = EmailTemplate::SignUpOtp { otp: 123456 };
+        let ru = renderer.render(&template, Locale::Ru);
+        let en = renderer.render(&template, Locale::En);
+
+        assert_eq!(ru.subject, "Код для подтверждения регистрации");
+        assert_eq!(en.subject, "Your sign-up code");
+        assert!(ru.text.contains("Ваш код подтверждения: 123456"));
+        assert!(en.text.contains("Your confirmation code: 123456"));
+    }
+
+    #[test]
+    fn test_only_emails_with_codes_expire() {
+        let expiring: Vec<&str> = all_templates()
+            .iter()
+            .filter(|template| template.lifetime_sec().is_some())
+            .map(|template| template.name())
+            .collect();
+        assert_eq!(
+            expiring,
+            vec!["sign_up_otp", "restore_otp", "email_change_otp"]
+        );
+    }
+
+    #[test]
+    fn test_values_are_escaped_in_html_only() {
+        let renderer = EmailRenderer::new("Rock & Roll");
+        let email = renderer.render(
+            &EmailTemplate::EmailChangeNotice {
+                new_email: "<script>{{otp}}</script>@example.com",
+            },
+            Locale::En,
+        );
+
+        assert!(
+            email
+                .html
+                .contains("&lt;script&gt;{{otp}}&lt;/script&gt;@example.com")
+        );
+        assert!(email.html.contains("Rock &amp; Roll"));
+        assert!(!email.html.contains("<script>"));
+        assert!(email.text.contains("<script>{{otp}}</script>@example.com"));
+        assert!(email.text.contains("Rock & Roll"));
+    }
+
+    #[test]
+    fn test_locale_from_accept_language() {
+        assert_eq!(
+            Locale::from_accept_language(Some("en-US,en;q=0.9,ru;q=0.8")),
+            Locale::En
+        );
+        assert_eq!(
+            Locale::from_accept_language(Some("de-DE, RU;q=0.5")),
+            Locale::Ru
+        );
+        assert_eq!(Locale::from_accept_language(Some("de")), Locale::Ru);
+        assert_eq!(Locale::from_accept_language(None), Locale::Ru);
+    }
+
+    #[test]
+    fn test_render_leaves_unknown_placeholders() {
+        let rendered = render("{{a}} {{b}} {{", &[("a", "{{b}}".to_string())]);
+        assert_eq!(rendered, "{{b}} {{b}} {{");
+    }
+}
@@ -0,0 +1,257 @@
+use std::{fs, sync::Arc};
+
+use axum::body::Bytes;
+
+use crate::{
+    config::AppConfig,
+    error::app_error::{AppError, AppResult, ErrorCode},
+    infrastucture::{
+        database::models::{Track, LOCAL_TRACK_SOURCE},
+        repositories::track_repository::TrackRepository,
+    },
+    service::{
+        audio_file::{probe_file, read_tags, AudioProperties, AudioTags, SUPPORTED_EXTENSIONS},
+        loudness::{analyze_file, TrackLoudness},
+        music_provider::{directory::DirectoryProvider, MusicProviders, FILE_URL_PREFIX},
+    },
+};
+
+/// Owner id of uploaded tracks, which belong to no provider account.
+pub const LOCAL_OWNER_ID: i32 = 0;
+
+/// A track file on disk.
+pub struct DownloadedFile {
+    pub path: String,
+    /// The file was fetched by this call rather than already present.
+    pub is_new: bool,
+}
+
+/// An uploaded file that was validated and stored in the songs directory.
+pub struct UploadedFile {
+    pub song_id: i32,
+    /// Name inside the songs directory, stored as the track `download_url`.
+    pub file_name: String,
+    pub path: String,
+    pub properties: AudioProperties,
+    pub tags: AudioTags,
+}
+
+/// Path of the file of a track, wherever it came from.
+pub fn track_file_path(config: &AppConfig, track: &Track) -> String {
+    if track.source == LOCAL_TRACK_SOURCE {
+        config.songs_config.local_file_path(&track.download_url)
+    } else if let Some(path) = track
+        .download_url
+        .strip_prefix(FILE_URL_PREFIX)
+        .filter(|_| track.source == DirectoryProvider::NAME)
+    {
+        path.to_string()
+    } else {
+        config
+            .songs_config
+            .track_file_path(track.owner_id, track.song_id)
+    }
+}
+
+/// Downloads track files and measures their real audio properties.
+pub struct IngestService {
+    track_repository: Arc<TrackRepository>,
+    providers: Arc<MusicProviders>,
+    config: Arc<AppConfig>,
+}
+
+impl IngestService {
+    pub fn new(
+        track_repository: Arc<TrackRepository>,
+        providers: Arc<MusicProviders>,
+        config: Arc<AppConfig>,
+    ) -> Self {
+        IngestService {
+            track_repository,
+            providers,
+            config,
+        }
+    }
+
+    /// Fetch the file of a track of the `source` provider. `file://` URLs
+    /// are only read if that provider serves the file.
+    pub async fn download(
+        &self,
+        source: &str,
+        song_id: i32,
+        owner_id: i32,
+        url: &str,
+    ) -> AppResult<DownloadedFile> {
+        if url.starts_with(FILE_URL_PREFIX) {
+            let path = self
+                .providers
+                .get(source)
+                .and_then(|provider| provider.local_file(url))
+                .ok_or_else(|| AppError::NotFound(format!("File {} is missing", url), None))?;
+            return Ok(DownloadedFile {
+                path,
+                is_new: false,
+            });
+        }
+
+        let path = self.config.songs_config.track_file_path(owner_id, song_id);
+        if std::path::Path::new(&path).exists() {
+            return Ok(DownloadedFile {
+                path,
+                is_new: false,
+            });
+        }
+
+        let response = reqwest::get(url).await?.error_for_status()?;
+        let bytes = response.bytes().await?;
+        fs::write(&path, bytes)?;
+        Ok(DownloadedFile { path, is_new: true })
+    }
+
+    /// Store an uploaded file under a new local song id, keeping it only if
+    /// symphonia can read it.
+    pub async fn store_upload(&self, original_name: &str, data: Bytes) -> AppResult<UploadedFile> {
+        let extension = std::path::Path::new(original_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .filter(|e| SUPPORTED_EXTENSIONS.contains(&e.as_str()))
+            .ok_or_else(Self::unsupported_file)?;
+
+        let song_id = self.track_repository.next_local_song_id().await?;
+        let file_name = format!("{}_{}.{}", LOCAL_OWNER_ID, song_id, extension);
+        let path = self.config.songs_config.local_file_path(&file_name);
+        tokio::fs::write(&path, data).await?;
+
+        let probe_path = path.clone();
+        let probed = tokio::task::spawn_blocking(move || {
+            Ok::<_, AppError>((probe_file(&probe_path)?, read_tags(&probe_path)?))
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Probe task failed: {}", e))?;
+        let (properties, tags) = match probed {
+            Ok(probed) => probed,
+            Err(e) => {
+                eprintln!("❌ Rejected upload {}: {}", original_name, e);
+                let _ = tokio::fs::remove_file(&path).await;
+                return Err(Self::unsupported_file());
+            }
+        };
+
+        Ok(UploadedFile {
+            song_id,
+            file_name,
+            path,
+            properties,
+            tags,
+        })
+    }
+
+    fn unsupported_file() -> AppError {
+        AppError::BadRequest(
+            format!(
+                "Unsupported audio file, expected one of: {}",
+                SUPPORTED_EXTENSIONS.join(", ")
+            ),
+            Some(ErrorCode::UnsupportedAudioFile),
+        )
+    }
+
+    pub async fn probe(&self, file_path: &str) -> AppResult<AudioProperties> {
+        let path = file_path.to_string();
+        tokio::task::spawn_blocking(move || probe_file(&path))
+            .await
+            .map_err(|e| anyhow::anyhow!("Probe task failed: {}", e))?
+    }
+
+    /// Measure the loudness of a new track file, so playback only has to read
+    /// it. A file that can't be analyzed plays at unity gain.
+    pub async fn analyze_loudness(&self, file_path: &str) -> Option<TrackLoudness> {
+        let path = file_path.to_string();
+        match tokio::task::spawn_blocking(move || analyze_file(&path)).await {
+            Ok(Ok(loudness)) => Some(loudness),
+            Ok(Err(e)) => {
+                eprintln!("⚠️  Loudness analysis failed for {}: {}", file_path, e);
+                None
+            }
+            Err(e) => {
+                eprintln!("⚠️  Loudness analysis panicked for {}: {}", file_path, e);
+                None
+            }
+        }
+    }
+
+    /// Make sure the track is on disk and has its properties stored, probing
+    /// it only if they are missing or the file was downloaded again.
+    pub async fn ensure_ingested(&self, track: &Track) -> AppResult<(String, AudioProperties)> {
+        let file = if track.source == LOCAL_TRACK_SOURCE {
+            let path = track_file_path(&self.config, track);
+            if !std::path::Path::new(&path).exists() {
+                return Err(AppError::NotFound(
+                    format!("Uploaded file {} is missing", path),
+                    None,
+                ));
+            }
+            DownloadedFile {
+                path,
+                is_new: false,
+            }
+        } else {
+            self.download_track(track).await?
+        };
+
+        if !file.is_new {
+            if let Some(properties) = Self::stored_properties(track) {
+                return Ok((file.path, properties));
+            }
+        }
+
+        let properties = self.probe(&file.path).await?;
+        self.track_repository
+            .update_audio_properties(track.id, &properties)
+            .await?;
+        Ok((file.path, properties))
+    }
+
+    /// Download a provider track, asking the provider for a fresh URL when
+    /// the stored one no longer works.
+    async fn download_track(&self, track: &Track) -> AppResult<DownloadedFile> {
+        let error = match self
+            .download(
+                &track.source,
+                track.song_id,
+                track.owner_id,
+                &track.download_url,
+            )
+            .await
+        {
+            Ok(file) => return Ok(file),
+            Err(e) => e,
+        };
+        let Some(provider) = self.providers.get(&track.source) else {
+            return Err(error);
+        };
+
+        let url = provider
+            .resolve_download_url(track.owner_id, track.song_id)
+            .await?;
+        let file = self
+            .download(&track.source, track.song_id, track.owner_id, &url)
+            .await?;
+        self.track_repository
+            .update_download_url(track.id, &url)
+            .await?;
+        Ok(file)
+    }
+
+    fn stored_properties(track: &Track) -> Option<AudioProperties> {
+        Some(AudioProperties {
+            duration_ms: track.duration_ms? as u64,
+            sample_rate: track.sample_rate? as u32,
+            channels: track.channels? as u16,
+            bitrate_kbps: track.bitrate_kbps? as u32,
+            is_vbr: track.is_vbr?,
+            file_size: track.file_size? as u64,
+        })
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+    },
+    time::Instant,
+};
+
+/// A broadcast item stamped with the moment it went live.
+#[derive(Debug, Clone)]
+pub struct Timed<T> {
+    pub sent_at: Instant,
+    pub data: T,
+}
+
+impl<T> Timed<T> {
+    pub fn now(data: T) -> Self {
+        Timed {
+            sent_at: Instant::now(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StreamStats {
+    pub delivered: u64,
+    /// Items dropped to catch up with the live edge.
+    pub skipped: u64,
+    /// Times the listener was moved to the live edge.
+    pub jumps: u64,
+}
+
+/// One listener's view of a live broadcast.
+///
+/// Items waiting in the broadcast channel are the listener's buffer. When the
+/// oldest of them is more than `max_latency` old, the buffer is dropped and
+/// the listener continues from the live edge. Items are whole frames/chunks,
+/// so a jump always lands on a boundary. Counters are logged when the
+/// listener goes away.
+pub struct LiveReceiver<T: Clone> {
+    rx: broadcast::Receiver<Timed<T>>,
+    max_latency: Duration,
+    /// Items that must survive a jump, e.g. track markers. Only the latest is kept.
+    keep: fn(&T) -> bool,
+    kept: Option<T>,
+    label: String,
+    stats: StreamStats,
+}
+
+impl<T: Clone> LiveReceiver<T> {
+    pub fn new(rx: broadcast::Receiver<Timed<T>>, max_latency: Duration, label: String) -> Self {
+        LiveReceiver {
+            rx,
+            max_latency,
+            keep: |_| false,
+            kept: None,
+            label,
+            stats: StreamStats::default(),
+        }
+    }
+
+    pub fn keep_when_skipping(mut self, keep: fn(&T) -> bool) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+
+    /// Next item for this listener, or `None` once the broadcast is closed.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            if let Some(data) = self.kept.take() {
+                self.stats.delivered += 1;
+                return Some(data);
+            }
+
+            let item = match self.rx.recv().await {
+                Ok(item) => item,
+                Err(RecvError::Lagged(skipped)) => {
+                    // The channel itself overflowed; the latency check on the
+                    // next item takes care of reaching the live edge
+                    self.stats.skipped += skipped;
+                    continue;
+                }
+                Err(RecvError::Closed) => return None,
+            };
+
+            let latency = item.sent_at.elapsed();
+            if latency > self.max_latency {
+                self.jump_to_live(item.data, latency);
+                continue;
+            }
+
+            self.stats.delivered += 1;
+            return Some(item.data);
+        }
+    }
+
+    fn jump_to_live(&mut self, late: T, latency: Duration) {
+        let before = self.stats.skipped;
+        self.stats.jumps += 1;
+
+        self.consider_skipping(late);
+        loop {
+            match self.rx.try_recv() {
+                Ok(item) => self.consider_skipping(item.data),
+                Err(TryRecvError::Lagged(skipped)) => self.stats.skipped += skipped,
+                Err(_) => break,
+            }
+        }
+
+        println!(
+            "[{}] {} ms behind, jumped to live edge skipping {} items",
+            self.label,
+            latency.as_millis(),
+            self.stats.skipped - before
+        );
+    }
+
+    fn consider_skipping(&mut self, data: T) {
+        if (self.keep)(&data) {
+            if self.kept.replace(data).is_some() {
+                self.stats.skipped += 1;
+            }
+        } else {
+            self.stats.skipped += 1;
+        }
+    }
+}
+
+impl<T: Clone> Drop for LiveReceiver<T> {
+    fn drop(&mut self) {
+        println!(
+            "[{}] Listener closed: {} delivered, {} skipped in {} jumps",
+            self.label, self.stats.delivered, self.stats.skipped, self.stats.jumps
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamped(data: u32, age_ms: u64) -> Timed<u32> {
+        Timed {
+            sent_at: Instant::now() - Duration::from_millis(age_ms),
+            data,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delivers_in_order_within_latency() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut live = LiveReceiver::new(rx, Duration::from_secs(1), "test".to_string());
+        for i in 0..3 {
+            tx.send(stamped(i, 100)).unwrap();
+        }
+
+        for i in 0..3 {
+            assert_eq!(live.recv().await, Some(i));
+        }
+        assert_eq!(
+            live.stats(),
+            StreamStats {
+                delivered: 3,
+                skipped: 0,
+                jumps: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jumps_to_live_edge() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut live = LiveReceiver::new(rx, Duration::from_secs(1), "test".to_string())
+            .keep_when_skipping(|&item| item >= 100);
+
+        tx.send(stamped(0, 3000)).unwrap();
+        tx.send(stamped(100, 2500)).unwrap();
+        tx.send(stamped(1, 2000)).unwrap();
+        tx.send(stamped(101, 1500)).unwrap();
+        tx.send(stamped(2, 1200)).unwrap();
+        tx.send(stamped(3, 500)).unwrap();
+
+        // Everything queued is dropped except the latest marker
+        assert_eq!(live.recv().await, Some(101));
+        tx.send(stamped(4, 0)).unwrap();
+        assert_eq!(live.recv().await, Some(4));
+        assert_eq!(
+            live.stats(),
+            StreamStats {
+                delivered: 2,
+                skipped: 5,
+                jumps: 1
+            }
+        );
+
+        drop(tx);
+        assert_eq!(live.recv().await, None);
+    }
+}
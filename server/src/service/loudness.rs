@@ -0,0 +1,319 @@
+use symphonia::core::audio::SampleBuffer;
+
+use crate::{
+    error::app_error::AppResult, infrastucture::database::models::Track,
+    service::audio_file::AudioFile, service::resampler::Resampler,
+};
+
+/// EBU R128 gating block: 400ms windows advanced in 100ms steps.
+const SEGMENTS_PER_BLOCK: usize = 4;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// True peak is measured on a 4x oversampled signal (ITU-R BS.1770-4).
+const TRUE_PEAK_OVERSAMPLING: u32 = 4;
+
+/// Loudness of a whole track as stored in the `tracks` table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackLoudness {
+    /// Integrated loudness in LUFS.
+    pub integrated_lufs: f32,
+    /// True peak in dBTP.
+    pub true_peak_dbtp: f32,
+}
+
+impl TrackLoudness {
+    /// Loudness stored with the track, if it was analyzed.
+    pub fn stored(track: &Track) -> Option<Self> {
+        Some(TrackLoudness {
+            integrated_lufs: track.loudness_lufs?,
+            true_peak_dbtp: track.true_peak_dbtp?,
+        })
+    }
+
+    /// Constant gain (linear) that brings the track to `target_lufs`, never
+    /// boosting it by more than `max_boost_db` or above `peak_ceiling_dbtp`.
+    pub fn gain_to(&self, target_lufs: f32, max_boost_db: f32, peak_ceiling_dbtp: f32) -> f32 {
+        let gain_db = (target_lufs - self.integrated_lufs)
+            .min(max_boost_db)
+            .min(peak_ceiling_dbtp - self.true_peak_dbtp);
+        10f32.powf(gain_db / 20.0)
+    }
+}
+
+/// Second-order IIR section in direct form I.
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn run(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// K-weighting filter for an arbitrary sample rate: a high shelf modelling the
+/// head followed by the RLB high-pass.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, highpass]
+}
+
+/// Streaming EBU R128 integrated loudness and true peak meter.
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    oversamplers: Vec<Resampler>,
+    segment_len: usize,
+    segment_pos: usize,
+    /// Running sum of squares per channel for the current 100ms segment.
+    segment_sums: Vec<f64>,
+    /// Mean square (summed over channels) of every finished segment.
+    segments: Vec<f64>,
+    peak: f32,
+    oversampled: Vec<f32>,
+    channel_buf: Vec<f32>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            filters: (0..channels).map(|_| k_weighting(sample_rate)).collect(),
+            oversamplers: (0..channels)
+                .map(|_| Resampler::new(sample_rate, sample_rate * TRUE_PEAK_OVERSAMPLING))
+                .collect(),
+            segment_len: (sample_rate as usize / 10).max(1),
+            segment_pos: 0,
+            segment_sums: vec![0.0; channels],
+            segments: Vec::new(),
+            peak: 0.0,
+            oversampled: Vec::new(),
+            channel_buf: Vec::new(),
+        }
+    }
+
+    /// Feed interleaved samples in `-1.0..=1.0`.
+    pub fn add(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (ch, &sample) in frame.iter().enumerate() {
+                let [shelf, highpass] = &mut self.filters[ch];
+                let weighted = highpass.run(shelf.run(sample as f64));
+                self.segment_sums[ch] += weighted * weighted;
+            }
+
+            self.segment_pos += 1;
+            if self.segment_pos == self.segment_len {
+                let power: f64 = self.segment_sums.iter().sum::<f64>() / self.segment_len as f64;
+                self.segments.push(power);
+                self.segment_sums.iter_mut().for_each(|s| *s = 0.0);
+                self.segment_pos = 0;
+            }
+        }
+
+        for ch in 0..self.channels {
+            self.channel_buf.clear();
+            self.channel_buf
+                .extend(samples.iter().skip(ch).step_by(self.channels));
+            self.oversampled.clear();
+            self.oversamplers[ch].process(&self.channel_buf, &mut self.oversampled);
+            self.peak = self
+                .oversampled
+                .iter()
+                .fold(self.peak, |peak, &s| peak.max(s.abs()));
+        }
+    }
+
+    pub fn finish(mut self) -> TrackLoudness {
+        for oversampler in self.oversamplers.iter_mut() {
+            self.oversampled.clear();
+            oversampler.flush(&mut self.oversampled);
+            self.peak = self
+                .oversampled
+                .iter()
+                .fold(self.peak, |peak, &s| peak.max(s.abs()));
+        }
+
+        TrackLoudness {
+            integrated_lufs: self.integrated_loudness() as f32,
+            true_peak_dbtp: (20.0 * (self.peak.max(1e-6) as f64).log10()) as f32,
+        }
+    }
+
+    fn integrated_loudness(&self) -> f64 {
+        let blocks: Vec<f64> = self
+            .segments
+            .windows(SEGMENTS_PER_BLOCK)
+            .map(|w| w.iter().sum::<f64>() / SEGMENTS_PER_BLOCK as f64)
+            .filter(|&power| Self::to_lufs(power) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return ABSOLUTE_GATE_LUFS;
+        }
+
+        let relative_gate = Self::to_lufs(Self::mean(&blocks)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|&power| Self::to_lufs(power) > relative_gate)
+            .collect();
+        if gated.is_empty() {
+            return ABSOLUTE_GATE_LUFS;
+        }
+        Self::to_lufs(Self::mean(&gated))
+    }
+
+    fn mean(values: &[f64]) -> f64 {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+
+    fn to_lufs(power: f64) -> f64 {
+        -0.691 + 10.0 * power.max(1e-20).log10()
+    }
+}
+
+/// Decode the whole file and measure its loudness. This is CPU bound and
+/// should be run on a blocking thread.
+pub fn analyze_file(file_path: &str) -> AppResult<TrackLoudness> {
+    let mut audio = AudioFile::open(file_path)?;
+    let mut meter: Option<LoudnessMeter> = None;
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    while let Ok(packet) = audio.format.next_packet() {
+        if packet.track_id() != audio.track_id {
+            continue;
+        }
+        let decoded = match audio.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(_) => continue,
+        };
+
+        let spec = *decoded.spec();
+        if sample_buf
+            .as_ref()
+            .is_none_or(|buf| buf.capacity() < decoded.capacity() * spec.channels.count())
+        {
+            sample_buf = Some(SampleBuffer::<f32>::new(decoded.capacity() as u64, spec));
+        }
+        let buf = sample_buf.as_mut().unwrap();
+        buf.copy_interleaved_ref(decoded);
+
+        meter
+            .get_or_insert_with(|| LoudnessMeter::new(spec.rate, spec.channels.count()))
+            .add(buf.samples());
+    }
+
+    let meter = meter.ok_or_else(|| anyhow::anyhow!("No audio decoded from {}", file_path))?;
+    Ok(meter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(rate: u32, freq: f64, amplitude: f32, seconds: usize) -> Vec<f32> {
+        (0..rate as usize * seconds)
+            .map(|i| {
+                (2.0 * std::f64::consts::PI * freq * i as f64 / rate as f64).sin() as f32
+                    * amplitude
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_full_scale_sine_reference() {
+        // BS.1770: a 0 dBFS 1 kHz sine in one channel reads -3.01 LKFS
+        let mut meter = LoudnessMeter::new(48000, 1);
+        meter.add(&sine(48000, 1000.0, 1.0, 5));
+        let loudness = meter.finish();
+
+        assert!((loudness.integrated_lufs - -3.01).abs() < 0.05);
+        assert!(loudness.true_peak_dbtp.abs() < 0.1);
+    }
+
+    #[test]
+    fn test_stereo_and_sample_rate() {
+        // Same tone in both channels at 44.1 kHz is 3 dB louder than in one
+        let mono = sine(44100, 1000.0, 0.5, 5);
+        let stereo: Vec<f32> = mono.iter().flat_map(|&s| [s, s]).collect();
+        let mut meter = LoudnessMeter::new(44100, 2);
+        meter.add(&stereo);
+        let loudness = meter.finish();
+
+        assert!((loudness.integrated_lufs - (-3.01 - 6.02 + 3.01)).abs() < 0.05);
+        assert!((loudness.true_peak_dbtp - -6.02).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_silence_is_gated() {
+        let mut meter = LoudnessMeter::new(48000, 1);
+        meter.add(&vec![0.0; 48000 * 10]);
+        meter.add(&sine(48000, 1000.0, 0.5, 20));
+        meter.add(&vec![0.0; 48000 * 10]);
+        let loudness = meter.finish();
+
+        // Only the few blocks straddling the edges may pull it down slightly
+        assert!((loudness.integrated_lufs - (-3.01 - 6.02)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_gain_is_limited() {
+        let loudness = TrackLoudness {
+            integrated_lufs: -30.0,
+            true_peak_dbtp: -6.0,
+        };
+        // Wants +16 dB, boost limit allows +12, peak ceiling allows +5
+        let gain_db = 20.0 * loudness.gain_to(-14.0, 12.0, -1.0).log10();
+        assert!((gain_db - 5.0).abs() < 1e-4);
+
+        let loud = TrackLoudness {
+            integrated_lufs: -8.0,
+            true_peak_dbtp: 0.5,
+        };
+        let gain_db = 20.0 * loud.gain_to(-14.0, 12.0, -1.0).log10();
+        assert!((gain_db - -6.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    error::app_error::AppResult,
+    service::mail_transport::{MailMessage, MailTransport},
+};
+
+/// Writes every email into a directory as an `.eml` file that any mail client
+/// can open, for local development without an SMTP server.
+pub struct FileTransport {
+    dir: PathBuf,
+    counter: AtomicU64,
+}
+
+impl FileTransport {
+    pub const NAME: &'static str = "file";
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileTransport {
+            dir: dir.into(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    fn file_name(&self, message: &MailMessage) -> String {
+        let recipient: String = message
+            .to
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!(
+            "{}-{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f"),
+            self.counter.fetch_add(1, Ordering::Relaxed),
+            recipient
+        )
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileTransport {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn send(&self, message: &MailMessage) -> AppResult<()> {
+        let contents = message.builder().write_to_vec()?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(self.file_name(message));
+        tokio::fs::write(&path, contents).await?;
+        println!(
+            "📧 {} email to {} saved to {}",
+            message.subject,
+            message.to,
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_writes_one_eml_file_per_email() {
+        let dir = std::env::temp_dir().join(format!("mail_dir_{}", std::process::id()));
+        let transport = FileTransport::new(&dir);
+        let message = MailMessage {
+            from: "noreply@example.com".to_string(),
+            to: "listener+1@example.com".to_string(),
+            subject: "Your sign-up code".to_string(),
+            text_body: "Your confirmation code: 123456".to_string(),
+            html_body: "<p>123456</p>".to_string(),
+        };
+
+        transport.send(&message).await.unwrap();
+        transport.send(&message).await.unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].ends_with("-0-listener_1@example.com.eml"));
+
+        let eml = std::fs::read_to_string(dir.join(&names[0])).unwrap();
+        assert!(eml.contains("To: <listener+1@example.com>"));
+        assert!(eml.contains("Subject: Your sign-up code"));
+        assert!(eml.contains("Your confirmation code: 123456"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
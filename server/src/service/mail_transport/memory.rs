@@ -0,0 +1,135 @@
+use std::{sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use tokio::sync::Notify;
+
+use crate::{
+    error::app_error::AppResult,
+    service::mail_transport::{MailMessage, MailTransport},
+};
+
+/// Keeps sent emails in memory, so tests can read the codes they carry.
+#[derive(Default)]
+pub struct MemoryTransport {
+    sent: Mutex<Vec<MailMessage>>,
+    notify: Notify,
+}
+
+impl MemoryTransport {
+    pub const NAME: &'static str = "memory";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every email sent so far, oldest first.
+    pub fn sent(&self) -> Vec<MailMessage> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Latest email to `to`.
+    pub fn last_to(&self, to: &str) -> Option<MailMessage> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|message| message.to == to)
+            .cloned()
+    }
+
+    /// Latest email to `to`, waiting up to `timeout` for one to be sent.
+    /// Emails go through the outbox, so they arrive after the request that
+    /// sent them has returned.
+    pub async fn wait_for(&self, to: &str, timeout: Duration) -> Option<MailMessage> {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let notified = self.notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+                if let Some(message) = self.last_to(to) {
+                    return message;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .ok()
+    }
+
+    pub fn clear(&self) {
+        self.sent.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl MailTransport for MemoryTransport {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn send(&self, message: &MailMessage) -> AppResult<()> {
+        self.sent.lock().unwrap().push(message.clone());
+        self.notify.notify_waiters();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn message(to: &str, text_body: &str) -> MailMessage {
+        MailMessage {
+            from: "noreply@example.com".to_string(),
+            to: to.to_string(),
+            subject: "Your sign-up code".to_string(),
+            text_body: text_body.to_string(),
+            html_body: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_returns_the_latest_email_to_recipient() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport
+            .send(&message("a@example.com", "111111"))
+            .await
+            .unwrap();
+
+        let sender = transport.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            sender
+                .send(&message("b@example.com", "222222"))
+                .await
+                .unwrap();
+            sender
+                .send(&message("a@example.com", "333333"))
+                .await
+                .unwrap();
+        });
+
+        let received = transport
+            .wait_for("b@example.com", Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(received.text_body, "222222");
+        assert!(
+            transport
+                .wait_for("c@example.com", Duration::from_millis(50))
+                .await
+                .is_none()
+        );
+        assert_eq!(
+            transport.last_to("a@example.com").unwrap().text_body,
+            "333333"
+        );
+        assert_eq!(transport.sent().len(), 3);
+
+        transport.clear();
+        assert!(transport.sent().is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+pub mod file;
+pub mod memory;
+pub mod smtp;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mail_send::mail_builder::MessageBuilder;
+
+use crate::{
+    config::{AppConfig, smtp::MailTransportKind},
+    error::app_error::AppResult,
+};
+
+/// A rendered email, ready to be delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MailMessage {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+impl MailMessage {
+    pub fn builder(&self) -> MessageBuilder<'_> {
+        MessageBuilder::new()
+            .from(self.from.as_str())
+            .to(self.to.as_str())
+            .subject(self.subject.as_str())
+            .text_body(self.text_body.as_str())
+            .html_body(self.html_body.as_str())
+    }
+}
+
+/// Where emails from the outbox are delivered.
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, message: &MailMessage) -> AppResult<()>;
+}
+
+/// The transport selected by `MAIL_TRANSPORT`. The in-memory one is also
+/// returned on its own, so tests can read what was sent.
+pub fn from_config(
+    config: &AppConfig,
+) -> (Arc<dyn MailTransport>, Option<Arc<memory::MemoryTransport>>) {
+    let smtp_config = &config.smtp_config;
+    match smtp_config.transport {
+        MailTransportKind::Smtp => (Arc::new(smtp::SmtpTransport::new(smtp_config)), None),
+        MailTransportKind::File => {
+            let dir = smtp_config
+                .mail_dir
+                .clone()
+                .expect("MAIL_DIR must be set for the file mail transport");
+            (Arc::new(file::FileTransport::new(dir)), None)
+        }
+        MailTransportKind::Memory => {
+            let memory = Arc::new(memory::MemoryTransport::new());
+            (memory.clone(), Some(memory))
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use mail_send::{SmtpClient, SmtpClientBuilder};
+use tokio::{net::TcpStream, sync::Mutex};
+use tokio_rustls::client::TlsStream;
+
+use crate::{
+    config::smtp::SMTPConfig,
+    error::app_error::AppResult,
+    service::mail_transport::{MailMessage, MailTransport},
+};
+
+type Connection = SmtpClient<TlsStream<TcpStream>>;
+
+/// Sends over STARTTLS, keeping the connection open between emails.
+pub struct SmtpTransport {
+    host: String,
+    port: u16,
+    login: String,
+    password: String,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl SmtpTransport {
+    pub const NAME: &'static str = "smtp";
+
+    pub fn new(config: &SMTPConfig) -> Self {
+        SmtpTransport {
+            host: config.host.clone(),
+            port: config.port,
+            login: config.login.clone(),
+            password: config.password.clone(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    async fn connect(&self) -> Result<Connection, mail_send::Error> {
+        SmtpClientBuilder::new(self.host.as_str(), self.port)
+            .implicit_tls(false)
+            .credentials((self.login.as_str(), self.password.as_str()))
+            .connect()
+            .await
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpTransport {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn send(&self, message: &MailMessage) -> AppResult<()> {
+        let mut connection = self.connection.lock().await;
+
+        // The server may have closed a kept connection while it was idle, so
+        // a failure on it gets one more try on a fresh one
+        if let Some(client) = connection.as_mut() {
+            if client.send(message.builder()).await.is_ok() {
+                return Ok(());
+            }
+            *connection = None;
+        }
+
+        let mut client = self.connect().await?;
+        client.send(message.builder()).await?;
+        *connection = Some(client);
+        Ok(())
+    }
+}
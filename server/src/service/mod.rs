@@ -1,8 +1,23 @@
+pub mod account_service;
+pub mod api_key_service;
+pub mod audio_file;
 pub mod auth;
+pub mod cc_client;
 pub mod dfpwm;
+pub mod email_outbox;
+pub mod email_template;
+pub mod ingest_service;
+pub mod live_stream;
+pub mod loudness;
+pub mod mail_transport;
+pub mod music_provider;
 pub mod otp_service;
+pub mod playback;
 pub mod playlist_service;
 pub mod radio_service;
+pub mod rate_limiter;
+pub mod resampler;
 pub mod smtp_service;
+pub mod station_policy_service;
 pub mod token_service;
 pub mod track_service;
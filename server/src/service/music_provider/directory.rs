@@ -0,0 +1,255 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+
+use crate::{
+    error::app_error::AppResult,
+    service::{
+        audio_file::{probe_file, read_tags, SUPPORTED_EXTENSIONS},
+        music_provider::{MusicProvider, ProviderTrack, SearchPage, FILE_URL_PREFIX},
+    },
+};
+
+/// Files added to the directory show up after at most this long.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+const DIRECTORY_OWNER_ID: i32 = 0;
+
+/// Audio files in a directory on the server, searched by their tags.
+///
+/// Searches go through the result of the last scan, which `run_scanner`
+/// replaces in the background.
+pub struct DirectoryProvider {
+    root: PathBuf,
+    /// `None` until the first scan is done.
+    snapshot: watch::Sender<Option<Arc<Vec<ProviderTrack>>>>,
+}
+
+impl DirectoryProvider {
+    pub const NAME: &'static str = "directory";
+
+    pub fn new(root: String) -> Self {
+        DirectoryProvider {
+            root: PathBuf::from(root),
+            snapshot: watch::Sender::new(None),
+        }
+    }
+
+    /// Scan the directory every `RESCAN_INTERVAL`, for as long as the server runs.
+    pub async fn run_scanner(self: Arc<Self>) {
+        loop {
+            if let Err(e) = self.rescan().await {
+                eprintln!("❌ Failed to scan {}: {}", self.root.display(), e);
+                // Searches wait for the first scan, don't keep them waiting
+                self.snapshot.send_if_modified(|snapshot| {
+                    let first = snapshot.is_none();
+                    snapshot.get_or_insert_with(Arc::default);
+                    first
+                });
+            }
+            tokio::time::sleep(RESCAN_INTERVAL).await;
+        }
+    }
+
+    /// Replace the searched snapshot with the files in the directory now.
+    pub async fn rescan(&self) -> AppResult<()> {
+        let root = self.root.clone();
+        let tracks = tokio::task::spawn_blocking(move || scan(&root))
+            .await
+            .map_err(|e| anyhow::anyhow!("Directory scan failed: {}", e))??;
+        self.snapshot.send_replace(Some(Arc::new(tracks)));
+        Ok(())
+    }
+
+    async fn tracks(&self) -> AppResult<Arc<Vec<ProviderTrack>>> {
+        let mut snapshot = self.snapshot.subscribe();
+        let tracks = snapshot
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|e| anyhow::anyhow!("Directory scanner is gone: {}", e))?;
+        Ok(tracks.clone().unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl MusicProvider for DirectoryProvider {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn search(&self, query: &str, offset: u32, count: u32) -> AppResult<SearchPage> {
+        let query = query.to_lowercase();
+        let matches = self
+            .tracks()
+            .await?
+            .iter()
+            .filter(|track| track.name().to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+        Ok(SearchPage::slice(matches, offset, count))
+    }
+
+    async fn get_by_id(&self, owner_id: i32, song_id: i32) -> AppResult<Option<ProviderTrack>> {
+        Ok(self
+            .tracks()
+            .await?
+            .iter()
+            .find(|track| track.owner_id == owner_id && track.song_id == song_id)
+            .cloned())
+    }
+
+    fn local_file(&self, url: &str) -> Option<String> {
+        let path = std::fs::canonicalize(url.strip_prefix(FILE_URL_PREFIX)?).ok()?;
+        let root = std::fs::canonicalize(&self.root).ok()?;
+        (path.starts_with(&root) && path.is_file()).then(|| path.to_string_lossy().to_string())
+    }
+}
+
+/// Stable id of a file, derived from its path inside the directory (FNV-1a).
+fn song_id(relative_path: &Path) -> i32 {
+    let hash = relative_path
+        .to_string_lossy()
+        .bytes()
+        .fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+    (hash & 0x7fff_ffff) as i32
+}
+
+fn scan(root: &Path) -> AppResult<Vec<ProviderTrack>> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files)?;
+    files.sort();
+
+    let mut tracks = Vec::with_capacity(files.len());
+    for path in files {
+        let file_path = path.to_string_lossy().to_string();
+        let properties = match probe_file(&file_path) {
+            Ok(properties) => properties,
+            Err(e) => {
+                eprintln!("⚠️  Skipping {}: {}", file_path, e);
+                continue;
+            }
+        };
+        let tags = read_tags(&file_path).unwrap_or_default();
+
+        // "Artist - Title.mp3" is the usual naming for untagged files
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (stem_artist, stem_title) = match stem.split_once(" - ") {
+            Some((artist, title)) => (Some(artist.trim()), title.trim()),
+            None => (None, stem.as_str()),
+        };
+
+        tracks.push(ProviderTrack {
+            song_id: song_id(path.strip_prefix(root).unwrap_or(&path)),
+            owner_id: DIRECTORY_OWNER_ID,
+            artist: tags
+                .artist
+                .or(stem_artist.map(str::to_string))
+                .unwrap_or_else(|| "Unknown artist".to_string()),
+            title: tags.title.unwrap_or_else(|| stem_title.to_string()),
+            duration_sec: properties.duration_sec(),
+            download_url: format!("{}{}", FILE_URL_PREFIX, file_path),
+        });
+    }
+    Ok(tracks)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> AppResult<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::playback::silent_mp3_frame;
+
+    #[tokio::test]
+    async fn test_untagged_files_are_named_after_the_file() {
+        let root = std::env::temp_dir().join(format!("music_dir_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("russian")).unwrap();
+        let (frame, _) = silent_mp3_frame(44100, 2).unwrap();
+        let audio = frame.repeat(100);
+        std::fs::write(root.join("russian/Kino - Gruppa krovi.mp3"), &audio).unwrap();
+        std::fs::write(root.join("Untitled.mp3"), &audio).unwrap();
+        std::fs::write(root.join("cover.jpg"), b"not audio").unwrap();
+        std::fs::write(root.join("Aria - Bespechnyi angel.ogg"), b"not MP3").unwrap();
+
+        let provider = DirectoryProvider::new(root.to_string_lossy().to_string());
+        provider.rescan().await.unwrap();
+        let found = provider.search("krovi", 0, 5).await;
+        let all = provider.search("", 0, 5).await;
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let found = found.unwrap().tracks;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].artist, "Kino");
+        assert_eq!(found[0].title, "Gruppa krovi");
+        assert_eq!(found[0].duration_sec, 3);
+        assert_eq!(
+            found[0].download_url,
+            format!(
+                "file://{}",
+                root.join("russian/Kino - Gruppa krovi.mp3").display()
+            )
+        );
+        assert_eq!(
+            found[0].song_id,
+            song_id(Path::new("russian/Kino - Gruppa krovi.mp3"))
+        );
+
+        let names: Vec<String> = all.unwrap().tracks.iter().map(|t| t.name()).collect();
+        assert_eq!(
+            names,
+            vec!["Unknown artist - Untitled", "Kino - Gruppa krovi"]
+        );
+    }
+
+    #[test]
+    fn test_local_files_stay_inside_the_directory() {
+        let base = std::env::temp_dir().join(format!("music_root_{}", std::process::id()));
+        let root = base.join("music");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("song.mp3"), b"audio").unwrap();
+        std::fs::write(base.join("secret.txt"), b"secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(base.join("secret.txt"), root.join("link.mp3")).unwrap();
+
+        let provider = DirectoryProvider::new(root.to_string_lossy().to_string());
+        let url = |path: PathBuf| format!("{}{}", FILE_URL_PREFIX, path.display());
+        let inside = provider.local_file(&url(root.join("song.mp3")));
+        let escaped = provider.local_file(&url(root.join("../secret.txt")));
+        let outside = provider.local_file(&url(base.join("secret.txt")));
+        let linked = provider.local_file(&url(root.join("link.mp3")));
+        let missing = provider.local_file(&url(root.join("missing.mp3")));
+        let remote = provider.local_file("https://example.com/song.mp3");
+        let song = std::fs::canonicalize(root.join("song.mp3")).unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(inside, Some(song.to_string_lossy().to_string()));
+        assert_eq!(escaped, None);
+        assert_eq!(outside, None);
+        assert_eq!(linked, None);
+        assert_eq!(missing, None);
+        assert_eq!(remote, None);
+    }
+}
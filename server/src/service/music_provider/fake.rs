@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+
+use crate::{
+    error::app_error::AppResult,
+    service::music_provider::{MusicProvider, ProviderTrack, SearchPage},
+};
+
+/// In-memory provider with a fixed track list, for tests.
+pub struct FakeProvider {
+    tracks: Vec<ProviderTrack>,
+}
+
+impl FakeProvider {
+    pub const NAME: &'static str = "fake";
+
+    pub fn new(tracks: Vec<ProviderTrack>) -> Self {
+        FakeProvider { tracks }
+    }
+}
+
+#[async_trait]
+impl MusicProvider for FakeProvider {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn search(&self, query: &str, offset: u32, count: u32) -> AppResult<SearchPage> {
+        let query = query.to_lowercase();
+        let matches = self
+            .tracks
+            .iter()
+            .filter(|track| track.name().to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+        Ok(SearchPage::slice(matches, offset, count))
+    }
+
+    async fn get_by_id(&self, owner_id: i32, song_id: i32) -> AppResult<Option<ProviderTrack>> {
+        Ok(self
+            .tracks
+            .iter()
+            .find(|track| track.owner_id == owner_id && track.song_id == song_id)
+            .cloned())
+    }
+}
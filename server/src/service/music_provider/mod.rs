@@ -0,0 +1,234 @@
+pub mod directory;
+#[cfg(test)]
+pub mod fake;
+pub mod vk;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    config::{music::MusicProviderKind, AppConfig},
+    error::app_error::{AppError, AppResult},
+};
+
+/// URLs with this prefix point at a file on this machine, which is read in
+/// place instead of being downloaded.
+pub const FILE_URL_PREFIX: &str = "file://";
+
+/// A track as described by a music provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderTrack {
+    pub song_id: i32,
+    pub owner_id: i32,
+    pub artist: String,
+    pub title: String,
+    /// Reported by the provider; the real one is measured after download.
+    pub duration_sec: i32,
+    pub download_url: String,
+}
+
+/// One page of search results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchPage {
+    pub tracks: Vec<ProviderTrack>,
+    /// Number of matches over all pages.
+    pub total: u32,
+}
+
+impl SearchPage {
+    /// Page out of every match, for providers that search in memory.
+    pub fn slice(matches: Vec<ProviderTrack>, offset: u32, count: u32) -> Self {
+        let total = matches.len() as u32;
+        let tracks = matches
+            .into_iter()
+            .skip(offset as usize)
+            .take(count as usize)
+            .collect();
+        SearchPage { tracks, total }
+    }
+
+    /// Offset of the page after this one, if there are more matches.
+    pub fn next_offset(&self, offset: u32) -> Option<u32> {
+        let next = offset.saturating_add(self.tracks.len() as u32);
+        (!self.tracks.is_empty() && next < self.total).then_some(next)
+    }
+}
+
+impl ProviderTrack {
+    pub fn name(&self) -> String {
+        format!("{} - {}", self.artist, self.title)
+    }
+}
+
+/// A source of tracks that users can search and queue.
+#[async_trait]
+pub trait MusicProvider: Send + Sync {
+    /// Stored in `tracks.source` of every track found through this provider.
+    fn name(&self) -> &'static str;
+
+    async fn search(&self, query: &str, offset: u32, count: u32) -> AppResult<SearchPage>;
+
+    async fn get_by_id(&self, owner_id: i32, song_id: i32) -> AppResult<Option<ProviderTrack>>;
+
+    /// Fresh download URL of a track. Provider URLs may expire, so stored
+    /// ones are resolved again when they stop working.
+    async fn resolve_download_url(&self, owner_id: i32, song_id: i32) -> AppResult<String> {
+        self.get_by_id(owner_id, song_id)
+            .await?
+            .map(|track| track.download_url)
+            .ok_or_else(|| {
+                AppError::NotFound(
+                    format!(
+                        "Track {}_{} not found in {}",
+                        owner_id,
+                        song_id,
+                        self.name()
+                    ),
+                    None,
+                )
+            })
+    }
+
+    /// Path of a `file://` download URL, only for providers that serve files
+    /// of this machine and only if the file is one of theirs.
+    fn local_file(&self, _url: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Every configured provider, one of which is used for new searches.
+pub struct MusicProviders {
+    active: Arc<dyn MusicProvider>,
+    providers: Vec<Arc<dyn MusicProvider>>,
+    directory: Option<Arc<directory::DirectoryProvider>>,
+}
+
+impl MusicProviders {
+    pub fn new(active: Arc<dyn MusicProvider>, mut providers: Vec<Arc<dyn MusicProvider>>) -> Self {
+        if !providers.iter().any(|p| p.name() == active.name()) {
+            providers.push(active.clone());
+        }
+        MusicProviders {
+            active,
+            providers,
+            directory: None,
+        }
+    }
+
+    pub fn from_config(config: &AppConfig) -> Self {
+        let vk: Arc<dyn MusicProvider> = Arc::new(vk::VkProvider::new(
+            config.music_api_url.clone(),
+            config.music_api_token.clone(),
+        ));
+        let mut providers = vec![vk.clone()];
+        let directory = config
+            .music_config
+            .dir_path
+            .as_ref()
+            .map(|dir_path| Arc::new(directory::DirectoryProvider::new(dir_path.clone())));
+        if let Some(directory) = &directory {
+            providers.push(directory.clone());
+        }
+
+        let active = match config.music_config.provider {
+            MusicProviderKind::Vk => vk,
+            MusicProviderKind::Directory => providers
+                .iter()
+                .find(|p| p.name() == directory::DirectoryProvider::NAME)
+                .cloned()
+                .expect("MUSIC_DIR_PATH must be set for the directory provider"),
+        };
+        MusicProviders {
+            directory,
+            ..Self::new(active, providers)
+        }
+    }
+
+    pub fn active(&self) -> &Arc<dyn MusicProvider> {
+        &self.active
+    }
+
+    /// The directory provider, whose scanner has to be started.
+    pub fn directory(&self) -> Option<&Arc<directory::DirectoryProvider>> {
+        self.directory.as_ref()
+    }
+
+    /// Provider a stored track came from, by its `tracks.source`.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn MusicProvider>> {
+        self.providers.iter().find(|p| p.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::music_provider::fake::FakeProvider;
+
+    fn track(song_id: i32, artist: &str, title: &str) -> ProviderTrack {
+        ProviderTrack {
+            song_id,
+            owner_id: 7,
+            artist: artist.to_string(),
+            title: title.to_string(),
+            duration_sec: 180,
+            download_url: format!("https://example.com/{}.mp3", song_id),
+        }
+    }
+
+    #[test]
+    fn test_registry_finds_provider_by_source() {
+        let fake: Arc<dyn MusicProvider> = Arc::new(FakeProvider::new(vec![]));
+        let providers = MusicProviders::new(fake, vec![]);
+
+        assert_eq!(providers.active().name(), FakeProvider::NAME);
+        assert!(providers.get(FakeProvider::NAME).is_some());
+        assert!(providers.get("vk").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_download_url_uses_get_by_id() {
+        let provider = FakeProvider::new(vec![track(1, "Kino", "Gruppa krovi")]);
+
+        let url = provider.resolve_download_url(7, 1).await.unwrap();
+        assert_eq!(url, "https://example.com/1.mp3");
+        assert!(matches!(
+            provider.resolve_download_url(7, 2).await,
+            Err(AppError::NotFound(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fake_search_is_case_insensitive() {
+        let provider = FakeProvider::new(vec![
+            track(1, "Kino", "Gruppa krovi"),
+            track(2, "Kino", "Kukushka"),
+            track(3, "Aria", "Bespechnyi angel"),
+        ]);
+
+        let found = provider.search("KINO", 0, 5).await.unwrap();
+        assert_eq!(
+            found.tracks.iter().map(|t| t.song_id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(found.total, 2);
+        assert_eq!(found.next_offset(0), None);
+
+        let second_page = provider.search("kino", 1, 1).await.unwrap();
+        assert_eq!(second_page.tracks[0].song_id, 2);
+        assert_eq!(second_page.total, 2);
+        assert_eq!(
+            provider.search("kino", 0, 1).await.unwrap().next_offset(0),
+            Some(1)
+        );
+        assert_eq!(second_page.next_offset(1), None);
+
+        let past_end = provider.search("kino", 2, 1).await.unwrap();
+        assert!(past_end.tracks.is_empty());
+        assert_eq!(past_end.next_offset(2), None);
+        assert_eq!(
+            provider.get_by_id(7, 3).await.unwrap().map(|t| t.name()),
+            Some("Aria - Bespechnyi angel".to_string())
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+
+use crate::{
+    error::app_error::{AppError, AppResult},
+    service::music_provider::{MusicProvider, ProviderTrack, SearchPage},
+};
+
+const API_VERSION: &str = "5.131";
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TrackFromApi {
+    id: i32,
+    owner_id: i32,
+    artist: String,
+    title: String,
+    duration: i32,
+    url: String,
+}
+
+impl From<TrackFromApi> for ProviderTrack {
+    fn from(track: TrackFromApi) -> Self {
+        ProviderTrack {
+            song_id: track.id,
+            owner_id: track.owner_id,
+            artist: track.artist,
+            title: track.title,
+            duration_sec: track.duration,
+            download_url: track.url,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct SearchTrackInApiItems {
+    count: u32,
+    items: Vec<TrackFromApi>,
+}
+#[derive(serde::Deserialize, Debug)]
+struct SearchTrackByNameInApiResponse {
+    response: SearchTrackInApiItems,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct SearchTrackByIdInApiResponse {
+    response: Vec<TrackFromApi>,
+}
+
+/// The VK audio API (`audio.search` and `audio.getById`).
+pub struct VkProvider {
+    api_url: String,
+    api_token: String,
+    client: reqwest::Client,
+}
+
+impl VkProvider {
+    pub const NAME: &'static str = "vk";
+
+    pub fn new(api_url: String, api_token: String) -> Self {
+        VkProvider {
+            api_url,
+            api_token,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> AppResult<T> {
+        let url = reqwest::Url::parse_with_params(
+            format!("{}.{}", self.api_url, method).as_str(),
+            params.iter().copied().chain([
+                ("access_token", self.api_token.as_str()),
+                ("v", API_VERSION),
+            ]),
+        )
+        .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
+
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
+
+        response
+            .json::<T>()
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))
+    }
+}
+
+#[async_trait]
+impl MusicProvider for VkProvider {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn search(&self, query: &str, offset: u32, count: u32) -> AppResult<SearchPage> {
+        let offset = offset.to_string();
+        let count = count.to_string();
+        let response: SearchTrackByNameInApiResponse = self
+            .call(
+                "search",
+                &[
+                    ("q", query),
+                    ("offset", offset.as_str()),
+                    ("count", count.as_str()),
+                ],
+            )
+            .await?;
+        Ok(SearchPage {
+            tracks: response
+                .response
+                .items
+                .into_iter()
+                .map(ProviderTrack::from)
+                .collect(),
+            total: response.response.count,
+        })
+    }
+
+    async fn get_by_id(&self, owner_id: i32, song_id: i32) -> AppResult<Option<ProviderTrack>> {
+        let audios = format!("{}_{}", owner_id, song_id);
+        let response: SearchTrackByIdInApiResponse =
+            self.call("getById", &[("audios", audios.as_str())]).await?;
+        Ok(response
+            .response
+            .into_iter()
+            .next()
+            .map(ProviderTrack::from))
+    }
+}
@@ -1,17 +1,12 @@
-use std::{
-    hash::{DefaultHasher, Hash, Hasher},
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use hmac::{Hmac, Mac};
 use rand::{Rng, thread_rng};
+use sha2::Sha256;
 
 use crate::error::app_error::{AppError, AppResult};
 
-#[derive(Hash)]
-pub struct HashData {
-    pub email: String,
-    pub otp: u32,
-}
+type HmacSha256 = Hmac<Sha256>;
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Claims {
@@ -20,11 +15,13 @@ pub struct Claims {
     exp: u64,
 }
 
-pub struct OTPService {}
+pub struct OTPService {
+    secret: String,
+}
 
 impl OTPService {
-    pub fn new() -> Self {
-        OTPService {}
+    pub fn new(secret: String) -> Self {
+        OTPService { secret }
     }
 
     pub fn generate(&self, len: u32) -> AppResult<u32> {
@@ -44,15 +41,34 @@ impl OTPService {
         Ok(thread_rng().gen_range(lower..upper))
     }
 
-    pub fn make_otp_hash(&self, email: &str, otp: u32) -> String {
-        let mut hasher = DefaultHasher::new();
-        let data = HashData {
-            email: email.to_string(),
-            otp,
-        };
-        data.hash(&mut hasher);
-        hasher.finish().to_string()
+    /// HMAC of the OTP, bound to the email and the id of the token it was sent
+    /// with, so a stored hash is useless for any other token.
+    pub fn make_otp_hash(&self, email: &str, token_id: &str, otp: &str) -> String {
+        hex::encode(self.mac(email, token_id, otp).finalize().into_bytes())
+    }
+
+    /// Compares in constant time.
+    pub fn verify_otp_hash(&self, email: &str, token_id: &str, otp: &str, hash: &str) -> bool {
+        match hex::decode(hash) {
+            Ok(expected) => self
+                .mac(email, token_id, otp)
+                .verify_slice(&expected)
+                .is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn mac(&self, email: &str, token_id: &str, otp: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+        for part in [email, token_id, otp] {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part.as_bytes());
+        }
+        mac
     }
+
     pub fn is_otp_expired(&self, send_timestamp_seconds: u64) -> bool {
         let current_timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -61,3 +77,28 @@ impl OTPService {
         current_timestamp - send_timestamp_seconds > 5 * 60
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otp_hash_is_bound_to_email_and_token() {
+        let service = OTPService::new("secret".to_string());
+        let hash = service.make_otp_hash("user@example.com", "token-1", "123456");
+
+        assert!(service.verify_otp_hash("user@example.com", "token-1", "123456", &hash));
+        assert!(!service.verify_otp_hash("user@example.com", "token-1", "654321", &hash));
+        assert!(!service.verify_otp_hash("user@example.com", "token-2", "123456", &hash));
+        assert!(!service.verify_otp_hash("other@example.com", "token-1", "123456", &hash));
+        assert!(!service.verify_otp_hash("user@example.com", "token-1", "123456", "not hex"));
+        assert!(!hash.contains("123456"));
+    }
+
+    #[test]
+    fn test_otp_hash_depends_on_the_key() {
+        let hash = OTPService::new("a".to_string()).make_otp_hash("e", "t", "123456");
+        let other = OTPService::new("b".to_string()).make_otp_hash("e", "t", "123456");
+        assert_ne!(hash, other);
+    }
+}
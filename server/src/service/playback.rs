@@ -0,0 +1,622 @@
+use std::time::Duration;
+
+use axum::body::Bytes;
+use biquad::{Biquad, Coefficients, DirectForm2Transposed, ToHertz, Type, Q_BUTTERWORTH_F32};
+use tokio::{sync::broadcast, time::Instant};
+
+use crate::service::{
+    dfpwm::DfpwmEncoder, live_stream::Timed, loudness::TrackLoudness, resampler::Resampler,
+};
+
+/// If the broadcaster falls further than this behind the clock (e.g. while a
+/// track was downloading), the clock is restarted instead of bursting to catch up.
+const MAX_CLOCK_LAG: Duration = Duration::from_secs(1);
+
+pub const DFPWM_SAMPLE_RATE: u32 = 48000;
+/// DFPWM has little dynamic range, so tracks are normalized fairly loud and
+/// the compressor/limiter below take care of the peaks.
+const DFPWM_TARGET_LUFS: f32 = -14.0;
+const DFPWM_MAX_BOOST_DB: f32 = 12.0;
+const DFPWM_PEAK_CEILING_DBTP: f32 = 6.0;
+/// 128ms at 48kHz (кратно 8 для DFPWM)
+pub const DFPWM_CHUNK_SAMPLES: usize = 6144;
+
+/// Station-wide playback clock.
+///
+/// Media time is accumulated as an exact sample count per track and converted
+/// into absolute deadlines, so pacing does not drift with sleep jitter.
+pub struct PlaybackClock {
+    origin: Instant,
+    /// Media time of every finished track since `origin`.
+    elapsed: Duration,
+    track_frames: u64,
+    track_rate: u32,
+}
+
+impl PlaybackClock {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            origin: now,
+            elapsed: Duration::ZERO,
+            track_frames: 0,
+            track_rate: 0,
+        }
+    }
+
+    /// Start counting a new track at `sample_rate`. If the clock is lagging
+    /// behind `now` it is moved forward to `now`.
+    pub fn start_track(&mut self, sample_rate: u32, now: Instant) {
+        self.elapsed += self.track_time();
+        self.track_frames = 0;
+        self.track_rate = sample_rate;
+
+        if now > self.deadline() + MAX_CLOCK_LAG {
+            self.origin = now;
+            self.elapsed = Duration::ZERO;
+        }
+    }
+
+    /// Account for `frames` samples per channel and return the instant at which
+    /// they have finished playing.
+    pub fn advance(&mut self, frames: u64) -> Instant {
+        self.track_frames += frames;
+        self.deadline()
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.origin + self.elapsed + self.track_time()
+    }
+
+    fn track_time(&self) -> Duration {
+        if self.track_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos(self.track_frames * 1_000_000_000 / self.track_rate as u64)
+    }
+}
+
+/// One decoded packet, handed to every output at the same clock tick.
+pub struct AudioFrame<'a> {
+    /// The compressed packet as read from the file, if it can be passed through as MP3.
+    pub mp3: Option<&'a [u8]>,
+    /// Interleaved PCM in `-1.0..=1.0`.
+    pub samples: &'a [f32],
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+/// What outputs need to know about a track before its first frame.
+pub struct TrackParams {
+    /// "Artist - Title", as shown to listeners.
+    pub name: String,
+    /// Measured loudness, if the track has been analyzed.
+    pub loudness: Option<TrackLoudness>,
+}
+
+/// One MPEG audio layer III frame of digital silence and the number of samples
+/// per channel it covers. With all side info and main data zeroed every
+/// granule decodes to silence, so no encoder is needed.
+pub fn silent_mp3_frame(sample_rate: u32, channels: usize) -> Option<(Vec<u8>, u64)> {
+    // (version bits, sample rate index, samples per frame, bitrate index, kbps)
+    let (version, rate_index, frame_samples, bitrate_index, kbps) = match sample_rate {
+        44100 => (0b11, 0, 1152, 0b1001, 128),
+        48000 => (0b11, 1, 1152, 0b1001, 128),
+        32000 => (0b11, 2, 1152, 0b1001, 128),
+        22050 => (0b10, 0, 576, 0b1000, 64),
+        24000 => (0b10, 1, 576, 0b1000, 64),
+        16000 => (0b10, 2, 576, 0b1000, 64),
+        11025 => (0b00, 0, 576, 0b1000, 64),
+        12000 => (0b00, 1, 576, 0b1000, 64),
+        8000 => (0b00, 2, 576, 0b1000, 64),
+        _ => return None,
+    };
+    let mode = if channels == 1 { 0b11 } else { 0b00 };
+    let len = (frame_samples / 8 * kbps * 1000 / sample_rate as u64) as usize;
+
+    let mut frame = vec![0u8; len];
+    // Sync word, version, layer III, no CRC
+    frame[0] = 0xFF;
+    frame[1] = 0xE0 | (version << 3) | (0b01 << 1) | 1;
+    frame[2] = (bitrate_index << 4) | (rate_index << 2);
+    frame[3] = mode << 6;
+    Some((frame, frame_samples))
+}
+
+/// Silence fed to every output while the station is paused, so listeners keep
+/// receiving data and stay connected.
+pub struct Silence {
+    mp3: Option<Vec<u8>>,
+    frames: u64,
+    samples: Vec<f32>,
+    channels: usize,
+    sample_rate: u32,
+}
+
+impl Silence {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let (mp3, frames) = match silent_mp3_frame(sample_rate, channels) {
+            Some((frame, frames)) => (Some(frame), frames),
+            None => (None, 1152),
+        };
+        Silence {
+            mp3,
+            frames,
+            samples: vec![0.0; frames as usize * channels],
+            channels,
+            sample_rate,
+        }
+    }
+
+    pub fn frame(&self) -> AudioFrame<'_> {
+        AudioFrame {
+            mp3: self.mp3.as_deref(),
+            samples: &self.samples,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Samples per channel in one frame.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+/// A broadcast format fed from the shared decoder.
+pub trait AudioOutput: Send {
+    fn start_track(&mut self, _params: &TrackParams) {}
+
+    fn write(&mut self, frame: &AudioFrame);
+
+    /// Called when a track ends or is interrupted, before the next one starts.
+    fn finish_track(&mut self);
+}
+
+/// Forwards the original MP3 frames without re-encoding.
+pub struct Mp3Output {
+    sender: broadcast::Sender<Timed<Bytes>>,
+}
+
+impl Mp3Output {
+    pub fn new(sender: broadcast::Sender<Timed<Bytes>>) -> Self {
+        Self { sender }
+    }
+}
+
+impl AudioOutput for Mp3Output {
+    fn write(&mut self, frame: &AudioFrame) {
+        if let Some(data) = frame.mp3 {
+            let _ = self.sender.send(Timed::now(Bytes::copy_from_slice(data)));
+        }
+    }
+
+    fn finish_track(&mut self) {}
+}
+
+/// An item of a DFPWM broadcast channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DfpwmPacket {
+    /// Encoded audio, `DFPWM_CHUNK_SAMPLES` samples except at the end of a track.
+    Audio(Bytes),
+    /// A new track starts with the next audio packet. The encoder is reset at
+    /// this point, so clients should reset their decoder too.
+    TrackStart { name: String },
+}
+
+/// Which speaker a DFPWM stream is meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DfpwmChannel {
+    #[default]
+    Mono,
+    Left,
+    Right,
+}
+
+impl DfpwmChannel {
+    /// Order of the broadcast senders handed to `DfpwmOutput`.
+    pub const ALL: [DfpwmChannel; 3] =
+        [DfpwmChannel::Mono, DfpwmChannel::Left, DfpwmChannel::Right];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DfpwmChannel::Mono => "mono",
+            DfpwmChannel::Left => "left",
+            DfpwmChannel::Right => "right",
+        }
+    }
+}
+
+/// Processing and encoding state of one DFPWM channel.
+struct DfpwmChain {
+    sender: broadcast::Sender<Timed<DfpwmPacket>>,
+    encoder: DfpwmEncoder,
+    resampler: Option<Resampler>,
+    lowpass: [DirectForm2Transposed<f32>; 2],
+    pre_emphasis_prev: f32,
+    dither_state: u32,
+    resampled: Vec<f32>,
+    /// Filtered and compressed samples of the current block, before limiting.
+    shaped: Vec<f32>,
+    pending: Vec<i8>,
+    encoded: Vec<u8>,
+}
+
+impl DfpwmChain {
+    fn new(sender: broadcast::Sender<Timed<DfpwmPacket>>, dither_seed: u32) -> Self {
+        // Butterworth lowpass at 18kHz, 2 stages = 4th order
+        let coeffs = Coefficients::<f32>::from_params(
+            Type::LowPass,
+            (DFPWM_SAMPLE_RATE as f32).hz(),
+            18000.0.hz(),
+            Q_BUTTERWORTH_F32,
+        )
+        .unwrap();
+
+        Self {
+            sender,
+            encoder: DfpwmEncoder::new(),
+            resampler: None,
+            lowpass: [
+                DirectForm2Transposed::<f32>::new(coeffs),
+                DirectForm2Transposed::<f32>::new(coeffs),
+            ],
+            pre_emphasis_prev: 0.0,
+            dither_state: dither_seed,
+            resampled: Vec::new(),
+            shaped: Vec::new(),
+            pending: Vec::with_capacity(DFPWM_CHUNK_SAMPLES),
+            encoded: Vec::with_capacity(DFPWM_CHUNK_SAMPLES / 8),
+        }
+    }
+
+    /// Resample `input` to 48kHz into `self.resampled`.
+    fn resample(&mut self, input: &[f32], sample_rate: u32) {
+        if self
+            .resampler
+            .as_ref()
+            .is_none_or(|r| r.input_rate() != sample_rate)
+        {
+            self.resampler = (sample_rate != DFPWM_SAMPLE_RATE)
+                .then(|| Resampler::new(sample_rate, DFPWM_SAMPLE_RATE));
+        }
+
+        self.resampled.clear();
+        match self.resampler.as_mut() {
+            Some(resampler) => resampler.process(input, &mut self.resampled),
+            None => self.resampled.extend_from_slice(input),
+        }
+    }
+
+    /// Drain the resampler lookahead into `self.resampled`.
+    fn flush_resampler(&mut self) {
+        self.resampled.clear();
+        if let Some(mut resampler) = self.resampler.take() {
+            resampler.flush(&mut self.resampled);
+        }
+    }
+
+    /// Lowpass, track gain and soft-knee compression of `self.resampled`.
+    /// Returns the peak of the shaped block.
+    fn shape(&mut self, gain: f32) -> f32 {
+        self.shaped.clear();
+        for &s in &self.resampled {
+            let s1 = self.lowpass[0].run(s);
+            let filtered = self.lowpass[1].run(s1).clamp(-32768.0, 32767.0);
+
+            // Soft-knee compressor, 3:1 above the threshold
+            let s = filtered * gain;
+            let threshold = 20000.0;
+            let ratio = 3.0;
+            let compressed = if s.abs() > threshold {
+                (threshold + (s.abs() - threshold) / ratio) * s.signum()
+            } else {
+                s
+            };
+            self.shaped.push(compressed);
+        }
+        self.shaped.iter().map(|&s| s.abs()).fold(1.0f32, f32::max)
+    }
+
+    /// Limiting, pre-emphasis and dither, then conversion to signed 8-bit.
+    fn emit(&mut self, limiter_gain: f32) {
+        // Gentle pre-emphasis (0.7 instead of 0.95) for DFPWM
+        let pre_emphasis_coef = 0.7;
+        let shaped = std::mem::take(&mut self.shaped);
+        for &sample in &shaped {
+            let limited = sample * limiter_gain;
+            let emphasized = limited - (self.pre_emphasis_prev * pre_emphasis_coef);
+            self.pre_emphasis_prev = limited;
+
+            // Triangular dither, ~0.25 LSB of 8-bit range
+            let dither = self.triangular_dither() * 32.0;
+            let sample_8bit = ((emphasized + dither) / 256.0).clamp(-128.0, 127.0) as i8;
+            self.pending.push(sample_8bit);
+        }
+        self.shaped = shaped;
+    }
+
+    fn triangular_dither(&mut self) -> f32 {
+        let state = &mut self.dither_state;
+        *state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        let r1 = ((*state >> 16) & 0xFFFF) as f32 / 65536.0;
+        *state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        let r2 = ((*state >> 16) & 0xFFFF) as f32 / 65536.0;
+        r1 - r2
+    }
+
+    fn send_chunks(&mut self) {
+        while self.pending.len() >= DFPWM_CHUNK_SAMPLES {
+            let chunk: Vec<i8> = self.pending.drain(..DFPWM_CHUNK_SAMPLES).collect();
+            self.encoded.clear();
+            self.encoder.encode(&chunk, &mut self.encoded);
+            let chunk = Bytes::copy_from_slice(&self.encoded);
+            let _ = self.sender.send(Timed::now(DfpwmPacket::Audio(chunk)));
+        }
+    }
+
+    fn send_remainder(&mut self) {
+        if !self.pending.is_empty() {
+            self.encoded.clear();
+            self.encoder.encode(&self.pending, &mut self.encoded);
+            self.pending.clear();
+            let chunk = Bytes::copy_from_slice(&self.encoded);
+            let _ = self.sender.send(Timed::now(DfpwmPacket::Audio(chunk)));
+        }
+    }
+}
+
+/// Limiter gain that keeps `peak` below the 8-bit headroom.
+fn limiter_gain(peak: f32) -> f32 {
+    if peak > 28000.0 {
+        28000.0 / peak
+    } else {
+        1.0
+    }
+}
+
+/// Encodes DFPWM for ComputerCraft speakers: a mono downmix plus separate left
+/// and right channels for two-speaker setups.
+///
+/// Every channel is fed the same number of samples and the same resampling
+/// ratio, so their chunks always cover the same stretch of audio.
+pub struct DfpwmOutput {
+    /// Indexed by `DfpwmChannel::index`.
+    chains: [DfpwmChain; 3],
+    /// Constant per-track gain from loudness analysis.
+    gain: f32,
+    input: Vec<f32>,
+}
+
+impl DfpwmOutput {
+    /// `senders` are in `DfpwmChannel::ALL` order.
+    pub fn new(senders: [broadcast::Sender<Timed<DfpwmPacket>>; 3]) -> Self {
+        let [mono, left, right] = senders;
+        Self {
+            chains: [
+                DfpwmChain::new(mono, 0x12345678),
+                DfpwmChain::new(left, 0x2468ace0),
+                DfpwmChain::new(right, 0x13579bdf),
+            ],
+            gain: 1.0,
+            input: Vec::new(),
+        }
+    }
+
+    /// Shape and emit the blocks currently in `resampled` of every chain. Left
+    /// and right share a limiter gain so the stereo image does not shift.
+    fn process_blocks(&mut self) {
+        let [mono, left, right] = &mut self.chains;
+
+        let peak = mono.shape(self.gain);
+        mono.emit(limiter_gain(peak));
+
+        let peak = left.shape(self.gain).max(right.shape(self.gain));
+        left.emit(limiter_gain(peak));
+        right.emit(limiter_gain(peak));
+
+        for chain in self.chains.iter_mut() {
+            chain.send_chunks();
+        }
+    }
+}
+
+impl AudioOutput for DfpwmOutput {
+    fn start_track(&mut self, params: &TrackParams) {
+        for chain in self.chains.iter_mut() {
+            chain.encoder = DfpwmEncoder::new();
+            let _ = chain.sender.send(Timed::now(DfpwmPacket::TrackStart {
+                name: params.name.clone(),
+            }));
+        }
+
+        self.gain = params.loudness.map_or(1.0, |loudness| {
+            loudness.gain_to(
+                DFPWM_TARGET_LUFS,
+                DFPWM_MAX_BOOST_DB,
+                DFPWM_PEAK_CEILING_DBTP,
+            )
+        });
+    }
+
+    fn write(&mut self, frame: &AudioFrame) {
+        let channels = frame.channels.max(1);
+
+        for channel in DfpwmChannel::ALL {
+            // Scaled to the 16-bit range the processing chain expects; a mono
+            // source feeds both speakers
+            self.input.clear();
+            self.input
+                .extend(frame.samples.chunks(channels).map(|chunk| {
+                    let sample = match channel {
+                        DfpwmChannel::Mono => chunk.iter().sum::<f32>() / chunk.len() as f32,
+                        DfpwmChannel::Left => chunk[0],
+                        DfpwmChannel::Right => chunk[chunk.len().min(2) - 1],
+                    };
+                    sample * 32768.0
+                }));
+            self.chains[channel.index()].resample(&self.input, frame.sample_rate);
+        }
+
+        self.process_blocks();
+    }
+
+    fn finish_track(&mut self) {
+        for chain in self.chains.iter_mut() {
+            chain.flush_resampler();
+        }
+        self.process_blocks();
+
+        for chain in self.chains.iter_mut() {
+            chain.send_remainder();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_deadlines_are_exact() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new(start);
+
+        clock.start_track(44100, start);
+        let mut deadline = start;
+        for _ in 0..44100 / 1152 * 60 {
+            deadline = clock.advance(1152);
+        }
+        let expected = Duration::from_nanos(44100 / 1152 * 60 * 1152 * 1_000_000_000 / 44100);
+        assert_eq!(deadline - start, expected);
+
+        // The next track continues from where the previous one ended
+        clock.start_track(48000, start);
+        assert_eq!(
+            clock.advance(48000) - start,
+            expected + Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_clock_resyncs_when_behind() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new(start);
+        clock.start_track(48000, start);
+        clock.advance(48000);
+
+        let late = start + Duration::from_secs(10);
+        clock.start_track(48000, late);
+        assert_eq!(clock.advance(4800), late + Duration::from_millis(100));
+    }
+
+    fn drain(rx: &mut broadcast::Receiver<Timed<DfpwmPacket>>) -> Vec<Bytes> {
+        let first = rx.try_recv().unwrap().data;
+        assert_eq!(
+            first,
+            DfpwmPacket::TrackStart {
+                name: "Artist - Title".to_string()
+            }
+        );
+
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|packet| match packet.data {
+                DfpwmPacket::Audio(chunk) => chunk,
+                DfpwmPacket::TrackStart { .. } => panic!("unexpected track start"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dfpwm_channels_stay_aligned() {
+        let senders = DfpwmChannel::ALL.map(|_| broadcast::channel(1024).0);
+        let mut receivers = senders.clone().map(|s| s.subscribe());
+        let mut output = DfpwmOutput::new(senders);
+
+        // Different tones left and right, in MP3-sized packets at 44.1 kHz
+        let samples: Vec<f32> = (0..44100)
+            .flat_map(|i| {
+                let t = i as f32 / 44100.0;
+                [
+                    (2.0 * std::f32::consts::PI * 440.0 * t).sin() * 0.5,
+                    (2.0 * std::f32::consts::PI * 660.0 * t).sin() * 0.5,
+                ]
+            })
+            .collect();
+        output.start_track(&TrackParams {
+            name: "Artist - Title".to_string(),
+            loudness: None,
+        });
+        for packet in samples.chunks(1152 * 2) {
+            output.write(&AudioFrame {
+                mp3: None,
+                samples: packet,
+                channels: 2,
+                sample_rate: 44100,
+            });
+        }
+        output.finish_track();
+
+        let [mono, left, right] = receivers.each_mut().map(drain);
+        let sizes = |chunks: &[Bytes]| chunks.iter().map(|c| c.len()).collect::<Vec<_>>();
+        assert_eq!(sizes(&mono), sizes(&left));
+        assert_eq!(sizes(&left), sizes(&right));
+        assert_eq!(
+            left.iter().map(|c| c.len()).sum::<usize>(),
+            48000usize.div_ceil(8)
+        );
+        // Only the track tail may be shorter than a full chunk
+        assert!(left[..left.len() - 1]
+            .iter()
+            .all(|c| c.len() == DFPWM_CHUNK_SAMPLES / 8));
+        assert_ne!(left, right);
+    }
+
+    #[test]
+    fn test_silent_mp3_frames_decode_to_silence() {
+        use symphonia::core::{
+            audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions,
+            io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+        };
+
+        for &(rate, channels) in &[(44100u32, 2usize), (48000, 1), (22050, 2), (8000, 1)] {
+            let (frame, frame_samples) = silent_mp3_frame(rate, channels).unwrap();
+            let stream: Vec<u8> = frame.repeat(20);
+
+            let mss =
+                MediaSourceStream::new(Box::new(std::io::Cursor::new(stream)), Default::default());
+            let mut hint = Hint::new();
+            hint.with_extension("mp3");
+            let mut format = symphonia::default::get_probe()
+                .format(
+                    &hint,
+                    mss,
+                    &FormatOptions::default(),
+                    &MetadataOptions::default(),
+                )
+                .unwrap()
+                .format;
+            let params = format.default_track().unwrap().codec_params.clone();
+            let mut decoder = symphonia::default::get_codecs()
+                .make(&params, &DecoderOptions::default())
+                .unwrap();
+
+            let mut decoded_frames = 0;
+            while let Ok(packet) = format.next_packet() {
+                let decoded = decoder.decode(&packet).unwrap();
+                assert_eq!(decoded.spec().rate, rate);
+                assert_eq!(decoded.spec().channels.count(), channels);
+                let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+                buf.copy_interleaved_ref(decoded);
+                assert!(buf.samples().iter().all(|&s| s == 0.0));
+                decoded_frames += buf.samples().len() as u64 / channels as u64;
+            }
+            assert!(decoded_frames >= 19 * frame_samples, "{} Hz", rate);
+        }
+    }
+}
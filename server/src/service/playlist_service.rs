@@ -17,6 +17,9 @@ pub struct PlaylistItem {
     pub title: String,
     pub duration_sec: i32,
     pub download_url: String,
+    /// User who requested the track, `None` for auto-play.
+    #[serde(default)]
+    pub queued_by: Option<i32>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
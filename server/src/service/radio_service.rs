@@ -1,26 +1,36 @@
 use std::{fs, sync::Arc, time::Instant};
 
 use axum::body::Bytes;
-use tokio::{
-    io::AsyncReadExt,
-    sync::{broadcast, Notify, RwLock},
-};
+use tokio::sync::{broadcast, watch, Notify, RwLock};
 
 use crate::{
-    config::AppConfig,
+    config::{AppConfig, STATION_NAME},
     dto::response::{
         raido::GetCurrentTrackResponse,
-        websocket::{CurrentTrackData, WebSocketMessage},
+        websocket::{CurrentTrackData, PlaybackStateData, WebSocketMessage},
     },
     error::app_error::AppResult,
     infrastucture::repositories::track_repository::TrackRepository,
-    service::playlist_service::{PlaylistItem, PlaylistService},
+    service::{
+        audio_file::{AudioFile, AudioProperties},
+        ingest_service::IngestService,
+        live_stream::{LiveReceiver, Timed},
+        loudness::TrackLoudness,
+        playback::{
+            AudioFrame, AudioOutput, DfpwmChannel, DfpwmOutput, DfpwmPacket, Mp3Output,
+            PlaybackClock, Silence, TrackParams,
+        },
+        playlist_service::{PlaylistItem, PlaylistService},
+        station_policy_service::StationPolicyService,
+    },
 };
 
-const CHUNK_MS: u64 = 100;
 const BROADCAST_CAPACITY: usize = 256;
 const DFPWM_BROADCAST_CAPACITY: usize = 1024;
 const WS_EVENT_CAPACITY: usize = 100;
+/// Format of the silence sent while paused between tracks.
+const PAUSE_SAMPLE_RATE: u32 = 44100;
+const PAUSE_CHANNELS: usize = 2;
 
 enum NextTrack {
     Queued(PlaylistItem),
@@ -30,20 +40,27 @@ enum NextTrack {
 pub struct CurrentTrack {
     pub item: PlaylistItem,
     pub started_at: Instant,
-    pub file_size: u64,
+    pub properties: AudioProperties,
 }
 
 pub struct RadioState {
     pub current_track: Option<CurrentTrack>,
+    /// The broadcaster holds its position and sends silence while paused.
+    pub paused: bool,
 }
 
 pub struct RadioService {
-    sender: broadcast::Sender<Bytes>,
-    dfpwm_sender: broadcast::Sender<Bytes>,
+    sender: broadcast::Sender<Timed<Bytes>>,
+    /// One channel per `DfpwmChannel`, in `DfpwmChannel::ALL` order.
+    dfpwm_senders: [broadcast::Sender<Timed<DfpwmPacket>>; 3],
     ws_event_sender: broadcast::Sender<WebSocketMessage>,
     pub state: Arc<RwLock<RadioState>>,
+    /// Mirrors `RadioState::paused` so the broadcaster can wait for a resume.
+    paused_sender: watch::Sender<bool>,
     playlist_service: Arc<PlaylistService>,
     track_repository: Arc<TrackRepository>,
+    ingest_service: Arc<IngestService>,
+    station_policy_service: Arc<StationPolicyService>,
     config: Arc<AppConfig>,
     queue_notify: Arc<Notify>,
 }
@@ -52,21 +69,28 @@ impl RadioService {
     pub fn new(
         playlist_service: Arc<PlaylistService>,
         track_repository: Arc<TrackRepository>,
+        ingest_service: Arc<IngestService>,
+        station_policy_service: Arc<StationPolicyService>,
         config: Arc<AppConfig>,
         queue_notify: Arc<Notify>,
     ) -> Arc<Self> {
         let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
-        let (dfpwm_sender, _) = broadcast::channel(DFPWM_BROADCAST_CAPACITY);
+        let dfpwm_senders =
+            DfpwmChannel::ALL.map(|_| broadcast::channel(DFPWM_BROADCAST_CAPACITY).0);
         let (ws_event_sender, _) = broadcast::channel(WS_EVENT_CAPACITY);
         let service = Arc::new(RadioService {
             sender,
-            dfpwm_sender,
+            dfpwm_senders,
             ws_event_sender,
             state: Arc::new(RwLock::new(RadioState {
                 current_track: None,
+                paused: false,
             })),
+            paused_sender: watch::Sender::new(false),
             playlist_service,
             track_repository,
+            ingest_service,
+            station_policy_service,
             config,
             queue_notify,
         });
@@ -91,12 +115,26 @@ impl RadioService {
         service
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<Bytes> {
-        self.sender.subscribe()
+    /// `label` identifies the listener in logs.
+    pub fn subscribe(&self, label: String) -> LiveReceiver<Bytes> {
+        LiveReceiver::new(
+            self.sender.subscribe(),
+            self.config.stream_config.max_latency,
+            label,
+        )
     }
 
-    pub fn subscribe_dfpwm(&self) -> broadcast::Receiver<Bytes> {
-        self.dfpwm_sender.subscribe()
+    pub fn subscribe_dfpwm(
+        &self,
+        channel: DfpwmChannel,
+        label: String,
+    ) -> LiveReceiver<DfpwmPacket> {
+        LiveReceiver::new(
+            self.dfpwm_senders[channel.index()].subscribe(),
+            self.config.stream_config.max_latency,
+            label,
+        )
+        .keep_when_skipping(|packet| matches!(packet, DfpwmPacket::TrackStart { .. }))
     }
 
     pub fn subscribe_events(&self) -> broadcast::Receiver<WebSocketMessage> {
@@ -116,6 +154,35 @@ impl RadioService {
         Ok(CurrentTrackData { name })
     }
 
+    pub async fn get_playback_state_ws(&self) -> PlaybackStateData {
+        PlaybackStateData {
+            paused: self.state.read().await.paused,
+        }
+    }
+
+    /// Pause or resume the broadcast. Resuming continues the same track from
+    /// where it was paused.
+    pub async fn set_paused(&self, paused: bool) -> PlaybackStateData {
+        {
+            let mut state = self.state.write().await;
+            if state.paused == paused {
+                return PlaybackStateData { paused };
+            }
+            state.paused = paused;
+        }
+        self.paused_sender.send_replace(paused);
+        println!(
+            "[radio] Broadcast {}",
+            if paused { "paused" } else { "resumed" }
+        );
+
+        let data = PlaybackStateData { paused };
+        let _ = self
+            .ws_event_sender
+            .send(WebSocketMessage::PlaybackState(data.clone()));
+        data
+    }
+
     fn notify_current_track_changed(&self, name: Option<String>) {
         let msg = WebSocketMessage::CurrentTrack(CurrentTrackData { name });
         let _ = self.ws_event_sender.send(msg);
@@ -143,19 +210,21 @@ impl RadioService {
         }
     }
 
-    async fn download_track(&self, song_id: i32, owner_id: i32, url: String) -> AppResult<()> {
-        let file_name = format!("{}_{}.mp3", owner_id, song_id);
-        let file_path = format!("{}/{}", self.config.songs_config.songs_dir_path, file_name);
-        if !std::path::Path::new(&file_path).exists() {
-            let response = reqwest::get(url).await?;
-            let bytes = response.bytes().await?;
-            fs::write(file_path, bytes)?;
-        }
-        Ok(())
-    }
-
     async fn run_broadcaster(&self) {
+        let mut outputs: Vec<Box<dyn AudioOutput>> = vec![
+            Box::new(Mp3Output::new(self.sender.clone())),
+            Box::new(DfpwmOutput::new(self.dfpwm_senders.clone())),
+        ];
+        let mut clock = PlaybackClock::new(tokio::time::Instant::now());
+
         loop {
+            // Don't take the next track off the queue while paused
+            if *self.paused_sender.borrow() {
+                clock.start_track(PAUSE_SAMPLE_RATE, tokio::time::Instant::now());
+                self.hold_while_paused(&mut outputs, &mut clock, PAUSE_SAMPLE_RATE, PAUSE_CHANNELS)
+                    .await;
+            }
+
             let next = match self.next_track_item().await {
                 Ok(next) => next,
                 Err(_) => {
@@ -169,80 +238,72 @@ impl RadioService {
                 NextTrack::Auto(item) => (item, true),
             };
 
-            if let Err(e) = self
-                .download_track(item.song_id, item.owner_id, item.download_url.clone())
-                .await
-            {
-                eprintln!("[radio] Failed to download track {}: {}", item.id, e);
-                continue;
-            }
-
-            let file_name = format!("{}_{}.mp3", item.owner_id, item.song_id);
-            let file_path = format!("{}/{}", self.config.songs_config.songs_dir_path, file_name);
+            let track = match self.track_repository.find_track_by_id(item.id).await {
+                Ok(track) => track,
+                Err(e) => {
+                    eprintln!("[radio] Failed to load track {}: {}", item.id, e);
+                    continue;
+                }
+            };
 
-            let file_size: u64 = match tokio::fs::metadata(&file_path).await {
-                Ok(m) => m.len(),
+            let (file_path, properties) = match self.ingest_service.ensure_ingested(&track).await {
+                Ok(ingested) => ingested,
                 Err(e) => {
-                    eprintln!("[radio] Cannot stat {}: {}", file_path, e);
+                    eprintln!("[radio] Failed to ingest track {}: {}", item.id, e);
                     continue;
                 }
             };
 
-            let duration_secs = item.duration_sec as u64;
-            let bytes_per_sec = if duration_secs > 0 {
-                file_size / duration_secs
-            } else {
-                16_000
+            let params = TrackParams {
+                name: format!("{} - {}", item.artist, item.title),
+                // Analyzed at ingest, tracks without it play at unity gain
+                loudness: TrackLoudness::stored(&track),
             };
-            let chunk_size = ((bytes_per_sec * CHUNK_MS) / 1000).max(1024) as usize;
 
             {
                 let mut state = self.state.write().await;
                 state.current_track = Some(CurrentTrack {
                     item: item.clone(),
                     started_at: Instant::now(),
-                    file_size,
+                    properties,
                 });
             }
 
             // Notify WebSocket clients about track change
-            self.notify_current_track_changed(Some(format!("{} - {}", item.artist, item.title)));
+            self.notify_current_track_changed(Some(params.name.clone()));
+            if let Err(e) = self.station_policy_service.record_play(track.id).await {
+                eprintln!("[radio] Failed to record play of track {}: {}", track.id, e);
+            }
 
             if is_auto {
                 let notified = self.queue_notify.notified();
                 tokio::pin!(notified);
                 notified.as_mut().enable();
-
-                let mp3_path = file_path.clone();
-                let dfpwm_path = file_path.clone();
+                // A track queued while paused interrupts only after the resume
+                let notified = async {
+                    notified.await;
+                    self.wait_until_resumed().await;
+                };
 
                 tokio::select! {
-                    _ = async {
-                        let (mp3_result, dfpwm_result) = tokio::join!(
-                            self.stream_file(&mp3_path, chunk_size, bytes_per_sec),
-                            self.stream_file_dfpwm(&dfpwm_path, bytes_per_sec)
-                        );
-                        if let Err(e) = mp3_result {
-                            eprintln!("[radio] Error streaming MP3 {}: {}", mp3_path, e);
-                        }
-                        if let Err(e) = dfpwm_result {
-                            eprintln!("[radio] Error streaming DFPWM {}: {}", dfpwm_path, e);
+                    result = self.play_track(&file_path, &params, &mut outputs, &mut clock) => {
+                        if let Err(e) = result {
+                            eprintln!("[radio] Error streaming {}: {}", file_path, e);
                         }
-                    } => {}
+                    }
                     _ = notified => {
                     }
                 }
-            } else {
-                let (mp3_result, dfpwm_result) = tokio::join!(
-                    self.stream_file(&file_path, chunk_size, bytes_per_sec),
-                    self.stream_file_dfpwm(&file_path, bytes_per_sec)
-                );
-                if let Err(e) = mp3_result {
-                    eprintln!("[radio] Error streaming MP3 {}: {}", file_path, e);
-                }
-                if let Err(e) = dfpwm_result {
-                    eprintln!("[radio] Error streaming DFPWM {}: {}", file_path, e);
-                }
+            } else if let Err(e) = self
+                .play_track(&file_path, &params, &mut outputs, &mut clock)
+                .await
+            {
+                eprintln!("[radio] Error streaming {}: {}", file_path, e);
+            }
+
+            // Every output ends the track at the same clock tick
+            for output in outputs.iter_mut() {
+                output.finish_track();
             }
 
             {
@@ -254,305 +315,115 @@ impl RadioService {
         }
     }
 
-    async fn stream_file(
+    /// Decode the file once and feed every output from the same packets,
+    /// pacing them all against the shared playback clock.
+    async fn play_track(
         &self,
         file_path: &str,
-        chunk_size: usize,
-        bytes_per_sec: u64,
+        params: &TrackParams,
+        outputs: &mut [Box<dyn AudioOutput>],
+        clock: &mut PlaybackClock,
     ) -> AppResult<()> {
-        let mut file: tokio::fs::File = tokio::fs::File::open(file_path).await?;
-        let mut buf = vec![0u8; chunk_size];
-
-        loop {
-            let read_start = tokio::time::Instant::now();
-
-            let n = file.read(&mut buf).await?;
-            if n == 0 {
-                break;
-            }
-
-            let _ = self.sender.send(Bytes::copy_from_slice(&buf[..n]));
-
-            let chunk_duration_ms = if bytes_per_sec > 0 {
-                (n as u64 * 1000) / bytes_per_sec
-            } else {
-                CHUNK_MS
-            };
-            let sleep_dur = tokio::time::Duration::from_millis(chunk_duration_ms)
-                .saturating_sub(read_start.elapsed());
-            if !sleep_dur.is_zero() {
-                tokio::time::sleep(sleep_dur).await;
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn stream_file_dfpwm(&self, file_path: &str, _bytes_per_sec: u64) -> AppResult<()> {
         use symphonia::core::audio::SampleBuffer;
-        use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
-        use symphonia::core::formats::FormatOptions;
-        use symphonia::core::io::MediaSourceStream;
-        use symphonia::core::meta::MetadataOptions;
-        use symphonia::core::probe::Hint;
-
-        let file = std::fs::File::open(file_path)?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
-
-        let mut hint = Hint::new();
-        hint.with_extension("mp3");
-
-        let meta_opts: MetadataOptions = Default::default();
-        let fmt_opts: FormatOptions = Default::default();
 
-        let probed = symphonia::default::get_probe()
-            .format(&hint, mss, &fmt_opts, &meta_opts)
-            .map_err(|e| anyhow::anyhow!("Failed to probe format: {}", e))?;
+        let mut audio = AudioFile::open(file_path)?;
+        // Tracks in other formats from before uploads were limited to MP3
+        audio.require_mp3()?;
+        let mut sample_buf: Option<SampleBuffer<f32>> = None;
+        let mut clock_started = false;
 
-        let mut format = probed.format;
-        let track = format
-            .tracks()
-            .iter()
-            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-            .ok_or_else(|| anyhow::anyhow!("No supported audio tracks"))?;
-
-        let dec_opts: DecoderOptions = Default::default();
-        let mut decoder = symphonia::default::get_codecs()
-            .make(&track.codec_params, &dec_opts)
-            .map_err(|e| anyhow::anyhow!("Failed to create decoder: {}", e))?;
-
-        let track_id = track.id;
-        let mut dfpwm_encoder = crate::service::dfpwm::DfpwmEncoder::new();
-
-        // DFPWM requires 48kHz mono signed 8-bit PCM
-        let mut sample_buf: Option<SampleBuffer<i16>> = None;
-        let mut saved_spec = None;
-        let mut resampled_buf = Vec::new();
-
-        let chunk_size = 6144; // 128ms at 48kHz (кратно 8 для DFPWM)
-        let mut output_buffer = Vec::with_capacity(chunk_size / 8);
-
-        loop {
-            let packet = match format.next_packet() {
-                Ok(packet) => packet,
-                Err(_) => break,
-            };
-
-            if packet.track_id() != track_id {
+        while let Ok(packet) = audio.format.next_packet() {
+            if packet.track_id() != audio.track_id {
                 continue;
             }
 
-            let decoded = match decoder.decode(&packet) {
+            let decoded = match audio.decoder.decode(&packet) {
                 Ok(decoded) => decoded,
                 Err(_) => continue,
             };
 
-            if sample_buf.is_none() {
-                let spec = *decoded.spec();
-                saved_spec = Some(spec);
-                let duration = decoded.capacity() as u64;
-                sample_buf = Some(SampleBuffer::<i16>::new(duration, spec));
+            let spec = *decoded.spec();
+            let frames = decoded.frames() as u64;
+            if sample_buf
+                .as_ref()
+                .is_none_or(|buf| buf.capacity() < decoded.capacity() * spec.channels.count())
+            {
+                sample_buf = Some(SampleBuffer::<f32>::new(decoded.capacity() as u64, spec));
             }
+            let buf = sample_buf.as_mut().unwrap();
+            buf.copy_interleaved_ref(decoded);
 
-            if let Some(ref mut buf) = sample_buf {
-                buf.copy_interleaved_ref(decoded);
-
-                let samples = buf.samples();
-                let spec = saved_spec.as_ref().unwrap();
-
-                // Convert to mono if needed
-                let mono_samples: Vec<i16> = if spec.channels.count() > 1 {
-                    samples
-                        .chunks(spec.channels.count())
-                        .map(|chunk| {
-                            let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
-                            (sum / chunk.len() as i32) as i16
-                        })
-                        .collect()
-                } else {
-                    samples.to_vec()
-                };
-
-                // Resample to 48kHz if needed using cubic interpolation
-                let target_rate = 48000;
-                let source_rate = spec.rate;
-
-                let resampled = if source_rate != target_rate {
-                    let ratio = target_rate as f32 / source_rate as f32;
-                    let new_len = (mono_samples.len() as f32 * ratio) as usize;
-                    (0..new_len)
-                        .map(|i| {
-                            let pos = i as f32 / ratio;
-                            let idx = pos as usize;
-                            let frac = pos - idx as f32;
-
-                            // Cubic (Catmull-Rom) interpolation для лучшего качества
-                            let len = mono_samples.len();
-                            if idx == 0 || idx >= len - 2 {
-                                // Граничные случаи: линейная интерполяция
-                                if idx >= len - 1 {
-                                    mono_samples[len - 1]
-                                } else {
-                                    let a = mono_samples[idx] as f32;
-                                    let b = mono_samples[idx + 1] as f32;
-                                    (a + (b - a) * frac) as i16
-                                }
-                            } else {
-                                // Cubic interpolation
-                                let y0 = mono_samples[idx - 1] as f32;
-                                let y1 = mono_samples[idx] as f32;
-                                let y2 = mono_samples[idx + 1] as f32;
-                                let y3 = mono_samples[idx + 2] as f32;
-
-                                let a0 = y3 - y2 - y0 + y1;
-                                let a1 = y0 - y1 - a0;
-                                let a2 = y2 - y0;
-                                let a3 = y1;
-
-                                let result =
-                                    a0 * frac * frac * frac + a1 * frac * frac + a2 * frac + a3;
-                                result.clamp(-32768.0, 32767.0) as i16
-                            }
-                        })
-                        .collect()
-                } else {
-                    mono_samples
-                };
-
-                // Enhanced audio processing for DFPWM
-                // 1. Apply high-quality lowpass filter (20kHz cutoff) to prevent aliasing
-                use biquad::*;
-
-                let fs = 48000.0; // sampling frequency
-                let f0 = 18000.0; // cutoff at 18kHz (leaves margin before Nyquist)
-
-                // Create Butterworth lowpass filter (2nd order for smooth rolloff)
-                let coeffs = Coefficients::<f32>::from_params(
-                    Type::LowPass,
-                    fs.hz(),
-                    f0.hz(),
-                    Q_BUTTERWORTH_F32,
-                )
-                .unwrap();
-
-                let mut biquad1 = DirectForm2Transposed::<f32>::new(coeffs);
-                let mut biquad2 = DirectForm2Transposed::<f32>::new(coeffs); // 2 stages = 4th order
-
-                let mut filtered: Vec<i16> = Vec::with_capacity(resampled.len());
-                for &sample in &resampled {
-                    let s = sample as f32;
-                    let s1 = biquad1.run(s);
-                    let s2 = biquad2.run(s1);
-                    filtered.push(s2.clamp(-32768.0, 32767.0) as i16);
+            if !clock_started {
+                clock.start_track(spec.rate, tokio::time::Instant::now());
+                for output in outputs.iter_mut() {
+                    output.start_track(params);
                 }
+                clock_started = true;
+            }
 
-                // 2. Calculate RMS for better dynamic range analysis
-                let rms: f32 = {
-                    let sum_squares: f64 = filtered.iter().map(|&s| (s as f64).powi(2)).sum();
-                    ((sum_squares / filtered.len() as f64).sqrt()) as f32
-                };
-
-                // 3. Dynamic compression to fit DFPWM's limited dynamic range
-                // Target RMS around -18dBFS (about 8192 for 16-bit)
-                let target_rms = 8192.0;
-                let rms_gain = if rms > 100.0 { target_rms / rms } else { 1.0 };
-
-                // Apply soft-knee compression for peaks
-                let compressed: Vec<f32> = filtered
-                    .iter()
-                    .map(|&sample| {
-                        let s = sample as f32 * rms_gain;
-
-                        // Soft-knee compressor
-                        let threshold = 20000.0;
-                        let ratio = 3.0; // 3:1 compression
-
-                        if s.abs() > threshold {
-                            let excess = s.abs() - threshold;
-                            let compressed_excess = excess / ratio;
-                            let result = threshold + compressed_excess;
-                            result * s.signum()
-                        } else {
-                            s
-                        }
-                    })
-                    .collect();
-
-                // 4. Peak limiting - ensure we don't clip
-                let final_peak = compressed
-                    .iter()
-                    .map(|&s| s.abs())
-                    .max_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap_or(1.0);
-
-                let limiter_gain = if final_peak > 28000.0 {
-                    28000.0 / final_peak
-                } else {
-                    1.0
-                };
-
-                // 5. Pre-emphasis filter with optimized coefficient
-                // More gentle pre-emphasis (0.7 instead of 0.95) for DFPWM
-                let pre_emphasis_coef = 0.7;
-                let mut prev_sample: f32 = 0.0;
-
-                // 6. Triangular dithering with appropriate amplitude
-                let mut rng_state = 0x12345678u32;
-                let triangular_dither = |state: &mut u32| {
-                    *state = state.wrapping_mul(1103515245).wrapping_add(12345);
-                    let r1 = (((*state) >> 16) & 0xFFFF) as f32 / 65536.0;
-                    *state = state.wrapping_mul(1103515245).wrapping_add(12345);
-                    let r2 = (((*state) >> 16) & 0xFFFF) as f32 / 65536.0;
-                    r1 - r2
-                };
-
-                // Convert to signed 8-bit with all processing
-                for &sample in &compressed {
-                    // Apply limiter
-                    let limited = sample * limiter_gain;
-
-                    // Apply pre-emphasis filter
-                    let emphasized = limited - (prev_sample * pre_emphasis_coef);
-                    prev_sample = limited;
-
-                    // Add triangular dither (reduced to ~0.25 LSB of 8-bit range)
-                    let dither = triangular_dither(&mut rng_state) * 32.0;
-                    let with_dither = emphasized + dither;
-
-                    // Convert to 8-bit
-                    let sample_8bit = (with_dither / 256.0).clamp(-128.0, 127.0) as i8;
-                    resampled_buf.push(sample_8bit);
+            if *self.paused_sender.borrow() {
+                // The track starts over for the outputs after the pause
+                for output in outputs.iter_mut() {
+                    output.finish_track();
+                }
+                self.hold_while_paused(outputs, clock, spec.rate, spec.channels.count())
+                    .await;
+                for output in outputs.iter_mut() {
+                    output.start_track(params);
                 }
+            }
 
-                // Encode to DFPWM in chunks
-                while resampled_buf.len() >= chunk_size {
-                    let chunk: Vec<i8> = resampled_buf.drain(..chunk_size).collect();
+            let frame = AudioFrame {
+                mp3: Some(packet.buf()),
+                samples: buf.samples(),
+                channels: spec.channels.count(),
+                sample_rate: spec.rate,
+            };
+            for output in outputs.iter_mut() {
+                output.write(&frame);
+            }
 
-                    output_buffer.clear();
-                    dfpwm_encoder.encode(&chunk, &mut output_buffer);
+            tokio::time::sleep_until(clock.advance(frames)).await;
+        }
 
-                    let _ = self
-                        .dfpwm_sender
-                        .send(Bytes::copy_from_slice(&output_buffer));
+        Ok(())
+    }
 
-                    // Timing: chunk_size samples at 48kHz
-                    let duration_ms = (chunk_size * 1000) / 48000;
-                    tokio::time::sleep(tokio::time::Duration::from_millis(duration_ms as u64))
-                        .await;
-                }
-            }
+    /// Feed silence to every output, paced by the clock, until resumed. The
+    /// outputs get it as a track of its own, named after the station.
+    async fn hold_while_paused(
+        &self,
+        outputs: &mut [Box<dyn AudioOutput>],
+        clock: &mut PlaybackClock,
+        sample_rate: u32,
+        channels: usize,
+    ) {
+        if !*self.paused_sender.borrow() {
+            return;
         }
 
-        // Flush remaining samples
-        if !resampled_buf.is_empty() {
-            output_buffer.clear();
-            dfpwm_encoder.encode(&resampled_buf, &mut output_buffer);
-            let _ = self
-                .dfpwm_sender
-                .send(Bytes::copy_from_slice(&output_buffer));
+        let silence = Silence::new(sample_rate, channels);
+        let params = TrackParams {
+            name: STATION_NAME.to_string(),
+            loudness: None,
+        };
+        for output in outputs.iter_mut() {
+            output.start_track(&params);
         }
+        while *self.paused_sender.borrow() {
+            for output in outputs.iter_mut() {
+                output.write(&silence.frame());
+            }
+            tokio::time::sleep_until(clock.advance(silence.frames())).await;
+        }
+        for output in outputs.iter_mut() {
+            output.finish_track();
+        }
+    }
 
-        Ok(())
+    async fn wait_until_resumed(&self) {
+        let mut paused = self.paused_sender.subscribe();
+        let _ = paused.wait_for(|paused| !*paused).await;
     }
 
     async fn next_track_item(&self) -> AppResult<NextTrack> {
@@ -569,6 +440,7 @@ impl RadioService {
             title: track.title,
             duration_sec: track.duration_sec,
             download_url: track.download_url,
+            queued_by: None,
         };
         Ok(NextTrack::Auto(item))
     }
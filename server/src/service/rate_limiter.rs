@@ -0,0 +1,73 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::rate_limit::RateLimit,
+    error::app_error::AppResult,
+    infrastucture::cache::{client::Cache, keys::AppCacheKey},
+};
+
+/// Refill the bucket for the time since the last request, then take a token.
+/// Returns how many milliseconds to wait when the bucket is empty, 0 otherwise.
+const TOKEN_BUCKET_SCRIPT: &str = r"
+local burst = tonumber(ARGV[1])
+local period_ms = tonumber(ARGV[2])
+local now_ms = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or burst
+local updated_at = tonumber(bucket[2]) or now_ms
+local refill_per_ms = burst / period_ms
+
+tokens = math.min(burst, tokens + math.max(0, now_ms - updated_at) * refill_per_ms)
+local wait_ms = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait_ms = math.ceil((1 - tokens) / refill_per_ms)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now_ms)
+redis.call('PEXPIRE', KEYS[1], period_ms)
+return wait_ms
+";
+
+/// Token bucket rate limiter with the buckets stored in Redis, so the limits
+/// hold across restarts and instances.
+pub struct RateLimiter {
+    cache: Arc<Cache>,
+    script: redis::Script,
+}
+
+impl RateLimiter {
+    pub fn new(cache: Arc<Cache>) -> Self {
+        RateLimiter {
+            cache,
+            script: redis::Script::new(TOKEN_BUCKET_SCRIPT),
+        }
+    }
+
+    /// Take a token from the bucket of `subject` on `route`. Returns how long
+    /// to wait before retrying when the bucket is empty.
+    pub async fn acquire(
+        &self,
+        route: &str,
+        subject: &str,
+        limit: &RateLimit,
+    ) -> AppResult<Option<Duration>> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::RATE_LIMIT(route, subject).build_key();
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let wait_ms: u64 = self
+            .script
+            .key(key)
+            .arg(limit.burst)
+            .arg(limit.period.as_millis() as u64)
+            .arg(now_ms)
+            .invoke_async(&mut con)
+            .await?;
+        Ok((wait_ms > 0).then(|| Duration::from_millis(wait_ms)))
+    }
+}
@@ -0,0 +1,271 @@
+use std::f64::consts::PI;
+
+/// Number of filter phases stored in the table. Positions between two phases
+/// are linearly interpolated, so arbitrary rate ratios are supported.
+const PHASES: usize = 256;
+/// Zero crossings of the sinc on each side of the kernel center.
+const ZERO_CROSSINGS: f64 = 16.0;
+/// Cutoff relative to the lower of the two Nyquist frequencies.
+const ROLLOFF: f64 = 0.92;
+
+/// Streaming windowed-sinc resampler for mono `f32` samples.
+///
+/// Unlike per-packet interpolation it keeps the fractional read position and
+/// the input history between `process` calls, so packet boundaries are seamless.
+/// The kernel is band-limited to the lower Nyquist of the two rates, which acts
+/// as the anti-alias (downsampling) or anti-imaging (upsampling) filter.
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    taps: usize,
+    /// `(PHASES + 1) * taps` coefficients; phase `p` covers fractional offset `p / PHASES`.
+    filter: Vec<f32>,
+    /// Pending input samples, starting `taps / 2 - 1` samples before the read position.
+    history: Vec<f32>,
+    /// Integer part of the read position inside `history`.
+    position: usize,
+    /// Fractional part of the read position, in units of `1 / output_rate`.
+    fraction: u64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        assert!(
+            input_rate > 0 && output_rate > 0,
+            "sample rates must be positive"
+        );
+
+        let ratio = output_rate as f64 / input_rate as f64;
+        // Cutoff in cycles per input sample
+        let cutoff = 0.5 * ratio.min(1.0) * ROLLOFF;
+        let half_width = (ZERO_CROSSINGS / (2.0 * cutoff)).ceil() as usize;
+        let taps = half_width * 2;
+
+        let mut filter = Vec::with_capacity((PHASES + 1) * taps);
+        for phase in 0..=PHASES {
+            let frac = phase as f64 / PHASES as f64;
+            for tap in 0..taps {
+                // Distance between the output position and the input sample this tap weights
+                let t = frac + (half_width - 1) as f64 - tap as f64;
+                filter.push(Self::kernel(t, cutoff, half_width as f64) as f32);
+            }
+        }
+
+        Self {
+            input_rate,
+            output_rate,
+            taps,
+            filter,
+            history: vec![0.0; half_width - 1],
+            position: half_width - 1,
+            fraction: 0,
+        }
+    }
+
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    /// Resample `input` and append the produced samples to `output`.
+    ///
+    /// Output is delayed by `taps / 2` input samples of lookahead; call
+    /// `flush` at the end of the stream to drain it.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.history.extend_from_slice(input);
+        self.drain(output);
+    }
+
+    /// Pad the stream with silence and emit every output sample that belongs
+    /// to the input seen so far. The resampler is reset afterwards.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let half_width = self.taps / 2;
+        // Samples past this index are padding and must not produce output
+        let end = self.history.len();
+        self.history.resize(end + half_width, 0.0);
+
+        while self.position < end {
+            output.push(self.sample_at());
+            self.advance();
+        }
+
+        self.history.clear();
+        self.history.resize(half_width - 1, 0.0);
+        self.position = half_width - 1;
+        self.fraction = 0;
+    }
+
+    fn drain(&mut self, output: &mut Vec<f32>) {
+        let half_width = self.taps / 2;
+        while self.position + half_width < self.history.len() {
+            output.push(self.sample_at());
+            self.advance();
+        }
+
+        // Drop history that no future output can reach
+        let keep_from = self.position + 1 - half_width;
+        if keep_from > 0 {
+            self.history.drain(..keep_from);
+            self.position -= keep_from;
+        }
+    }
+
+    fn advance(&mut self) {
+        self.fraction += self.input_rate as u64;
+        let whole = self.fraction / self.output_rate as u64;
+        self.fraction %= self.output_rate as u64;
+        self.position += whole as usize;
+    }
+
+    fn sample_at(&self) -> f32 {
+        let half_width = self.taps / 2;
+        let start = self.position + 1 - half_width;
+        let window = &self.history[start..start + self.taps];
+
+        let phase_pos = self.fraction as f64 * PHASES as f64 / self.output_rate as f64;
+        let phase = (phase_pos as usize).min(PHASES - 1);
+        let blend = (phase_pos - phase as f64) as f32;
+
+        let lower = &self.filter[phase * self.taps..(phase + 1) * self.taps];
+        let upper = &self.filter[(phase + 1) * self.taps..(phase + 2) * self.taps];
+
+        let mut acc_lower = 0.0f32;
+        let mut acc_upper = 0.0f32;
+        for ((&s, &a), &b) in window.iter().zip(lower).zip(upper) {
+            acc_lower += s * a;
+            acc_upper += s * b;
+        }
+        acc_lower + (acc_upper - acc_lower) * blend
+    }
+
+    /// Blackman-windowed sinc with cutoff `cutoff` (cycles per input sample).
+    fn kernel(t: f64, cutoff: f64, half_width: f64) -> f64 {
+        if t.abs() >= half_width {
+            return 0.0;
+        }
+        let x = 2.0 * cutoff * t;
+        let sinc = if x.abs() < 1e-12 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        };
+        let n = (t / half_width + 1.0) * 0.5;
+        let window = 0.42 - 0.5 * (2.0 * PI * n).cos() + 0.08 * (4.0 * PI * n).cos();
+        2.0 * cutoff * sinc * window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(rate: u32, freq: f64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f64 / rate as f64).sin() as f32 * 0.5)
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        let sum: f64 = samples.iter().map(|&s| (s as f64).powi(2)).sum();
+        (sum / samples.len() as f64).sqrt()
+    }
+
+    fn resample_in_chunks(resampler: &mut Resampler, input: &[f32], chunk: usize) -> Vec<f32> {
+        let mut output = Vec::new();
+        for part in input.chunks(chunk) {
+            resampler.process(part, &mut output);
+        }
+        resampler.flush(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_passband_is_flat() {
+        for &(from, to) in &[(44100, 48000), (22050, 48000), (96000, 48000)] {
+            let mut resampler = Resampler::new(from, to);
+            let input = sine(from, 1000.0, from as usize);
+            let output = resample_in_chunks(&mut resampler, &input, 1152);
+
+            // Skip the filter ramp at both ends
+            let settled = &output[2000..output.len() - 2000];
+            let gain_db = 20.0 * (rms(settled) / rms(&input)).log10();
+            assert!(gain_db.abs() < 0.1, "{}->{}: gain {} dB", from, to, gain_db);
+        }
+    }
+
+    #[test]
+    fn test_stopband_is_attenuated() {
+        // 30 kHz would alias to 18 kHz at 48 kHz output
+        let mut resampler = Resampler::new(96000, 48000);
+        let input = sine(96000, 30000.0, 96000);
+        let output = resample_in_chunks(&mut resampler, &input, 1000);
+        let settled = &output[2000..output.len() - 2000];
+        let gain_db = 20.0 * (rms(settled) / rms(&input)).log10();
+        assert!(gain_db < -60.0, "aliased tone at {} dB", gain_db);
+    }
+
+    #[test]
+    fn test_matches_ideal_tone() {
+        // Output is phase-aligned with the input, so a resampled tone must match
+        // the same tone generated directly at the output rate
+        let mut resampler = Resampler::new(44100, 48000);
+        let input = sine(44100, 10000.0, 44100);
+        let output = resample_in_chunks(&mut resampler, &input, 1152);
+        let ideal = sine(48000, 10000.0, output.len());
+
+        let residual: Vec<f32> = output[2000..output.len() - 2000]
+            .iter()
+            .zip(&ideal[2000..ideal.len() - 2000])
+            .map(|(a, b)| a - b)
+            .collect();
+        let error_db = 20.0 * (rms(&residual) / rms(&input)).log10();
+        assert!(error_db < -60.0, "resampling error {} dB", error_db);
+    }
+
+    #[test]
+    fn test_chunking_does_not_change_output() {
+        let input = sine(44100, 440.0, 20000);
+        let whole = resample_in_chunks(&mut Resampler::new(44100, 48000), &input, input.len());
+        let small = resample_in_chunks(&mut Resampler::new(44100, 48000), &input, 37);
+
+        assert_eq!(whole.len(), small.len());
+        for (a, b) in whole.iter().zip(&small) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_sample_count_over_long_stream() {
+        for &(from, to) in &[
+            (44100u32, 48000u32),
+            (32000, 48000),
+            (48000, 48000),
+            (11025, 48000),
+        ] {
+            let mut resampler = Resampler::new(from, to);
+            let mut produced = 0usize;
+            let mut consumed = 0usize;
+            let mut output = Vec::new();
+            let chunk = vec![0.0f32; 1152];
+
+            // A minute of audio fed packet by packet
+            for _ in 0..(from as usize * 60 / chunk.len()) {
+                output.clear();
+                resampler.process(&chunk, &mut output);
+                consumed += chunk.len();
+                produced += output.len();
+
+                // Output never falls behind by more than the filter lookahead
+                let expected = consumed as u64 * to as u64 / from as u64;
+                let lag = expected - produced as u64;
+                assert!(lag <= resampler.taps as u64 * to as u64 / from as u64 + 1);
+            }
+
+            output.clear();
+            resampler.flush(&mut output);
+            produced += output.len();
+
+            let expected = (consumed as u64 * to as u64).div_ceil(from as u64);
+            assert_eq!(produced as u64, expected, "{}->{}", from, to);
+        }
+    }
+}
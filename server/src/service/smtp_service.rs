@@ -1,72 +1,117 @@
-use mail_send::{SmtpClientBuilder, mail_builder::MessageBuilder};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
 
 use crate::{
-    config::AppConfig,
-    error::app_error::{AppError, AppResult},
+    config::STATION_NAME,
+    error::app_error::AppResult,
+    infrastucture::{
+        database::models::NewOutboxEmail,
+        repositories::email_outbox_repository::EmailOutboxRepository,
+    },
+    service::email_template::{EmailRenderer, EmailTemplate, Locale},
 };
 
-#[derive(Clone, Debug)]
-pub struct EmailMessage<'a> {
-    pub to: &'a str,
-    pub subject: &'a str,
-    pub text_body: Option<&'a str>,
-    pub html_body: Option<&'a str>,
-}
-
-#[derive(Clone, Debug)]
+/// Renders transactional emails and queues them in the outbox. Delivery
+/// happens in the background, see
+/// [`EmailOutboxWorker`](crate::service::email_outbox::EmailOutboxWorker).
 pub struct SMTPService {
-    from: String,
-    host: String,
-    port: u16,
-    login: String,
-    password: String,
+    renderer: EmailRenderer,
+    outbox_repository: Arc<EmailOutboxRepository>,
+    notify: Arc<Notify>,
 }
 
 impl SMTPService {
-    pub fn new(config: &AppConfig) -> Self {
+    /// `notify` wakes the outbox worker up when an email is queued.
+    pub fn new(outbox_repository: Arc<EmailOutboxRepository>, notify: Arc<Notify>) -> Self {
         SMTPService {
-            from: config.smtp_config.from.clone(),
-            host: config.smtp_config.host.clone(),
-            port: config.smtp_config.port,
-            login: config.smtp_config.login.clone(),
-            password: config.smtp_config.password.clone(),
+            renderer: EmailRenderer::new(STATION_NAME),
+            outbox_repository,
+            notify,
         }
     }
 
-    async fn send_message(&self, message: EmailMessage<'_>) -> Result<(), mail_send::Error> {
-        let mut mail_builder = MessageBuilder::new()
-            .from(("", self.from.as_str()))
-            .to(("", message.to))
-            .subject(message.subject);
+    pub async fn send_registration_otp(
+        &self,
+        email: &str,
+        otp: u32,
+        locale: Locale,
+    ) -> AppResult<()> {
+        self.send_template(email, locale, EmailTemplate::SignUpOtp { otp })
+            .await
+    }
 
-        if let Some(text) = message.text_body {
-            mail_builder = mail_builder.text_body(text);
-        }
+    pub async fn send_restore_otp(&self, email: &str, otp: u32, locale: Locale) -> AppResult<()> {
+        self.send_template(email, locale, EmailTemplate::RestoreOtp { otp })
+            .await
+    }
 
-        if let Some(html) = message.html_body {
-            mail_builder = mail_builder.html_body(html);
-        }
+    pub async fn send_email_change_otp(
+        &self,
+        email: &str,
+        otp: u32,
+        locale: Locale,
+    ) -> AppResult<()> {
+        self.send_template(email, locale, EmailTemplate::EmailChangeOtp { otp })
+            .await
+    }
 
-        SmtpClientBuilder::new(self.host.as_str(), self.port)
-            .implicit_tls(false)
-            .credentials((self.login.as_str(), self.password.as_str()))
-            .connect()
-            .await?
-            .send(mail_builder)
-            .await?;
+    /// Sent to the old address when a change to `new_email` is requested.
+    pub async fn send_email_change_notice(
+        &self,
+        email: &str,
+        new_email: &str,
+        locale: Locale,
+    ) -> AppResult<()> {
+        self.send_template(
+            email,
+            locale,
+            EmailTemplate::EmailChangeNotice { new_email },
+        )
+        .await
+    }
 
-        Ok(())
+    /// Sent to the old address once the email was changed to `new_email`.
+    pub async fn send_email_changed(
+        &self,
+        email: &str,
+        new_email: &str,
+        locale: Locale,
+    ) -> AppResult<()> {
+        self.send_template(email, locale, EmailTemplate::EmailChanged { new_email })
+            .await
     }
 
-    pub async fn send_registration_otp(&self, email: &str, otp: u32) -> AppResult<()> {
-        self.send_message(EmailMessage {
-            subject: "Код для подтверждения регистрации",
-            to: email,
-            text_body: Some(&format!("Ваш код подтверждения: {}", otp)),
-            html_body: None,
-        })
-        .await
-        .map_err(|err| AppError::Internal(anyhow::anyhow!("Failed to send email: {}", err)))?;
+    pub async fn send_account_locked(
+        &self,
+        email: &str,
+        minutes: u32,
+        locale: Locale,
+    ) -> AppResult<()> {
+        self.send_template(email, locale, EmailTemplate::AccountLocked { minutes })
+            .await
+    }
+
+    async fn send_template(
+        &self,
+        to: &str,
+        locale: Locale,
+        template: EmailTemplate<'_>,
+    ) -> AppResult<()> {
+        let email = self.renderer.render(&template, locale);
+        self.outbox_repository
+            .enqueue(
+                &NewOutboxEmail {
+                    recipient: to.to_string(),
+                    template: template.name().to_string(),
+                    subject: email.subject,
+                    text_body: email.text,
+                    html_body: email.html,
+                },
+                template.lifetime_sec(),
+            )
+            .await?;
+        self.notify.notify_one();
         Ok(())
     }
 }
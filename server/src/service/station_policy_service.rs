@@ -0,0 +1,363 @@
+use std::sync::Arc;
+
+use redis::AsyncCommands;
+
+use crate::{
+    config::{station::StationPolicy, AppConfig},
+    dto::request::station::UpdateStationPolicyRequest,
+    error::app_error::{AppError, AppResult, ErrorCode},
+    infrastucture::cache::{client::Cache, keys::AppCacheKey},
+    service::{
+        playlist_service::{PlaylistItem, PlaylistService},
+        token_service::new_token_id,
+    },
+};
+
+/// Deletes the cooldown (`KEYS[1]`) only if it is still the one set with
+/// `ARGV[1]`.
+const RELEASE_COOLDOWN_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// A track request that passed the checks. It holds the cooldown of the
+/// user, which is given back if the request fails later on.
+pub struct RequestClaim {
+    user_id: i32,
+    /// Value of the cooldown key, `None` without a cooldown.
+    cooldown: Option<String>,
+}
+
+/// Enforces the station policy. The policy starts from the config and admins
+/// can override it at runtime; the override is kept in Redis.
+pub struct StationPolicyService {
+    cache: Arc<Cache>,
+    playlist_service: Arc<PlaylistService>,
+    config: Arc<AppConfig>,
+}
+
+impl StationPolicyService {
+    pub fn new(
+        cache: Arc<Cache>,
+        playlist_service: Arc<PlaylistService>,
+        config: Arc<AppConfig>,
+    ) -> Self {
+        StationPolicyService {
+            cache,
+            playlist_service,
+            config,
+        }
+    }
+
+    pub async fn get_policy(&self) -> AppResult<StationPolicy> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::STATION_POLICY().build_key();
+        let stored: Option<String> = con.get(&key).await?;
+        match stored.and_then(|json| serde_json::from_str(&json).ok()) {
+            Some(policy) => Ok(policy),
+            None => Ok(self.config.station_policy.clone()),
+        }
+    }
+
+    pub async fn update_policy(
+        &self,
+        data: UpdateStationPolicyRequest,
+    ) -> AppResult<StationPolicy> {
+        let mut policy = self.get_policy().await?;
+        if let Some(value) = data.max_track_duration_sec {
+            policy.max_track_duration_sec = value;
+        }
+        if let Some(value) = data.max_queued_per_user {
+            policy.max_queued_per_user = value;
+        }
+        if let Some(value) = data.user_cooldown_sec {
+            policy.user_cooldown_sec = value;
+        }
+        if let Some(value) = data.max_queue_length {
+            policy.max_queue_length = value;
+        }
+        if let Some(value) = data.no_repeat_window_sec {
+            policy.no_repeat_window_sec = value;
+        }
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::STATION_POLICY().build_key();
+        let _: () = con.set(&key, serde_json::to_string(&policy)?).await?;
+        Ok(policy)
+    }
+
+    /// Drop the runtime override and go back to the configured policy.
+    pub async fn reset_policy(&self) -> AppResult<StationPolicy> {
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::STATION_POLICY().build_key();
+        let _: () = con.del(&key).await?;
+        Ok(self.config.station_policy.clone())
+    }
+
+    /// Checks that can be made before the track is downloaded and starts the
+    /// cooldown of the user, so concurrent requests can't both pass. Finish
+    /// the request with [`Self::finish_request`]. `track_id` is the stored
+    /// track, if it was requested before.
+    pub async fn check_request(
+        &self,
+        policy: &StationPolicy,
+        user_id: i32,
+        track_id: Option<i32>,
+    ) -> AppResult<RequestClaim> {
+        let claim = self.claim_cooldown(policy, user_id).await?;
+        let checked = self
+            .check_queue_and_repeats(policy, user_id, track_id)
+            .await;
+        self.finish_request(&claim, checked).await?;
+        Ok(claim)
+    }
+
+    /// Passes `result` through. A failed request doesn't count towards the
+    /// cooldown, so it is given back.
+    pub async fn finish_request<T>(
+        &self,
+        claim: &RequestClaim,
+        result: AppResult<T>,
+    ) -> AppResult<T> {
+        if result.is_err() {
+            if let Err(e) = self.release_cooldown(claim).await {
+                eprintln!(
+                    "Failed to release the cooldown of user {}: {}",
+                    claim.user_id, e
+                );
+            }
+        }
+        result
+    }
+
+    async fn claim_cooldown(
+        &self,
+        policy: &StationPolicy,
+        user_id: i32,
+    ) -> AppResult<RequestClaim> {
+        if policy.user_cooldown_sec == 0 {
+            return Ok(RequestClaim {
+                user_id,
+                cooldown: None,
+            });
+        }
+
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::TRACK_REQUEST_COOLDOWN(user_id).build_key();
+        let token = new_token_id();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&token)
+            .arg("NX")
+            .arg("EX")
+            .arg(policy.user_cooldown_sec)
+            .query_async(&mut con)
+            .await?;
+        if claimed.is_none() {
+            let remaining: i64 = con.ttl(&key).await?;
+            return Err(AppError::TooManyRequests(
+                format!(
+                    "Next track can be requested in {} seconds",
+                    remaining.max(1)
+                ),
+                Some(ErrorCode::TrackRequestCooldown),
+            ));
+        }
+        Ok(RequestClaim {
+            user_id,
+            cooldown: Some(token),
+        })
+    }
+
+    async fn release_cooldown(&self, claim: &RequestClaim) -> AppResult<()> {
+        let Some(token) = &claim.cooldown else {
+            return Ok(());
+        };
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::TRACK_REQUEST_COOLDOWN(claim.user_id).build_key();
+        let _: i32 = redis::Script::new(RELEASE_COOLDOWN_SCRIPT)
+            .key(&key)
+            .arg(token)
+            .invoke_async(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    async fn check_queue_and_repeats(
+        &self,
+        policy: &StationPolicy,
+        user_id: i32,
+        track_id: Option<i32>,
+    ) -> AppResult<()> {
+        let playlist = self.playlist_service.get_playlist().await?;
+        check_queue(policy, &playlist.items, user_id, track_id)?;
+
+        if let (Some(track_id), true) = (track_id, policy.no_repeat_window_sec > 0) {
+            let mut con = self.cache.get_async_conn().await?;
+            let key = AppCacheKey::RECENT_TRACKS().build_key();
+            let played_at: Option<u64> = con.zscore(&key, track_id).await?;
+            if played_at.is_some_and(|at| now() < at + policy.no_repeat_window_sec as u64) {
+                return Err(recently_played());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remember that the track started playing, for the no-repeat window.
+    pub async fn record_play(&self, track_id: i32) -> AppResult<()> {
+        let policy = self.get_policy().await?;
+        let mut con = self.cache.get_async_conn().await?;
+        let key = AppCacheKey::RECENT_TRACKS().build_key();
+        let now = now();
+        let _: () = con.zadd(&key, track_id, now).await?;
+        let expired_before = now.saturating_sub(policy.no_repeat_window_sec as u64);
+        let _: () = con.zrembyscore(&key, 0, expired_before).await?;
+        Ok(())
+    }
+}
+
+pub fn check_duration(policy: &StationPolicy, duration_ms: u64) -> AppResult<()> {
+    if policy.max_track_duration_sec > 0
+        && duration_ms > policy.max_track_duration_sec as u64 * 1000
+    {
+        return Err(AppError::BadRequest(
+            format!(
+                "Tracks longer than {} seconds are not allowed",
+                policy.max_track_duration_sec
+            ),
+            Some(ErrorCode::TrackDurationLimit),
+        ));
+    }
+    Ok(())
+}
+
+fn check_queue(
+    policy: &StationPolicy,
+    queue: &[PlaylistItem],
+    user_id: i32,
+    track_id: Option<i32>,
+) -> AppResult<()> {
+    if policy.max_queue_length > 0 && queue.len() >= policy.max_queue_length as usize {
+        return Err(AppError::BadRequest(
+            "The queue is full".to_string(),
+            Some(ErrorCode::QueueFull),
+        ));
+    }
+
+    let queued_by_user = queue
+        .iter()
+        .filter(|item| item.queued_by == Some(user_id))
+        .count();
+    if policy.max_queued_per_user > 0 && queued_by_user >= policy.max_queued_per_user as usize {
+        return Err(AppError::BadRequest(
+            format!(
+                "You already have {} tracks in the queue",
+                policy.max_queued_per_user
+            ),
+            Some(ErrorCode::UserQueueLimit),
+        ));
+    }
+
+    if policy.no_repeat_window_sec > 0
+        && track_id.is_some_and(|id| queue.iter().any(|item| item.id == id))
+    {
+        return Err(recently_played());
+    }
+    Ok(())
+}
+
+fn recently_played() -> AppError {
+    AppError::BadRequest(
+        "This track was played or queued recently".to_string(),
+        Some(ErrorCode::TrackRecentlyPlayed),
+    )
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> StationPolicy {
+        StationPolicy {
+            max_track_duration_sec: 600,
+            max_queued_per_user: 2,
+            user_cooldown_sec: 30,
+            max_queue_length: 4,
+            no_repeat_window_sec: 3600,
+        }
+    }
+
+    fn item(id: i32, queued_by: Option<i32>) -> PlaylistItem {
+        PlaylistItem {
+            id,
+            song_id: id,
+            owner_id: 1,
+            artist: "Artist".to_string(),
+            title: "Title".to_string(),
+            duration_sec: 180,
+            download_url: String::new(),
+            queued_by,
+        }
+    }
+
+    fn code(result: AppResult<()>) -> Option<ErrorCode> {
+        match result {
+            Err(AppError::BadRequest(_, code)) => code,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_queue_limits() {
+        let queue = vec![item(1, Some(10)), item(2, Some(10)), item(3, None)];
+
+        assert!(check_queue(&policy(), &queue, 20, Some(4)).is_ok());
+        assert!(matches!(
+            code(check_queue(&policy(), &queue, 10, Some(4))),
+            Some(ErrorCode::UserQueueLimit)
+        ));
+        assert!(matches!(
+            code(check_queue(&policy(), &queue, 20, Some(3))),
+            Some(ErrorCode::TrackRecentlyPlayed)
+        ));
+
+        let full = vec![item(1, None), item(2, None), item(3, None), item(4, None)];
+        assert!(matches!(
+            code(check_queue(&policy(), &full, 20, None)),
+            Some(ErrorCode::QueueFull)
+        ));
+    }
+
+    #[test]
+    fn test_zero_disables_a_limit() {
+        let unlimited = StationPolicy {
+            max_track_duration_sec: 0,
+            max_queued_per_user: 0,
+            user_cooldown_sec: 0,
+            max_queue_length: 0,
+            no_repeat_window_sec: 0,
+        };
+        let queue: Vec<PlaylistItem> = (1..=10).map(|id| item(id, Some(10))).collect();
+
+        assert!(check_queue(&unlimited, &queue, 10, Some(1)).is_ok());
+        assert!(check_duration(&unlimited, 60 * 60 * 1000).is_ok());
+    }
+
+    #[test]
+    fn test_duration_limit() {
+        assert!(check_duration(&policy(), 600 * 1000).is_ok());
+        assert!(matches!(
+            code(check_duration(&policy(), 600 * 1000 + 1)),
+            Some(ErrorCode::TrackDurationLimit)
+        ));
+    }
+}
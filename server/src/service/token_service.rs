@@ -1,19 +1,22 @@
-use std::sync::Arc;
-
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, encode, get_current_timestamp};
+use jsonwebtoken::{
+    DecodingKey, EncodingKey, Header, Validation, encode, errors::ErrorKind, get_current_timestamp,
+};
+use rand::{Rng, distributions::Alphanumeric, thread_rng};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use subtle::ConstantTimeEq;
 
 use crate::{
-    config::AppConfig,
+    config::secret::SecretConfig,
     error::app_error::{AppError, AppResult, ErrorCode},
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     Access,
     Refresh,
     SignUp,
     Restore,
+    EmailChange,
 }
 
 pub trait Token {
@@ -22,12 +25,12 @@ pub trait Token {
 }
 
 pub struct TokenService {
-    config: Arc<AppConfig>,
+    secrets: SecretConfig,
 }
 
 impl TokenService {
-    pub fn new(config: Arc<AppConfig>) -> Self {
-        TokenService { config }
+    pub fn new(secrets: SecretConfig) -> Self {
+        TokenService { secrets }
     }
 
     pub fn create_jwt<T: Serialize + Token>(&self, data: T) -> AppResult<String> {
@@ -50,7 +53,24 @@ impl TokenService {
             token,
             &DecodingKey::from_secret(secret.as_bytes()),
             &Validation::default(),
-        )?;
+        )
+        .map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => {
+                AppError::Unauthorized("Токен устарел".to_string(), Some(ErrorCode::JWTExpired))
+            }
+            _ => AppError::Unauthorized(
+                "Недействительный токен".to_string(),
+                Some(ErrorCode::JWTInvalid),
+            ),
+        })?;
+        // Secrets may be shared between token types, so the signature alone
+        // doesn't tell a refresh token from an access token
+        if token_data.claims.token_type() != token_type {
+            return Err(AppError::Unauthorized(
+                "Недействительный токен".to_string(),
+                Some(ErrorCode::JWTInvalid),
+            ));
+        }
         if get_current_timestamp() > token_data.claims.exp() {
             return Err(AppError::Unauthorized(
                 "Токен устарел".to_string(),
@@ -71,10 +91,25 @@ impl TokenService {
 
     fn get_secret_by_token_type(&self, token_type: &TokenType) -> &str {
         match token_type {
-            TokenType::Access => &self.config.secret_config.access_secret,
-            TokenType::Refresh => &self.config.secret_config.refresh_secret,
-            TokenType::SignUp => &self.config.secret_config.sign_up_secret,
-            TokenType::Restore => &self.config.secret_config.restore_secret,
+            TokenType::Access => &self.secrets.access_secret,
+            TokenType::Refresh => &self.secrets.refresh_secret,
+            TokenType::SignUp => &self.secrets.sign_up_secret,
+            TokenType::Restore => &self.secrets.restore_secret,
+            TokenType::EmailChange => &self.secrets.email_change_secret,
         }
     }
 }
+
+/// Random id of a token, to bind server-side state to a single token.
+pub fn new_token_id() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect()
+}
+
+/// Compares two tokens in constant time.
+pub fn tokens_match(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
@@ -1,43 +1,42 @@
 use std::sync::Arc;
 
+use axum::body::Bytes;
+use redis::AsyncCommands;
 use tokio::sync::Notify;
 
 use crate::{
-    dto::{request::track::UserSelectTrackRequest, response::track::SearchTrackResponse},
-    error::app_error::{AppError, AppResult, ErrorCode},
-    infrastucture::{database::models::NewTrack, repositories::track_repository::TrackRepository},
-    service::playlist_service::{PlaylistItem, PlaylistService},
+    config::station::StationPolicy,
+    dto::{
+        request::track::UserSelectTrackRequest,
+        response::track::{SearchTrackResponse, UploadTrackResponse},
+    },
+    error::app_error::{AppError, AppResult},
+    infrastucture::{
+        cache::{client::Cache, keys::AppCacheKey},
+        database::models::{NewTrack, LOCAL_TRACK_SOURCE},
+        repositories::track_repository::TrackRepository,
+    },
+    service::{
+        audio_file::AudioProperties,
+        ingest_service::{IngestService, LOCAL_OWNER_ID},
+        loudness::TrackLoudness,
+        music_provider::{MusicProvider, MusicProviders, ProviderTrack},
+        playlist_service::{PlaylistItem, PlaylistService},
+        station_policy_service::{check_duration, StationPolicyService},
+    },
 };
 
-#[derive(serde::Deserialize, Debug, Clone)]
-struct TrackFromApi {
-    id: i32,
-    owner_id: i32,
-    artist: String,
-    title: String,
-    duration: i32,
-    date: i64,
-    url: String,
-}
-
-#[derive(serde::Deserialize, Debug)]
-struct SearchTrackInApiItems {
-    items: Vec<TrackFromApi>,
-}
-#[derive(serde::Deserialize, Debug)]
-struct SearchTrackByNameInApiResponse {
-    response: SearchTrackInApiItems,
-}
-
-#[derive(serde::Deserialize, Debug, Clone)]
-struct SearchTrackByIdInApiResponse {
-    response: Vec<TrackFromApi>,
-}
+pub const DEFAULT_SEARCH_COUNT: u32 = 5;
+const MAX_SEARCH_COUNT: u32 = 50;
+const SEARCH_CACHE_TTL_SEC: u64 = 10 * 60;
 
 pub struct TrackService {
     track_repository: Arc<TrackRepository>,
     playlist_service: Arc<PlaylistService>,
-    config: Arc<crate::config::AppConfig>,
+    ingest_service: Arc<IngestService>,
+    providers: Arc<MusicProviders>,
+    station_policy_service: Arc<StationPolicyService>,
+    cache: Arc<Cache>,
     queue_notify: Arc<Notify>,
 }
 
@@ -45,150 +44,262 @@ impl TrackService {
     pub fn new(
         track_repository: Arc<TrackRepository>,
         playlist_service: Arc<PlaylistService>,
-        config: Arc<crate::config::AppConfig>,
+        ingest_service: Arc<IngestService>,
+        providers: Arc<MusicProviders>,
+        station_policy_service: Arc<StationPolicyService>,
+        cache: Arc<Cache>,
         queue_notify: Arc<Notify>,
     ) -> Self {
         TrackService {
             track_repository,
             playlist_service,
-            config,
+            ingest_service,
+            providers,
+            station_policy_service,
+            cache,
             queue_notify,
         }
     }
 
-    pub async fn search_track(&self, search_value: String) -> AppResult<SearchTrackResponse> {
-        let search_response = self.search_track_by_name_in_api(&search_value).await?;
-
-        let tracks = search_response
-            .response
-            .items
-            .into_iter()
-            .map(|item| crate::dto::response::track::SearchTrackDTO {
-                song_id: item.id,
-                owner_id: item.owner_id,
-                duration: item.duration,
-                name: format!("{} - {}", item.artist, item.title),
-            })
-            .collect();
-
-        Ok(SearchTrackResponse { tracks })
-    }
-
-    pub async fn user_select_track(
+    pub async fn search_track(
         &self,
-        user_id: i32,
-        data: UserSelectTrackRequest,
-    ) -> AppResult<()> {
-        let tracks = self
-            .search_track_by_id_in_api(data.song_id, data.owner_id)
-            .await?;
-        if tracks.response.is_empty() {
+        search_value: String,
+        offset: u32,
+        count: u32,
+    ) -> AppResult<SearchTrackResponse> {
+        if count == 0 || count > MAX_SEARCH_COUNT {
             return Err(AppError::BadRequest(
-                "Track not found in music API".to_string(),
+                format!("count must be between 1 and {}", MAX_SEARCH_COUNT),
                 None,
             ));
         }
 
-        let track = tracks.response[0].clone();
-        if track.duration > 60 * 10 {
-            return Err(AppError::BadRequest(
-                "Track duration limit".to_string(),
-                Some(ErrorCode::TrackDurationLimit),
-            ));
+        let provider = self.providers.active();
+        let query = search_value.trim().to_lowercase();
+        let key = AppCacheKey::TRACK_SEARCH(provider.name(), &query, offset, count).build_key();
+        let mut con = self.cache.get_async_conn().await?;
+        let cached: Option<String> = con.get(&key).await?;
+        if let Some(response) = cached.and_then(|json| serde_json::from_str(&json).ok()) {
+            return Ok(response);
         }
 
-        let (track, _) = self
+        let page = provider.search(&query, offset, count).await?;
+        let response = SearchTrackResponse {
+            next_offset: page.next_offset(offset),
+            total: page.total,
+            tracks: page
+                .tracks
+                .into_iter()
+                .map(|item| crate::dto::response::track::SearchTrackDTO {
+                    song_id: item.song_id,
+                    owner_id: item.owner_id,
+                    duration: item.duration_sec,
+                    name: item.name(),
+                })
+                .collect(),
+        };
+
+        let json =
+            serde_json::to_string(&response).map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+        let _: () = con.set_ex(&key, json, SEARCH_CACHE_TTL_SEC).await?;
+        Ok(response)
+    }
+
+    pub async fn user_select_track(
+        &self,
+        user_id: i32,
+        data: UserSelectTrackRequest,
+    ) -> AppResult<()> {
+        let provider = self.providers.active();
+        let track = provider
+            .get_by_id(data.owner_id, data.song_id)
+            .await?
+            .ok_or_else(|| {
+                AppError::BadRequest("Track not found in music API".to_string(), None)
+            })?;
+
+        let policy = self.station_policy_service.get_policy().await?;
+        let stored = self
             .track_repository
-            .create_track_with_user_track(
-                &NewTrack {
-                    song_id: track.id,
-                    owner_id: track.owner_id,
-                    artist: track.artist,
-                    title: track.title,
-                    duration_sec: track.duration,
-                    download_url: track.url,
-                    likes_count: None,
-                    listens_count: None,
-                },
-                user_id,
+            .find_provider_track(provider.name(), track.owner_id, track.song_id)
+            .await?;
+        let claim = self
+            .station_policy_service
+            .check_request(&policy, user_id, stored.as_ref().map(|t| t.id))
+            .await?;
+        let stored_loudness = stored.as_ref().and_then(TrackLoudness::stored);
+        let result = self
+            .queue_provider_track(user_id, &policy, provider.as_ref(), track, stored_loudness)
+            .await;
+        self.station_policy_service
+            .finish_request(&claim, result)
+            .await
+    }
+
+    async fn queue_provider_track(
+        &self,
+        user_id: i32,
+        policy: &StationPolicy,
+        provider: &dyn MusicProvider,
+        track: ProviderTrack,
+        stored_loudness: Option<TrackLoudness>,
+    ) -> AppResult<()> {
+        // The duration reported by the API is not reliable, check the real file
+        let file = self
+            .ingest_service
+            .download(
+                provider.name(),
+                track.song_id,
+                track.owner_id,
+                &track.download_url,
             )
             .await?;
-        self.playlist_service
-            .add_new_track(PlaylistItem {
-                id: track.id,
+        let properties = match self.ingest_service.probe(&file.path).await {
+            Ok(properties) => properties,
+            Err(e) => {
+                if file.is_new {
+                    let _ = tokio::fs::remove_file(&file.path).await;
+                }
+                return Err(e);
+            }
+        };
+        if let Err(e) = check_duration(policy, properties.duration_ms) {
+            if file.is_new {
+                let _ = tokio::fs::remove_file(&file.path).await;
+            }
+            return Err(e);
+        }
+        let loudness = match stored_loudness {
+            Some(loudness) if !file.is_new => Some(loudness),
+            _ => self.ingest_service.analyze_loudness(&file.path).await,
+        };
+
+        self.add_track(
+            user_id,
+            NewTrack {
                 song_id: track.song_id,
                 owner_id: track.owner_id,
                 artist: track.artist,
                 title: track.title,
-                duration_sec: track.duration_sec,
+                duration_sec: properties.duration_sec(),
                 download_url: track.download_url,
-            })
-            .await?;
-        self.queue_notify.notify_one();
+                likes_count: None,
+                listens_count: None,
+                source: provider.name().to_string(),
+            },
+            &properties,
+            loudness.as_ref(),
+        )
+        .await?;
         Ok(())
     }
 
-    async fn search_track_by_name_in_api(
+    /// Add an uploaded audio file to the library and queue it. Title and
+    /// artist come from the file tags, falling back to the file name.
+    pub async fn upload_track(
         &self,
-        search_value: &str,
-    ) -> AppResult<SearchTrackByNameInApiResponse> {
-        let url = reqwest::Url::parse_with_params(
-            format!("{}.search", self.config.music_api_url.as_str()).as_str(),
-            &[
-                ("q", search_value),
-                ("access_token", self.config.music_api_token.as_str()),
-                ("count", "5"),
-                ("v", "5.131"),
-            ],
-        )
-        .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
-
-        let client = reqwest::Client::new();
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
-
-        let search_response = response
-            .json::<SearchTrackByNameInApiResponse>()
+        user_id: i32,
+        file_name: &str,
+        data: Bytes,
+    ) -> AppResult<UploadTrackResponse> {
+        let policy = self.station_policy_service.get_policy().await?;
+        // Uploads are always new tracks, so there is nothing to repeat
+        let claim = self
+            .station_policy_service
+            .check_request(&policy, user_id, None)
+            .await?;
+        let result = self
+            .queue_uploaded_track(user_id, &policy, file_name, data)
+            .await;
+        self.station_policy_service
+            .finish_request(&claim, result)
             .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
-
-        Ok(search_response)
     }
 
-    async fn search_track_by_id_in_api(
+    async fn queue_uploaded_track(
         &self,
-        song_id_val: i32,
-        owner_id_val: i32,
-    ) -> AppResult<SearchTrackByIdInApiResponse> {
-        let url = reqwest::Url::parse_with_params(
-            format!("{}.getById", self.config.music_api_url.as_str()).as_str(),
-            &[
-                (
-                    "audios",
-                    format!("{}_{}", owner_id_val, song_id_val).as_str(),
-                ),
-                ("access_token", self.config.music_api_token.as_str()),
-                ("v", "5.131"),
-            ],
-        )
-        .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
+        user_id: i32,
+        policy: &StationPolicy,
+        file_name: &str,
+        data: Bytes,
+    ) -> AppResult<UploadTrackResponse> {
+        let upload = self.ingest_service.store_upload(file_name, data).await?;
+        if let Err(e) = check_duration(policy, upload.properties.duration_ms) {
+            let _ = tokio::fs::remove_file(&upload.path).await;
+            return Err(e);
+        }
+        let loudness = self.ingest_service.analyze_loudness(&upload.path).await;
 
-        println!("{}", url);
-        let client = reqwest::Client::new();
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
+        let title = upload.tags.title.unwrap_or_else(|| {
+            std::path::Path::new(file_name)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(file_name)
+                .to_string()
+        });
+        let artist = upload
+            .tags
+            .artist
+            .unwrap_or_else(|| "Unknown artist".to_string());
 
-        let search_response = response
-            .json::<SearchTrackByIdInApiResponse>()
-            .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))?;
+        let track = self
+            .add_track(
+                user_id,
+                NewTrack {
+                    song_id: upload.song_id,
+                    owner_id: LOCAL_OWNER_ID,
+                    artist,
+                    title,
+                    duration_sec: upload.properties.duration_sec(),
+                    download_url: upload.file_name,
+                    likes_count: None,
+                    listens_count: None,
+                    source: LOCAL_TRACK_SOURCE.to_string(),
+                },
+                &upload.properties,
+                loudness.as_ref(),
+            )
+            .await?;
+
+        Ok(UploadTrackResponse {
+            id: track.id,
+            name: format!("{} - {}", track.artist, track.title),
+            duration: track.duration_sec,
+        })
+    }
 
-        Ok(search_response)
+    /// Store the track for the user and put it in the queue.
+    async fn add_track(
+        &self,
+        user_id: i32,
+        new_track: NewTrack,
+        properties: &AudioProperties,
+        loudness: Option<&TrackLoudness>,
+    ) -> AppResult<PlaylistItem> {
+        let (track, _) = self
+            .track_repository
+            .create_track_with_user_track(&new_track, user_id)
+            .await?;
+        self.track_repository
+            .update_audio_properties(track.id, properties)
+            .await?;
+        if let Some(loudness) = loudness {
+            self.track_repository
+                .update_loudness(track.id, loudness)
+                .await?;
+        }
+        let item = PlaylistItem {
+            id: track.id,
+            song_id: track.song_id,
+            owner_id: track.owner_id,
+            artist: track.artist,
+            title: track.title,
+            duration_sec: properties.duration_sec(),
+            download_url: track.download_url,
+            queued_by: Some(user_id),
+        };
+        self.playlist_service.add_new_track(item.clone()).await?;
+        self.queue_notify.notify_one();
+        Ok(item)
     }
 }
@@ -0,0 +1,32 @@
+-- Installer for the radio client. Run it with:
+--   wget run {{install_url}}
+
+local clientUrl = {{client_url}}
+local program = {{program}}
+local startup = {{startup}}
+
+if not http then
+    error("The http API is disabled on this server", 0)
+end
+
+print("Downloading " .. clientUrl)
+local response, err = http.get(clientUrl)
+if not response then
+    error("Download failed: " .. tostring(err), 0)
+end
+local code = response.readAll()
+response.close()
+
+local file = fs.open(program, "w")
+file.write(code)
+file.close()
+print("Saved to " .. program)
+
+if startup then
+    local file = fs.open("startup.lua", "w")
+    file.write(("shell.run(%q)\n"):format(program))
+    file.close()
+    print("Added to startup.lua")
+end
+
+print(("Installed. Run '%s' to start listening."):format((program:gsub("%.lua$", ""))))
@@ -0,0 +1,86 @@
+-- Radio client for ComputerCraft speakers.
+-- Downloaded from {{client_url}}
+
+local streamUrl = {{stream_url}}
+local volume = {{volume}}
+-- Peripheral name of the speaker to use, or nil for every attached speaker
+local speakerName = {{speaker}}
+
+local minReconnectDelay = 1
+local maxReconnectDelay = 30
+
+local dfpwm = require("cc.audio.dfpwm")
+
+local function findSpeakers()
+    if speakerName then
+        local speaker = peripheral.wrap(speakerName)
+        if not speaker or peripheral.getType(speaker) ~= "speaker" then
+            error("No speaker named " .. speakerName, 0)
+        end
+        return { speaker }
+    end
+
+    local speakers = { peripheral.find("speaker") }
+    if #speakers == 0 then
+        error("No speaker attached", 0)
+    end
+    return speakers
+end
+
+local function play(speakers, buffer)
+    local jobs = {}
+    for i, speaker in ipairs(speakers) do
+        jobs[i] = function()
+            while not speaker.playAudio(buffer, volume) do
+                os.pullEvent("speaker_audio_empty")
+            end
+        end
+    end
+    parallel.waitForAll(table.unpack(jobs))
+end
+
+local function listen(speakers, onConnected)
+    local ws, err = http.websocket(streamUrl)
+    if not ws then
+        return err
+    end
+    print("Connected to " .. streamUrl)
+    onConnected()
+
+    -- A fresh decoder per connection, the stream restarts from an unknown state
+    local decoder = dfpwm.make_decoder()
+    while true do
+        local ok, message, binary = pcall(ws.receive)
+        if not ok then
+            pcall(ws.close)
+            return message
+        end
+        if message == nil then
+            return "connection closed"
+        end
+        if binary == false then
+            local event = textutils.unserialiseJSON(message)
+            if type(event) == "table" and event.type == "track_start" then
+                -- The server restarts its encoder on every track
+                decoder = dfpwm.make_decoder()
+                print("Now playing: " .. tostring(event.data and event.data.name))
+            end
+        else
+            play(speakers, decoder(message))
+        end
+    end
+end
+
+local speakers = findSpeakers()
+local reconnectDelay = minReconnectDelay
+while true do
+    local reason = listen(speakers, function()
+        reconnectDelay = minReconnectDelay
+    end)
+    for _, speaker in ipairs(speakers) do
+        speaker.stop()
+    end
+    print(("Disconnected (%s), retrying in %ds"):format(tostring(reason), reconnectDelay))
+    sleep(reconnectDelay)
+    reconnectDelay = math.min(reconnectDelay * 2, maxReconnectDelay)
+end